@@ -0,0 +1,192 @@
+//! Cheap one tile structures for holding the forest back. They're placed in lines by dragging.
+//!
+//! **LOGIC:**
+//!
+//! The [BarrierMap] keeps track of which tiles are blocked, and by what:
+//!
+//! - nothing germinates on a barrier tile, same as under any other building (see
+//!   [OccupancyGrid](crate::game::occupancy::OccupancyGrid))
+//! - [Wall]s stop drifting seeds dead
+//! - fire doesn't spread across a [Firebreak]
+//!
+//! Barriers switched off with [BuildingState::Inactive] don't stop anything until they're
+//! switched back on.
+//!
+//! Aggressive species don't take kindly to being fenced in, and slowly tear down any barrier
+//! they grow up against.
+
+use std::collections::HashMap;
+
+use bevy::prelude::*;
+use bevy_ecs_tilemap::prelude::TilePos;
+
+use crate::{
+    creeps::{lifecycle::Sapling, TreeSpecies},
+    game::{
+        map::{MapData, TILE_SIZE},
+        power::{AddBuilding, RemoveBuilding},
+        resources::{Cost, ResourceType},
+    },
+    AppState, Health, Tree,
+};
+
+use super::{BuildingDefinition, BuildingState};
+
+/// Every *this* many seconds aggressive trees take a chunk out of the barriers around them
+const OVERRUN_TICK_RATE: f32 = 1.0;
+/// Aggressive trees this close to a barrier damage it
+const OVERRUN_RANGE: f32 = 48.0;
+/// Damage each aggressive tree in range does to a barrier each tick
+const OVERRUN_DPT: u32 = 2;
+
+pub struct BarrierPlugin;
+impl Plugin for BarrierPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(OverrunTick(Timer::from_seconds(
+            OVERRUN_TICK_RATE,
+            TimerMode::Repeating,
+        )))
+        .init_resource::<BarrierMap>()
+        .add_systems(OnEnter(AppState::Gameplay), reset_barriers)
+        .add_systems(
+            Update,
+            (overrun_barriers).run_if(in_state(AppState::Gameplay)),
+        )
+        // after barriers spawned or destroyed this frame actually exist or are gone
+        .add_systems(
+            PostUpdate,
+            (track_barriers).run_if(in_state(AppState::Gameplay)),
+        );
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BarrierKind {
+    Wall,
+    Firebreak,
+}
+
+#[derive(Component)]
+pub struct Barrier(pub BarrierKind);
+
+#[derive(Default)]
+pub struct Wall;
+impl BuildingDefinition for Wall {
+    const SPRITE_PATH: &'static str = "textures/rock.png";
+    const BASE_HEALTH: u32 = 200;
+    const COST: Cost = &[
+        (ResourceType::CorporationPoints, 10),
+        (ResourceType::Wood, 4),
+    ];
+    const BUILD_TIME: u32 = 1;
+    const UPKEEP: u32 = 0;
+    const NAME: &'static str = "Wall";
+    const DESCRIPTION: &'static str = "Stops seeds drifting through and nothing grows on it.
+        Drag to build a line of them.";
+
+    fn add_extra_components(commands: &mut Commands, ent_id: Entity) {
+        commands.entity(ent_id).insert(Barrier(BarrierKind::Wall));
+    }
+}
+
+#[derive(Default)]
+pub struct Firebreak;
+impl BuildingDefinition for Firebreak {
+    const SPRITE_PATH: &'static str = "textures/rock.png";
+    const BASE_HEALTH: u32 = 80;
+    const COST: Cost = &[(ResourceType::CorporationPoints, 15)];
+    const BUILD_TIME: u32 = 1;
+    const UPKEEP: u32 = 0;
+    const NAME: &'static str = "Firebreak";
+    const DESCRIPTION: &'static str = "A strip of scorched earth fire can't cross, and
+        nothing grows on it. Drag to build a line of them.";
+
+    fn add_extra_components(commands: &mut Commands, ent_id: Entity) {
+        commands.entity(ent_id).insert((
+            Barrier(BarrierKind::Firebreak),
+            Sprite {
+                color: Color::rgb(0.45, 0.3, 0.2),
+                ..default()
+            },
+        ));
+    }
+}
+
+/// Every tile with a barrier on it
+#[derive(Resource, Default)]
+pub struct BarrierMap {
+    tiles: HashMap<TilePos, BarrierKind>,
+}
+
+impl BarrierMap {
+    pub fn blocks_seeds(&self, map: &MapData, pos: Vec2) -> bool {
+        map.world_to_tile(pos).map_or(false, |tile| {
+            self.tiles.get(&tile) == Some(&BarrierKind::Wall)
+        })
+    }
+
+    /// Whether there's a [Firebreak] anywhere on the way from `from` to `to`
+    pub fn blocks_fire(&self, map: &MapData, from: Vec2, to: Vec2) -> bool {
+        let steps = (from.distance(to) / (TILE_SIZE / 2.0)).ceil().max(1.0) as usize;
+        (0..=steps).any(|step| {
+            map.world_to_tile(from.lerp(to, step as f32 / steps as f32))
+                .map_or(false, |tile| {
+                    self.tiles.get(&tile) == Some(&BarrierKind::Firebreak)
+                })
+        })
+    }
+}
+
+#[derive(Resource)]
+struct OverrunTick(Timer);
+
+fn reset_barriers(mut barriers: ResMut<BarrierMap>) {
+    *barriers = BarrierMap::default();
+}
+
+fn track_barriers(
+    tower_spawned: EventReader<AddBuilding>,
+    tower_removed: EventReader<RemoveBuilding>,
+    state_changed: Query<(), Changed<BuildingState>>,
+    mut barriers: ResMut<BarrierMap>,
+    map: Res<MapData>,
+    q_barriers: Query<(&Barrier, &Transform, Option<&BuildingState>)>,
+) {
+    if tower_spawned.is_empty() && tower_removed.is_empty() && state_changed.is_empty() {
+        return;
+    }
+
+    barriers.tiles = q_barriers
+        .iter()
+        .filter(|(_, _, state)| *state != Some(&BuildingState::Inactive))
+        .filter_map(|(barrier, tf, _)| {
+            map.building_tile(tf.translation.truncate())
+                .map(|tile| (tile, barrier.0))
+        })
+        .collect();
+}
+
+/// Grown up aggressive trees wear down the barriers next to them
+fn overrun_barriers(
+    mut timer: ResMut<OverrunTick>,
+    time: Res<Time>,
+    mut q_barriers: Query<(&mut Health, &Transform), With<Barrier>>,
+    q_trees: Query<(&TreeSpecies, &Transform), (With<Tree>, Without<Sapling>)>,
+) {
+    if !timer.0.tick(time.delta()).just_finished() {
+        return;
+    }
+
+    q_barriers.iter_mut().for_each(|(mut hp, barrier_tf)| {
+        let attackers = q_trees
+            .iter()
+            .filter(|(species, tree_tf)| {
+                species.is_aggressive()
+                    && tree_tf.translation.distance(barrier_tf.translation) < OVERRUN_RANGE
+            })
+            .count() as u32;
+        if attackers > 0 {
+            hp.deduct(attackers * OVERRUN_DPT);
+        }
+    });
+}
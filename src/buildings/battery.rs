@@ -0,0 +1,45 @@
+//! Stores whatever power the network doesn't need and gives it back when demand spikes, see
+//! [PowerGrid](crate::game::power::PowerGrid).
+
+use bevy::prelude::*;
+
+use crate::game::{
+    power::RequiresPower,
+    resources::{Cost, ResourceType},
+};
+
+use super::BuildingDefinition;
+
+/// Most power a single battery holds
+pub const BATTERY_CAPACITY: f32 = 300.0;
+
+#[derive(Component, Default)]
+pub struct Battery {
+    pub charge: f32,
+}
+
+impl Battery {
+    pub fn room(&self) -> f32 {
+        BATTERY_CAPACITY - self.charge
+    }
+}
+
+impl BuildingDefinition for Battery {
+    const SPRITE_PATH: &'static str = "textures/tower_single.png";
+    const BASE_HEALTH: u32 = 80;
+    const COST: Cost = &[
+        (ResourceType::CorporationPoints, 250),
+        (ResourceType::Minerals, 20),
+    ];
+    const BUILD_TIME: u32 = 5;
+    const UPKEEP: u32 = 1;
+    const NAME: &'static str = "Battery";
+    const DESCRIPTION: &'static str = "Soaks up spare power and lets it back out when the
+        network is short.";
+
+    fn add_extra_components(commands: &mut Commands, ent_id: Entity) {
+        commands
+            .entity(ent_id)
+            .insert((RequiresPower, Battery::default()));
+    }
+}
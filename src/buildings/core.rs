@@ -1,10 +1,19 @@
 use crate::{
-    game::power::{IsPowered, SupplyRadius},
-    AnimationIndices, AnimationTimer, AppState, Health, Teardown, BUILDING_Z, SHADER_Z,
+    game::{
+        hp_bars::HpBarUISettings,
+        power::{IsPowered, PowerOutput, SupplyRadius},
+        resources::Cost,
+    },
+    AnimationIndices, AnimationTimer, AppState, Health, MaxHealth, Teardown, BUILDING_Z, SHADER_Z,
 };
 use bevy::{prelude::*, sprite::MaterialMesh2dBundle};
 
-use super::{twr_custom_mats::TowerRadiusMaterial, Building, BuildingDefinition};
+use super::{
+    twr_custom_mats::TowerRadiusMaterial, Building, BuildingDefinition, Footprint, Upkeep,
+};
+
+/// Power the core makes every second, enough for a handful of buildings
+const CORE_OUTPUT: f32 = 20.0;
 
 /// Core building that the player starts with
 #[derive(Component, Default)]
@@ -12,24 +21,33 @@ pub struct TheCore;
 impl BuildingDefinition for TheCore {
     const SPRITE_PATH: &'static str = "textures/core-spritesheet.png";
     const BASE_HEALTH: u32 = 1000;
-    const COST: u32 = 0; // Core is free since you start with it
+    const COST: Cost = &[]; // Core is free since you start with it
     const BUILD_TIME: u32 = 0;
+    const UPKEEP: u32 = 1;
     const NAME: &'static str = "Core";
-    const DESCRIPTION: &'static str = "";
+    const DESCRIPTION: &'static str = "The Company's outpost. Powers everything close by, and
+        losing it gets you fired.";
+    const FOOTPRINT: Footprint = Footprint {
+        width: 2,
+        height: 2,
+    };
 
     fn add_extra_components(commands: &mut Commands, ent_id: Entity) {
-        commands
-            .entity(ent_id)
-            .insert((IsPowered, SupplyRadius(550.0), TheCore));
+        commands.entity(ent_id).insert((
+            IsPowered,
+            SupplyRadius(550.0),
+            PowerOutput(CORE_OUTPUT),
+            TheCore,
+        ));
     }
 }
 
 impl TheCore {
     pub fn custom_spawn(
         commands: &mut Commands,
-        mut texture_atlases: ResMut<Assets<TextureAtlas>>,
-        mut meshes: ResMut<Assets<Mesh>>,
-        mut materials: ResMut<Assets<TowerRadiusMaterial>>,
+        texture_atlases: &mut Assets<TextureAtlas>,
+        meshes: &mut Assets<Mesh>,
+        materials: &mut Assets<TowerRadiusMaterial>,
         asset_server: Res<AssetServer>,
         pos: Vec2,
     ) -> Entity {
@@ -55,6 +73,13 @@ impl TheCore {
                 Building,
                 Teardown,
                 Health(Self::BASE_HEALTH),
+                MaxHealth(Self::BASE_HEALTH),
+                HpBarUISettings {
+                    max: Self::BASE_HEALTH,
+                    offset: None,
+                },
+                Upkeep(Self::UPKEEP),
+                Self::FOOTPRINT,
                 SpriteSheetBundle {
                     texture_atlas: texture_atlas_handle,
                     sprite: TextureAtlasSprite::new(core_anim.first),
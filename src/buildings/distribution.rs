@@ -1,7 +1,14 @@
-use super::{twr_custom_mats::TowerRadiusMaterial, Building, BuildingDefinition, BuildingState};
+use super::{
+    twr_custom_mats::TowerRadiusMaterial, Building, BuildingDefinition, BuildingState, Upkeep,
+};
 use crate::{
-    game::power::{IsPowered, RequiresPower, SupplyRadius},
-    AnimationIndices, AnimationTimer, AppState, Health, Teardown, BUILDING_Z, SHADER_Z,
+    game::{
+        hp_bars::HpBarUISettings,
+        logistics::Stockpile,
+        power::{IsPowered, RequiresPower, SupplyRadius},
+        resources::{Cost, ResourceType},
+    },
+    AnimationIndices, AnimationTimer, AppState, Health, MaxHealth, Teardown, BUILDING_Z, SHADER_Z,
 };
 use bevy::{
     prelude::*,
@@ -9,6 +16,9 @@ use bevy::{
 };
 use std::path::Path;
 
+/// How far a [DistributionTower] reaches before research
+pub const DISTRIBUTION_RADIUS: f32 = 300.0;
+
 const BUILDING_ANIM: AnimationIndices = AnimationIndices { first: 1, last: 11 };
 const ACTIVE_ANIM: AnimationIndices = AnimationIndices {
     first: 12,
@@ -21,22 +31,28 @@ pub struct DistributionTower;
 impl BuildingDefinition for DistributionTower {
     const SPRITE_PATH: &'static str = "textures/tower.png";
     const BASE_HEALTH: u32 = 100;
-    const COST: u32 = 300;
+    const COST: Cost = &[
+        (ResourceType::CorporationPoints, 250),
+        (ResourceType::Wood, 20),
+    ];
     const BUILD_TIME: u32 = 6;
+    const UPKEEP: u32 = 1;
     const NAME: &'static str = "Distribution Tower";
     const DESCRIPTION: &'static str = "";
 
     fn add_extra_components(commands: &mut Commands, ent_id: Entity) {
-        commands.entity(ent_id).insert(SupplyRadius(300.0));
+        commands
+            .entity(ent_id)
+            .insert((SupplyRadius(DISTRIBUTION_RADIUS), Stockpile::default()));
     }
 }
 
 impl DistributionTower {
     pub fn custom_spawn(
         commands: &mut Commands,
-        mut texture_atlases: ResMut<Assets<TextureAtlas>>,
-        mut meshes: ResMut<Assets<Mesh>>,
-        mut materials: ResMut<Assets<TowerRadiusMaterial>>,
+        texture_atlases: &mut Assets<TextureAtlas>,
+        meshes: &mut Assets<Mesh>,
+        materials: &mut Assets<TowerRadiusMaterial>,
         asset_server: Res<AssetServer>,
         pos: Vec2,
     ) -> Entity {
@@ -47,7 +63,9 @@ impl DistributionTower {
 
         let radius_display = commands
             .spawn(MaterialMesh2dBundle {
-                mesh: meshes.add(shape::Circle::new(300.).into()).into(),
+                mesh: meshes
+                    .add(shape::Circle::new(DISTRIBUTION_RADIUS).into())
+                    .into(),
                 material: materials.add(TowerRadiusMaterial {
                     color: Color::rgb(0.9453125, 0.0, 0.0625),
                 }),
@@ -64,6 +82,13 @@ impl DistributionTower {
                 RequiresPower,
                 Teardown,
                 Health(Self::BASE_HEALTH),
+                MaxHealth(Self::BASE_HEALTH),
+                HpBarUISettings {
+                    max: Self::BASE_HEALTH,
+                    offset: None,
+                },
+                Upkeep(Self::UPKEEP),
+                Self::FOOTPRINT,
                 SpriteSheetBundle {
                     texture_atlas: texture_atlas_handle,
                     sprite: TextureAtlasSprite::new(BUILDING_ANIM.first),
@@ -1,65 +1,210 @@
-//! A building that drains **all** trees in close proximity.
+//! A building that drains the trees in close proximity.
 //!
 //! **LOGIC:**
 //!
 //! On [SpawnCreep] we recalculate the trees that a particular [DrainTower] can harvest from.
 //! This is because we don't want to do a N^2 every frame.
 //!
-//! Every frame we try and drain a tree
+//! Every drain tick each tower picks its [Targeting] from those trees, depending on its
+//! [TargetMode] and how many targets it's allowed at once:
+//!
+//! - [TargetMode::All] spreads its drain over every tree in range
+//! - the other modes put the full drain into the nearest, most rewarding or weakest trees
+//!
+//! Every point of health drained turns into a share of the tree's yield straight away, which
+//! goes into that tower's [Stockpile]. The tower that finishes a tree off gets a kill bonus on
+//! top, see [TreeDrained].
+//!
+//! Neighbours can speed a tower up or get it more out of every tree, see [Synergy].
 
 use crate::{
-    creeps::{CreepDie, SpawnCreep},
-    game::power::{update_powered_unpowered, AddBuilding, IsPowered, RequiresPower},
-    AnimationIndices, AnimationTimer, AppState, Health, Teardown, Tree, BUILDING_Z, SHADER_Z,
+    creeps::{CreepDie, Drained, SpawnCreep, TreeSpecies, TREE_HEALTH},
+    game::{
+        hp_bars::HpBarUISettings,
+        logistics::Stockpile,
+        power::{
+            update_powered_unpowered, AddBuilding, IsPowered, PowerDraw, PowerGrid, PowerPriority,
+            RequiresPower,
+        },
+        research::Research,
+        resources::{Cost, ResourceType},
+    },
+    AnimationIndices, AnimationTimer, AppState, CorpoPoints, Health, MaxHealth, Targeting,
+    Teardown, Tree, BUILDING_Z, SHADER_Z,
 };
 use bevy::{
     prelude::*,
     sprite::{Material2dPlugin, MaterialMesh2dBundle},
     tasks::IoTaskPool,
+    utils::HashMap,
 };
 
-use super::{twr_custom_mats::TowerRadiusMaterial, Building, BuildingDefinition};
+use super::{
+    synergy::Synergy, twr_custom_mats::TowerRadiusMaterial, Building, BuildingDefinition, Upkeep,
+};
 
 /// Drain damage applied to trees per tick of [GlobalDrainTick]
 const DRAIN_DPT: u32 = 2;
 /// Every *this* many seconds trees get drained
 const DRAIN_TICK_RATE: f32 = 0.15;
+/// Trees a freshly built tower goes after at once
+const DEFAULT_MAX_TARGETS: usize = 8;
+/// The most trees a single tower can be set to go after at once
+pub const MAX_TARGETS_LIMIT: usize = 24;
+const BEAM_COLOR: Color = Color::rgba(1.0, 0.6, 0.1, 0.6);
 
 #[derive(Component)]
 pub struct DrainRadius(f32);
 
+/// Fired when a [DrainTower] drains the last of a tree's health, so the kill bonus ends up in
+/// that tower's [Stockpile]
+#[derive(Event)]
+pub struct TreeDrained {
+    pub tower: Entity,
+    pub tree: Entity,
+}
+
 #[derive(Resource)]
 struct GlobalDrainTick(Timer);
 
-#[derive(Component, Default)]
+/// How a [DrainTower] picks which trees in range to drain
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum TargetMode {
+    /// Any trees in range, up to `max_targets`, with the drain of `max_targets` trees split
+    /// between however many there are
+    #[default]
+    All,
+    Nearest,
+    /// Trees worth the most Corporation Points
+    HighestReward,
+    /// Trees closest to dying
+    LowestHealth,
+}
+
+impl TargetMode {
+    pub fn next(&self) -> Self {
+        match self {
+            TargetMode::All => TargetMode::Nearest,
+            TargetMode::Nearest => TargetMode::HighestReward,
+            TargetMode::HighestReward => TargetMode::LowestHealth,
+            TargetMode::LowestHealth => TargetMode::All,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            TargetMode::All => "All in range",
+            TargetMode::Nearest => "Nearest first",
+            TargetMode::HighestReward => "Highest reward first",
+            TargetMode::LowestHealth => "Lowest health first",
+        }
+    }
+}
+
+#[derive(Component)]
 pub struct DrainTower {
     trees_in_proximity: Vec<Entity>,
+    /// Everything this tower has put in its [Stockpile] so far
+    pub earned: HashMap<ResourceType, u32>,
+    /// Health drained from trees so far
+    pub hp_drained: u32,
+    /// Trees this tower finished off
+    pub kills: u32,
+    /// Fractions of resources drained but not yet worth a whole unit
+    carry: HashMap<ResourceType, f32>,
+    /// Fraction of a point of health per tree not yet drained, same deal
+    damage_carry: f32,
+    pub mode: TargetMode,
+    /// The most trees it drains at the same time, see [MAX_TARGETS_LIMIT]. When draining
+    /// everything in range it's also how many trees' worth of drain gets split between them.
+    pub max_targets: usize,
+}
+
+impl Default for DrainTower {
+    fn default() -> Self {
+        Self {
+            trees_in_proximity: vec![],
+            earned: HashMap::default(),
+            hp_drained: 0,
+            kills: 0,
+            carry: HashMap::default(),
+            damage_carry: 0.0,
+            mode: TargetMode::default(),
+            max_targets: DEFAULT_MAX_TARGETS,
+        }
+    }
 }
 
 impl BuildingDefinition for DrainTower {
     const SPRITE_PATH: &'static str = "textures/sucky-uppy.png";
     const BASE_HEALTH: u32 = 100;
-    const COST: u32 = 200;
+    const COST: Cost = &[(ResourceType::CorporationPoints, 200)];
     const BUILD_TIME: u32 = 5;
+    const UPKEEP: u32 = 3;
     const NAME: &'static str = "Drain Tower";
     const DESCRIPTION: &'static str = "The Drain Tower slowly drains the health of
         closeby towers. Upgrading it increases it's active radius.";
 
     fn add_extra_components(commands: &mut Commands, ent_id: Entity) {
-        commands
-            .entity(ent_id)
-            .insert((RequiresPower, DrainRadius(400.0), DrainTower::default()));
+        commands.entity(ent_id).insert((
+            RequiresPower,
+            PowerDraw(3.0),
+            DrainRadius(400.0),
+            DrainTower::default(),
+            Targeting::default(),
+            Stockpile::default(),
+        ));
     }
 }
 
 impl DrainTower {
+    /// How many trees this tower is draining from
+    pub fn trees_in_range(&self) -> usize {
+        self.trees_in_proximity.len()
+    }
+
+    /// Counts `amount` towards what this tower has earned
+    pub fn credit(&mut self, resource: ResourceType, amount: u32) {
+        if amount > 0 {
+            *self.earned.entry(resource).or_default() += amount;
+        }
+    }
+
+    /// Adds the share of a tree's yield that `drained` health is worth, plus `bonus` of it on
+    /// top, handing back the whole units that are ready to be stored
+    fn harvest(
+        &mut self,
+        species: &TreeSpecies,
+        corpo_pts: &CorpoPoints,
+        drained: u32,
+        bonus: f32,
+    ) -> Vec<(ResourceType, u32)> {
+        let share = drained as f32 / TREE_HEALTH as f32 * (1.0 + bonus);
+        species
+            .base_yield()
+            .iter()
+            .copied()
+            .chain(std::iter::once((
+                ResourceType::CorporationPoints,
+                corpo_pts.0,
+            )))
+            .filter_map(|(resource, amount)| {
+                let carry = self.carry.entry(resource).or_default();
+                *carry += amount as f32 * share;
+                let whole = carry.floor();
+                *carry -= whole;
+                (whole >= 1.0).then_some((resource, whole as u32))
+            })
+            .collect()
+    }
+
     /// Manually implement the spawning since it uses SpriteSheetBundle and animatin components
     /// compared to the simple [MinimalBuilding] Bundle
     pub fn custom_spawn(
         commands: &mut Commands,
-        mut texture_atlases: ResMut<Assets<TextureAtlas>>,
-        mut meshes: ResMut<Assets<Mesh>>,
-        mut materials: ResMut<Assets<TowerRadiusMaterial>>,
+        texture_atlases: &mut Assets<TextureAtlas>,
+        meshes: &mut Assets<Mesh>,
+        materials: &mut Assets<TowerRadiusMaterial>,
         asset_server: Res<AssetServer>,
         pos: Vec2,
     ) -> Entity {
@@ -86,6 +231,13 @@ impl DrainTower {
                 Building,
                 Teardown,
                 Health(Self::BASE_HEALTH),
+                MaxHealth(Self::BASE_HEALTH),
+                HpBarUISettings {
+                    max: Self::BASE_HEALTH,
+                    offset: None,
+                },
+                Upkeep(Self::UPKEEP),
+                Self::FOOTPRINT,
                 SpriteSheetBundle {
                     texture_atlas: texture_atlas_handle,
                     sprite: TextureAtlasSprite::new(succ_anim.first),
@@ -112,9 +264,15 @@ impl Plugin for DrainTowerPlugin {
         )))
         .add_event::<AddBuilding>()
         .add_event::<SpawnCreep>()
+        .add_event::<TreeDrained>()
         .add_systems(
             Update,
-            (animate_sprite, drain_closeby_trees, debug_drain_radii)
+            (
+                animate_sprite,
+                (tick_drain, pick_drain_targets, drain_closeby_trees).chain(),
+                draw_drain_beams,
+                debug_drain_radii,
+            )
                 .run_if(in_state(AppState::Gameplay)),
         )
         .add_systems(
@@ -156,33 +314,161 @@ fn calculate_drainees(
     }
 }
 
-/// Each [DrainTower] slowly drains the health of every close tree
+fn tick_drain(mut timer: ResMut<GlobalDrainTick>, time: Res<Time>) {
+    timer.0.tick(time.delta());
+}
+
+/// Each [DrainTower] picks which of the trees in its proximity to go after this tick, see
+/// [TargetMode]
+fn pick_drain_targets(
+    timer: Res<GlobalDrainTick>,
+    mut q_towers: Query<(&DrainTower, &mut Targeting, &Transform)>,
+    q_trees: Query<(&Health, &CorpoPoints, &Transform), With<Tree>>,
+) {
+    if !timer.0.just_finished() {
+        return;
+    }
+
+    q_towers
+        .iter_mut()
+        .for_each(|(tower, mut targeting, tower_tf)| {
+            let mut candidates: Vec<_> = tower
+                .trees_in_proximity
+                .iter()
+                .filter_map(|ent| q_trees.get(*ent).ok().map(|tree| (*ent, tree)))
+                .filter(|(_, (hp, _, _))| hp.0 > 0)
+                .collect();
+            match tower.mode {
+                TargetMode::All => {}
+                TargetMode::Nearest => candidates.sort_by(|(_, (_, _, a)), (_, (_, _, b))| {
+                    let a = a.translation.distance(tower_tf.translation);
+                    let b = b.translation.distance(tower_tf.translation);
+                    a.total_cmp(&b)
+                }),
+                TargetMode::HighestReward => {
+                    candidates.sort_by_key(|(_, (_, cp, _))| std::cmp::Reverse(cp.0))
+                }
+                TargetMode::LowestHealth => candidates.sort_by_key(|(_, (hp, _, _))| hp.0),
+            }
+            targeting.0 = candidates
+                .into_iter()
+                .take(tower.max_targets)
+                .map(|(ent, _)| ent)
+                .collect();
+        });
+}
+
+/// Each [DrainTower] slowly drains the health of the trees it's targeting. Towers draining
+/// every tree in range split what they'd do to `max_targets` trees between all of them. Drain
+/// that doesn't add up to a whole point of health yet is carried over to the next tick.
+/// [Synergy] bonuses make a tower drain faster, and get more out of what it drains.
 ///
 /// NOTE: we don't care about what happens after a tree dies (hp -> zero) here, that should be
 /// handled in other systems.
 fn drain_closeby_trees(
-    mut timer: ResMut<GlobalDrainTick>,
-    mut q_trees: Query<(Entity, &mut Health), With<Tree>>,
-    q_towers: Query<&DrainTower, With<IsPowered>>,
-    time: Res<Time>,
+    timer: Res<GlobalDrainTick>,
+    mut q_trees: Query<
+        (
+            Entity,
+            &mut Health,
+            &mut Drained,
+            &TreeSpecies,
+            &CorpoPoints,
+        ),
+        With<Tree>,
+    >,
+    mut q_towers: Query<
+        (
+            Entity,
+            &mut DrainTower,
+            &Targeting,
+            &mut Stockpile,
+            Option<&Synergy>,
+            Option<&PowerPriority>,
+        ),
+        With<IsPowered>,
+    >,
+    research: Res<Research>,
+    grid: Res<PowerGrid>,
+    mut drained: EventWriter<TreeDrained>,
 ) {
-    if timer.0.tick(time.delta()).just_finished() {
+    if timer.0.just_finished() {
         trace!(
             "Drain Tick: draining from {} Drain Towers",
             q_towers.iter().len()
         );
-        // for each tower check all the trees in proximity and deduct hp from them.
-        q_towers.iter().for_each(|tower: &DrainTower| {
-            tower.trees_in_proximity.iter().for_each(|ent| {
-                if let Ok((_tree, mut hp)) = q_trees.get_mut(*ent) {
-                    hp.deduct(DRAIN_DPT);
-                    debug!("hp rem: {}", hp.0);
+        let damage = DRAIN_DPT as f32 * research.drain_multiplier;
+        // for each tower check all the trees it's targeting and deduct hp from them.
+        q_towers.iter_mut().for_each(
+            |(tower_ent, mut tower, targeting, mut stockpile, synergy, priority)| {
+                let (speed, yield_bonus) = synergy.map_or((0.0, 0.0), |synergy| {
+                    (synergy.drain_speed, synergy.drain_yield)
+                });
+                let damage = damage * grid.satisfaction_of(priority) * (1.0 + speed);
+                let per_tree = match tower.mode {
+                    TargetMode::All if !targeting.0.is_empty() => {
+                        damage * tower.max_targets as f32 / targeting.0.len() as f32
+                    }
+                    _ => damage,
+                };
+                let per_tree = per_tree + tower.damage_carry;
+                tower.damage_carry = per_tree.fract();
+                let per_tree = per_tree as u32;
+                if per_tree == 0 {
+                    return;
                 }
-            });
-        });
+                targeting.0.iter().for_each(|ent| {
+                    if let Ok((tree, mut hp, mut tree_drained, species, corpo_pts)) =
+                        q_trees.get_mut(*ent)
+                    {
+                        if hp.0 == 0 {
+                            // someone else got to it first
+                            return;
+                        }
+                        let dealt = per_tree.min(hp.0);
+                        hp.deduct(dealt);
+                        debug!("hp rem: {}", hp.0);
+                        tree_drained.0 += dealt;
+                        tower.hp_drained += dealt;
+                        tower
+                            .harvest(species, corpo_pts, dealt, yield_bonus)
+                            .into_iter()
+                            .for_each(|(resource, amount)| {
+                                let stored = stockpile.store(resource, amount);
+                                tower.credit(resource, stored);
+                            });
+                        if hp.0 == 0 {
+                            drained.send(TreeDrained {
+                                tower: tower_ent,
+                                tree,
+                            });
+                        }
+                    }
+                });
+            },
+        );
     }
 }
 
+/// A beam from every powered [DrainTower] to each tree it's draining
+fn draw_drain_beams(
+    mut gizmos: Gizmos,
+    q_towers: Query<(&Targeting, &Transform), (With<DrainTower>, With<IsPowered>)>,
+    q_trees: Query<&Transform, With<Tree>>,
+) {
+    q_towers.iter().for_each(|(targeting, tower_tf)| {
+        targeting.0.iter().for_each(|target| {
+            if let Ok(tree_tf) = q_trees.get(*target) {
+                gizmos.line_2d(
+                    tower_tf.translation.truncate(),
+                    tree_tf.translation.truncate(),
+                    BEAM_COLOR,
+                );
+            }
+        });
+    });
+}
+
 fn animate_sprite(
     time: Res<Time>,
     mut query: Query<
@@ -0,0 +1,44 @@
+//! Burns harvested biomass to put more power into the network, see
+//! [PowerGrid](crate::game::power::PowerGrid).
+
+use bevy::prelude::*;
+
+use crate::game::{
+    power::{PowerOutput, RequiresPower},
+    resources::{Cost, ResourceType},
+};
+
+use super::BuildingDefinition;
+
+/// Power a burning generator makes every second
+pub const GENERATOR_OUTPUT: f32 = 15.0;
+/// Biomass a generator burns every second it's running
+pub const GENERATOR_FUEL: u32 = 1;
+
+#[derive(Component, Default)]
+pub struct Generator {
+    /// Whether it burnt fuel last tick, generators only fire up when the network needs them
+    pub burning: bool,
+}
+
+impl BuildingDefinition for Generator {
+    const SPRITE_PATH: &'static str = "textures/radar.png";
+    const BASE_HEALTH: u32 = 120;
+    const COST: Cost = &[
+        (ResourceType::CorporationPoints, 300),
+        (ResourceType::Wood, 40),
+    ];
+    const BUILD_TIME: u32 = 6;
+    const UPKEEP: u32 = 2;
+    const NAME: &'static str = "Generator";
+    const DESCRIPTION: &'static str = "Burns biomass from the warehouse to power the network
+        when the core can't keep up.";
+
+    fn add_extra_components(commands: &mut Commands, ent_id: Entity) {
+        commands.entity(ent_id).insert((
+            RequiresPower,
+            PowerOutput(GENERATOR_OUTPUT),
+            Generator::default(),
+        ));
+    }
+}
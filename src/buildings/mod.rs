@@ -1,16 +1,38 @@
-use bevy::prelude::*;
+use bevy::{prelude::*, utils::HashMap};
+use serde::{Deserialize, Serialize};
 use std::path::Path;
 
 use self::twr_custom_mats::TowerRadiusMaterial;
 use self::{
+    barrier::{Firebreak, Wall},
+    battery::Battery,
     distribution::DistributionTower,
     drain::{DrainTower, DrainTowerPlugin},
+    generator::Generator,
     radar::RadarTower,
+    repair::RepairDrone,
+    roboport::Roboport,
+    turrets::{Flamethrower, HerbicideSprayer, SeedInterceptor},
 };
 use crate::global_systems::eargasm::AudioRequest;
-use crate::{game::hp_bars::HpBarUISettings, Health};
-use crate::{Teardown, BUILDING_Z};
+use crate::global_systems::fade_transition::{transition_to, TransitionState};
+use crate::{
+    game::{
+        hp_bars::HpBarUISettings,
+        hud::is_cursor_over_ui,
+        keybinds::FloraCommand,
+        map::{CurrentTileHover, TILE_SIZE},
+        occupancy::OccupancyGrid,
+        placement::PlacementState,
+        power::RemoveBuilding,
+        resources::{Cost, Inventory},
+    },
+    Health, MaxHealth,
+};
+use crate::{AppState, GameOver, Teardown, BUILDING_Z};
 
+pub mod barrier;
+pub mod battery;
 pub mod core;
 pub mod distribution;
 pub mod twr_custom_mats {
@@ -34,22 +56,285 @@ pub mod twr_custom_mats {
     }
 }
 pub mod drain;
+pub mod generator;
 pub mod radar;
+pub mod repair;
+pub mod roboport;
+pub mod synergy;
+pub mod turrets;
 
 /// Marker component all buildings should have
 #[derive(Component)]
 pub struct Building;
 
+/// Corporation Points a building costs to keep running every second, before the upkeep curve
+#[derive(Component)]
+pub struct Upkeep(pub u32);
+
+/// How close the cursor has to be to a building to be hovering it
+const HOVER_RADIUS: f32 = 32.0;
+/// Fraction of a building's [Cost] given back when it's sold
+const SELL_REFUND: f32 = 0.5;
+/// Switched off buildings are greyed out like unpowered ones on the power map
+const INACTIVE_TINT: Color = Color::rgb(0.5, 0.5, 0.5);
+
+/// The building under the cursor, if any
+#[derive(Resource, Default)]
+pub struct HoveredBuilding(pub Option<Entity>);
+
+/// The building last clicked on, if any. Selling and repairing act on this one.
+#[derive(Resource, Default)]
+pub struct SelectedBuilding(pub Option<Entity>);
+
+/// Systems shared by every kind of building
+pub struct BuildingsPlugin;
+impl Plugin for BuildingsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<RemoveBuilding>()
+            .init_resource::<HoveredBuilding>()
+            .init_resource::<SelectedBuilding>()
+            .add_systems(OnEnter(AppState::Gameplay), clear_selection)
+            .add_systems(
+                Update,
+                (
+                    destroy_dead_buildings,
+                    tint_inactive_buildings,
+                    (
+                        update_hovered_building,
+                        select_building,
+                        sell_selected_building,
+                        highlight_selected_building,
+                    )
+                        .chain(),
+                )
+                    .run_if(in_state(AppState::Gameplay)),
+            );
+    }
+}
+
+/// Whatever's standing on the tile under the cursor, otherwise the closest building to it
+fn update_hovered_building(
+    mut hovered: ResMut<HoveredBuilding>,
+    tile_hover: Res<CurrentTileHover>,
+    occupancy: Res<OccupancyGrid>,
+    q_buildings: Query<(Entity, &Transform), With<Building>>,
+) {
+    let on_tile = tile_hover
+        .tile_pos
+        .and_then(|tile| occupancy.building_at(&tile))
+        .filter(|ent| q_buildings.contains(*ent));
+    let closest = on_tile.or_else(|| {
+        tile_hover.world_pos.and_then(|cursor| {
+            q_buildings
+                .iter()
+                .map(|(ent, tf)| (ent, tf.translation.truncate().distance(cursor)))
+                .filter(|(_, dist)| *dist < HOVER_RADIUS)
+                .min_by(|(_, a), (_, b)| a.total_cmp(b))
+                .map(|(ent, _)| ent)
+        })
+    });
+    if hovered.0 != closest {
+        hovered.0 = closest;
+    }
+}
+
+/// Greys out buildings as they're switched off, and gives them back whatever tint they had
+/// (turrets and drones have their own) when they're switched on again. Nothing else about
+/// the tint is touched, the power map keeps greying out unpowered ones.
+#[allow(clippy::type_complexity)]
+fn tint_inactive_buildings(
+    // what each switched off building looked like before
+    mut active_tints: Local<HashMap<Entity, Color>>,
+    removed: EventReader<RemoveBuilding>,
+    q_existing: Query<(), With<Building>>,
+    mut q_buildings: Query<
+        (
+            Entity,
+            &BuildingState,
+            Option<&mut Sprite>,
+            Option<&mut TextureAtlasSprite>,
+        ),
+        (With<Building>, Changed<BuildingState>),
+    >,
+) {
+    // forget the ones that were sold or destroyed while switched off
+    if !removed.is_empty() {
+        active_tints.retain(|ent, _| q_existing.contains(*ent));
+    }
+
+    q_buildings
+        .iter_mut()
+        .for_each(|(ent, state, sprite, atlas_sprite)| {
+            let Some(color) = sprite
+                .map(|sprite| &mut sprite.into_inner().color)
+                .or_else(|| atlas_sprite.map(|sprite| &mut sprite.into_inner().color))
+            else {
+                return;
+            };
+            match state {
+                BuildingState::Inactive => {
+                    active_tints.entry(ent).or_insert(*color);
+                    *color = INACTIVE_TINT;
+                }
+                // finishing construction leaves the tint alone, it was never switched off
+                _ => {
+                    if let Some(tint) = active_tints.remove(&ent) {
+                        *color = tint;
+                    }
+                }
+            }
+        });
+}
+
+fn clear_selection(mut selected: ResMut<SelectedBuilding>) {
+    selected.0 = None;
+}
+
+/// Left clicking selects the hovered building, or clears the selection when there's nothing
+/// there. Clicks only place buildings while placing, and never select through the HUD.
+fn select_building(
+    mut selected: ResMut<SelectedBuilding>,
+    hovered: Res<HoveredBuilding>,
+    placement: Res<PlacementState>,
+    mouse_btns: Res<Input<MouseButton>>,
+    q_interactions: Query<&Interaction>,
+    q_buildings: Query<(), With<Building>>,
+) {
+    // whatever was selected might have been sold or destroyed since
+    if selected.0.map_or(false, |ent| !q_buildings.contains(ent)) {
+        selected.0 = None;
+    }
+
+    if !mouse_btns.just_pressed(MouseButton::Left)
+        || placement.is_placing()
+        || is_cursor_over_ui(&q_interactions)
+    {
+        return;
+    }
+    if selected.0 != hovered.0 {
+        selected.0 = hovered.0;
+    }
+}
+
+/// Outline around the selected building, with a bit of room around its sprite
+fn highlight_selected_building(
+    mut gizmos: Gizmos,
+    selected: Res<SelectedBuilding>,
+    q_buildings: Query<(&Transform, &Footprint), With<Building>>,
+) {
+    let Some((tf, footprint)) = selected.0.and_then(|ent| q_buildings.get(ent).ok()) else {
+        return;
+    };
+    gizmos.rect_2d(
+        tf.translation.truncate(),
+        0.0,
+        footprint.sprite_size() + Vec2::splat(TILE_SIZE / 2.0),
+        Color::YELLOW,
+    );
+}
+
+/// Sells the selected building back to The Company for part of what it cost. The core isn't
+/// for sale.
+fn sell_selected_building(
+    mut commands: Commands,
+    input: Res<Input<FloraCommand>>,
+    mut selected: ResMut<SelectedBuilding>,
+    mut inventory: ResMut<Inventory>,
+    mut removed: EventWriter<RemoveBuilding>,
+    q_buildings: Query<&BuildingType, Without<core::TheCore>>,
+) {
+    if !input.just_pressed(FloraCommand::SellBuilding) {
+        return;
+    }
+    let Some(ent) = selected.0 else {
+        return;
+    };
+    let Ok(building) = q_buildings.get(ent) else {
+        return;
+    };
+
+    info!("Sold a {:?}", building);
+    building.cost().iter().for_each(|(resource, amount)| {
+        inventory.add(*resource, (*amount as f32 * SELL_REFUND) as u32);
+    });
+    commands.entity(ent).despawn_recursive();
+    removed.send(RemoveBuilding);
+    selected.0 = None;
+}
+
+/// Buildings that run out of [Health] are destroyed. Losing [core::TheCore] loses the game.
+fn destroy_dead_buildings(
+    mut commands: Commands,
+    q_buildings: Query<(Entity, &Health, Option<&core::TheCore>), With<Building>>,
+    mut removed: EventWriter<RemoveBuilding>,
+    mut game_over: EventWriter<GameOver>,
+    mut transition_state: ResMut<TransitionState>,
+    // the core sticks around until the transition's done, it's only lost the once
+    mut lost_core: Local<Option<Entity>>,
+) {
+    q_buildings
+        .iter()
+        .filter(|(_, health, _)| health.0 == 0)
+        .for_each(|(ent, _, core)| {
+            if core.is_some() {
+                if *lost_core == Some(ent) {
+                    return;
+                }
+                *lost_core = Some(ent);
+                info!("The Core was destroyed, GAME OVER");
+                game_over.send(GameOver);
+                transition_to(AppState::GameOver, &mut transition_state);
+                return;
+            }
+
+            commands.entity(ent).despawn_recursive();
+            removed.send(RemoveBuilding);
+        });
+}
+
+/// The tiles a building takes up, see [crate::game::occupancy::OccupancyGrid]
+#[derive(Component, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Footprint {
+    pub width: u32,
+    pub height: u32,
+}
+
+impl Footprint {
+    pub const SINGLE: Footprint = Footprint {
+        width: 1,
+        height: 1,
+    };
+
+    /// Offsets in tiles from the tile the building stands on. Wide buildings spread out either
+    /// side of it (leaning left when there's no middle), tall ones go up from it.
+    pub fn offsets(&self) -> impl Iterator<Item = (i32, i32)> {
+        let (width, height) = (self.width as i32, self.height as i32);
+        (0..height).flat_map(move |dy| (-width / 2..width - width / 2).map(move |dx| (dx, dy)))
+    }
+
+    /// How big the sprite of a building this size is, they stick out a tile above their
+    /// footprint
+    pub fn sprite_size(&self) -> Vec2 {
+        Vec2::new(self.width as f32, self.height as f32 + 1.0) * TILE_SIZE
+    }
+}
+
 /// Blueprint for a generic tower entity
 #[derive(Bundle)]
 pub struct MinimalBuilding {
     marker: Building,
     health: Health,
+    max_health: MaxHealth,
+    upkeep: Upkeep,
+    footprint: Footprint,
     hp_bar: HpBarUISettings,
     sprite: SpriteBundle,
 }
 
-#[derive(Component, PartialEq)]
+/// Buildings without one are always active. The player can switch off any building but the
+/// core, it stops working, drops off the power grid and costs less [Upkeep] until it's switched
+/// back on.
+#[derive(Component, Clone, Copy, Debug, PartialEq)]
 pub enum BuildingState {
     Building,
     Active,
@@ -60,12 +345,16 @@ pub enum BuildingState {
 pub trait BuildingDefinition: Default {
     const SPRITE_PATH: &'static str;
     const BASE_HEALTH: u32;
-    /// How many Corporation Points it costs to build
-    const COST: u32;
+    /// What it costs to build, see [Cost]
+    const COST: Cost;
     /// How long it takes to build in seconds
     const BUILD_TIME: u32;
+    /// Corporation Points per second it costs to keep running, see [Upkeep]
+    const UPKEEP: u32;
     const NAME: &'static str;
     const DESCRIPTION: &'static str;
+    /// The tiles it takes up, most buildings only need the one they stand on
+    const FOOTPRINT: Footprint = Footprint::SINGLE;
 
     fn add_extra_components(commands: &mut Commands, end_id: Entity);
 }
@@ -82,6 +371,9 @@ pub fn spawn_building<B: BuildingDefinition>(
             MinimalBuilding {
                 marker: Building,
                 health: Health(B::BASE_HEALTH),
+                max_health: MaxHealth(B::BASE_HEALTH),
+                upkeep: Upkeep(B::UPKEEP),
+                footprint: B::FOOTPRINT,
                 hp_bar: HpBarUISettings {
                     max: B::BASE_HEALTH,
                     offset: None,
@@ -102,12 +394,30 @@ pub fn spawn_building<B: BuildingDefinition>(
 }
 
 // /// Representing the types of buildings we have
-#[derive(Clone, Hash, Component, Debug, PartialEq, Eq)]
+#[derive(Clone, Hash, Component, Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub enum BuildingType {
     Radar,
     /// Pylons, you must construct additional...
     Distribution,
     Drain,
+    /// Home base for the harvesting robots
+    Roboport,
+    /// Burns biomass for power
+    Generator,
+    /// Stores spare power
+    Battery,
+    /// Turret, sprays a cone of herbicide
+    HerbicideSprayer,
+    /// Turret, sets trees on fire
+    Flamethrower,
+    /// Turret, shoots down seeds
+    SeedInterceptor,
+    /// Barrier, stops seeds
+    Wall,
+    /// Barrier, stops fire
+    Firebreak,
+    /// Repairs powered buildings around it
+    RepairDrone,
 }
 
 impl BuildingType {
@@ -115,14 +425,14 @@ impl BuildingType {
     pub fn spawn(
         &self,
         commands: &mut Commands,
-        texture_atlases: ResMut<Assets<TextureAtlas>>,
-        meshes: ResMut<Assets<Mesh>>,
-        materials: ResMut<Assets<TowerRadiusMaterial>>,
+        texture_atlases: &mut Assets<TextureAtlas>,
+        meshes: &mut Assets<Mesh>,
+        materials: &mut Assets<TowerRadiusMaterial>,
         asset_server: Res<AssetServer>,
         pos: Vec2,
-        mut audio_mngr: EventWriter<AudioRequest>,
-    ) {
-        match self {
+        audio_mngr: &mut EventWriter<AudioRequest>,
+    ) -> Entity {
+        let ent_id = match self {
             BuildingType::Radar => {
                 audio_mngr.send(AudioRequest {
                     component: crate::global_systems::eargasm::AudioComponent::Radar1(
@@ -163,14 +473,139 @@ impl BuildingType {
                     pos,
                 )
             }
+            BuildingType::Roboport => {
+                audio_mngr.send(AudioRequest {
+                    component: crate::global_systems::eargasm::AudioComponent::Electric(
+                        crate::global_systems::eargasm::Electric,
+                    ),
+                });
+                spawn_building::<Roboport>(commands, asset_server, pos)
+            }
+            BuildingType::Generator => {
+                audio_mngr.send(AudioRequest {
+                    component: crate::global_systems::eargasm::AudioComponent::Thump(
+                        crate::global_systems::eargasm::Thump,
+                    ),
+                });
+                spawn_building::<Generator>(commands, asset_server, pos)
+            }
+            BuildingType::Battery => {
+                audio_mngr.send(AudioRequest {
+                    component: crate::global_systems::eargasm::AudioComponent::Electric(
+                        crate::global_systems::eargasm::Electric,
+                    ),
+                });
+                spawn_building::<Battery>(commands, asset_server, pos)
+            }
+            BuildingType::HerbicideSprayer => {
+                audio_mngr.send(AudioRequest {
+                    component: crate::global_systems::eargasm::AudioComponent::Thump(
+                        crate::global_systems::eargasm::Thump,
+                    ),
+                });
+                spawn_building::<HerbicideSprayer>(commands, asset_server, pos)
+            }
+            BuildingType::Flamethrower => {
+                audio_mngr.send(AudioRequest {
+                    component: crate::global_systems::eargasm::AudioComponent::Thump(
+                        crate::global_systems::eargasm::Thump,
+                    ),
+                });
+                spawn_building::<Flamethrower>(commands, asset_server, pos)
+            }
+            BuildingType::SeedInterceptor => {
+                audio_mngr.send(AudioRequest {
+                    component: crate::global_systems::eargasm::AudioComponent::Radar1(
+                        crate::global_systems::eargasm::Radar1,
+                    ),
+                });
+                spawn_building::<SeedInterceptor>(commands, asset_server, pos)
+            }
+            BuildingType::Wall => spawn_building::<Wall>(commands, asset_server, pos),
+            BuildingType::Firebreak => spawn_building::<Firebreak>(commands, asset_server, pos),
+            BuildingType::RepairDrone => {
+                audio_mngr.send(AudioRequest {
+                    component: crate::global_systems::eargasm::AudioComponent::Electric(
+                        crate::global_systems::eargasm::Electric,
+                    ),
+                });
+                spawn_building::<RepairDrone>(commands, asset_server, pos)
+            }
         };
+        commands.entity(ent_id).insert(self.clone());
+        ent_id
     }
 
-    pub fn cost(&self) -> u32 {
+    pub fn cost(&self) -> Cost {
         match self {
             BuildingType::Distribution => DistributionTower::COST,
             BuildingType::Radar => RadarTower::COST,
-            BuildingType::Drain => RadarTower::COST,
+            BuildingType::Drain => DrainTower::COST,
+            BuildingType::Roboport => Roboport::COST,
+            BuildingType::Generator => Generator::COST,
+            BuildingType::Battery => Battery::COST,
+            BuildingType::HerbicideSprayer => HerbicideSprayer::COST,
+            BuildingType::Flamethrower => Flamethrower::COST,
+            BuildingType::SeedInterceptor => SeedInterceptor::COST,
+            BuildingType::Wall => Wall::COST,
+            BuildingType::Firebreak => Firebreak::COST,
+            BuildingType::RepairDrone => RepairDrone::COST,
+        }
+    }
+
+    pub fn footprint(&self) -> Footprint {
+        match self {
+            BuildingType::Distribution => DistributionTower::FOOTPRINT,
+            BuildingType::Radar => RadarTower::FOOTPRINT,
+            BuildingType::Drain => DrainTower::FOOTPRINT,
+            BuildingType::Roboport => Roboport::FOOTPRINT,
+            BuildingType::Generator => Generator::FOOTPRINT,
+            BuildingType::Battery => Battery::FOOTPRINT,
+            BuildingType::HerbicideSprayer => HerbicideSprayer::FOOTPRINT,
+            BuildingType::Flamethrower => Flamethrower::FOOTPRINT,
+            BuildingType::SeedInterceptor => SeedInterceptor::FOOTPRINT,
+            BuildingType::Wall => Wall::FOOTPRINT,
+            BuildingType::Firebreak => Firebreak::FOOTPRINT,
+            BuildingType::RepairDrone => RepairDrone::FOOTPRINT,
+        }
+    }
+
+    /// Barriers are placed in lines rather than one at a time
+    pub fn is_barrier(&self) -> bool {
+        matches!(self, BuildingType::Wall | BuildingType::Firebreak)
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            BuildingType::Distribution => DistributionTower::NAME,
+            BuildingType::Radar => RadarTower::NAME,
+            BuildingType::Drain => DrainTower::NAME,
+            BuildingType::Roboport => Roboport::NAME,
+            BuildingType::Generator => Generator::NAME,
+            BuildingType::Battery => Battery::NAME,
+            BuildingType::HerbicideSprayer => HerbicideSprayer::NAME,
+            BuildingType::Flamethrower => Flamethrower::NAME,
+            BuildingType::SeedInterceptor => SeedInterceptor::NAME,
+            BuildingType::Wall => Wall::NAME,
+            BuildingType::Firebreak => Firebreak::NAME,
+            BuildingType::RepairDrone => RepairDrone::NAME,
+        }
+    }
+
+    pub fn description(&self) -> &'static str {
+        match self {
+            BuildingType::Distribution => DistributionTower::DESCRIPTION,
+            BuildingType::Radar => RadarTower::DESCRIPTION,
+            BuildingType::Drain => DrainTower::DESCRIPTION,
+            BuildingType::Roboport => Roboport::DESCRIPTION,
+            BuildingType::Generator => Generator::DESCRIPTION,
+            BuildingType::Battery => Battery::DESCRIPTION,
+            BuildingType::HerbicideSprayer => HerbicideSprayer::DESCRIPTION,
+            BuildingType::Flamethrower => Flamethrower::DESCRIPTION,
+            BuildingType::SeedInterceptor => SeedInterceptor::DESCRIPTION,
+            BuildingType::Wall => Wall::DESCRIPTION,
+            BuildingType::Firebreak => Firebreak::DESCRIPTION,
+            BuildingType::RepairDrone => RepairDrone::DESCRIPTION,
         }
     }
 
@@ -179,6 +614,15 @@ impl BuildingType {
             BuildingType::Radar => "",
             BuildingType::Distribution => "textures/tower_single.png",
             BuildingType::Drain => "textures/sucky-uppy-single-frame.png",
+            BuildingType::Roboport => Roboport::SPRITE_PATH,
+            BuildingType::Generator => Generator::SPRITE_PATH,
+            BuildingType::Battery => Battery::SPRITE_PATH,
+            BuildingType::HerbicideSprayer => HerbicideSprayer::SPRITE_PATH,
+            BuildingType::Flamethrower => Flamethrower::SPRITE_PATH,
+            BuildingType::SeedInterceptor => SeedInterceptor::SPRITE_PATH,
+            BuildingType::Wall => Wall::SPRITE_PATH,
+            BuildingType::Firebreak => Firebreak::SPRITE_PATH,
+            BuildingType::RepairDrone => RepairDrone::SPRITE_PATH,
         }
     }
 }
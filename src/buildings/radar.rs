@@ -1,4 +1,7 @@
-use crate::game::power::{RequiresPower, SupplyRadius};
+use crate::game::{
+    power::{PowerDraw, RequiresPower, SupplyRadius},
+    resources::{Cost, ResourceType},
+};
 use bevy::prelude::*;
 
 use super::BuildingDefinition;
@@ -9,12 +12,18 @@ pub struct RadarTower;
 impl BuildingDefinition for RadarTower {
     const SPRITE_PATH: &'static str = "textures/radar.png";
     const BASE_HEALTH: u32 = 100;
-    const COST: u32 = 100; // Core is free since you start with it
+    const COST: Cost = &[
+        (ResourceType::CorporationPoints, 100),
+        (ResourceType::Minerals, 10),
+    ];
     const BUILD_TIME: u32 = 10;
+    const UPKEEP: u32 = 2;
     const NAME: &'static str = "Radar Tower";
     const DESCRIPTION: &'static str = ""; // TODO
 
     fn add_extra_components(commands: &mut Commands, ent_id: Entity) {
-        commands.entity(ent_id).insert(RequiresPower);
+        commands
+            .entity(ent_id)
+            .insert((RequiresPower, PowerDraw(2.0)));
     }
 }
@@ -0,0 +1,224 @@
+//! Patching up damaged buildings.
+//!
+//! **LOGIC:**
+//!
+//! - pressing repair on the selected building marks it [Repairing], and every repair tick it gets
+//!   a little [Health] back for a few Corporation Points, until it's whole again or you're broke
+//! - powered [RepairDrone]s do the same on their own, always fixing up the most damaged powered
+//!   building in range first
+//!
+//! Nothing is ever repaired past its [MaxHealth].
+
+use bevy::prelude::*;
+
+use crate::{
+    game::{
+        keybinds::FloraCommand,
+        power::{IsPowered, PowerDraw, PowerGrid, PowerPriority, RequiresPower},
+        resources::{Cost, Inventory, ResourceType},
+    },
+    AppState, Health, MaxHealth,
+};
+
+use super::{Building, BuildingDefinition, SelectedBuilding};
+
+/// Every *this* many seconds repairs happen
+const REPAIR_TICK_RATE: f32 = 0.5;
+/// Health a building being repaired by hand gets back each tick
+const REPAIR_HP_PER_TICK: u32 = 10;
+/// Corporation Points each point of repaired [Health] costs
+const REPAIR_COST_PER_HP: u32 = 1;
+
+const DRONE_RANGE: f32 = 300.0;
+/// Health a [RepairDrone] gives back each tick, on a fully powered grid
+const DRONE_HP_PER_TICK: u32 = 6;
+const DRONE_DRAW: f32 = 3.0;
+const DRONE_TINT: Color = Color::rgb(1.0, 0.9, 0.4);
+
+pub struct RepairPlugin;
+impl Plugin for RepairPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(RepairTick(Timer::from_seconds(
+            REPAIR_TICK_RATE,
+            TimerMode::Repeating,
+        )))
+        .add_systems(
+            Update,
+            (
+                toggle_repair,
+                (tick_repairs, repair_buildings, drone_repair_buildings).chain(),
+                draw_repair_beams,
+            )
+                .run_if(in_state(AppState::Gameplay)),
+        );
+    }
+}
+
+/// Marks a building that's being repaired by hand
+#[derive(Component)]
+pub struct Repairing;
+
+/// Repairs the most damaged powered building in range, see [RepairDrone]
+#[derive(Component, Default)]
+pub struct RepairDrone {
+    /// What it's currently fixing, if anything
+    target: Option<Entity>,
+}
+
+impl BuildingDefinition for RepairDrone {
+    const SPRITE_PATH: &'static str = "textures/tower_single.png";
+    const BASE_HEALTH: u32 = 80;
+    const COST: Cost = &[
+        (ResourceType::CorporationPoints, 250),
+        (ResourceType::Minerals, 10),
+    ];
+    const BUILD_TIME: u32 = 4;
+    const UPKEEP: u32 = 1;
+    const NAME: &'static str = "Repair Drone";
+    const DESCRIPTION: &'static str = "Keeps powered buildings around it in one piece, for a
+        few Corporation Points per repair.";
+
+    fn add_extra_components(commands: &mut Commands, ent_id: Entity) {
+        commands.entity(ent_id).insert((
+            RepairDrone::default(),
+            RequiresPower,
+            PowerDraw(DRONE_DRAW),
+            Sprite {
+                color: DRONE_TINT,
+                ..default()
+            },
+        ));
+    }
+}
+
+#[derive(Resource)]
+struct RepairTick(Timer);
+
+/// Starts or stops repairing the selected building. Buildings that aren't damaged don't need it.
+fn toggle_repair(
+    mut commands: Commands,
+    input: Res<Input<FloraCommand>>,
+    selected: Res<SelectedBuilding>,
+    q_buildings: Query<(&Health, &MaxHealth, Option<&Repairing>), With<Building>>,
+) {
+    if !input.just_pressed(FloraCommand::Repair) {
+        return;
+    }
+    let Some(ent) = selected.0 else {
+        return;
+    };
+    let Ok((hp, max, repairing)) = q_buildings.get(ent) else {
+        return;
+    };
+
+    if repairing.is_some() {
+        commands.entity(ent).remove::<Repairing>();
+    } else if hp.missing(max) > 0 {
+        info!("Repairing building {:?}", ent);
+        commands.entity(ent).insert(Repairing);
+    }
+}
+
+fn tick_repairs(mut timer: ResMut<RepairTick>, time: Res<Time>) {
+    timer.0.tick(time.delta());
+}
+
+/// Heals everything marked [Repairing] for as long as it's damaged and the money lasts
+fn repair_buildings(
+    mut commands: Commands,
+    timer: Res<RepairTick>,
+    mut inventory: ResMut<Inventory>,
+    mut q_repairing: Query<(Entity, &mut Health, &MaxHealth), With<Repairing>>,
+) {
+    if !timer.0.just_finished() {
+        return;
+    }
+
+    q_repairing.iter_mut().for_each(|(ent, mut hp, max)| {
+        let affordable = inventory.money() / REPAIR_COST_PER_HP;
+        let amount = REPAIR_HP_PER_TICK.min(hp.missing(max)).min(affordable);
+        if amount > 0 {
+            inventory.remove(ResourceType::CorporationPoints, amount * REPAIR_COST_PER_HP);
+            hp.restore(amount, max);
+        }
+        if hp.missing(max) == 0 || affordable == 0 {
+            commands.entity(ent).remove::<Repairing>();
+        }
+    });
+}
+
+/// Every powered [RepairDrone] picks the most damaged powered building in range and patches it
+/// up a bit. Drones work slower on a struggling grid.
+fn drone_repair_buildings(
+    timer: Res<RepairTick>,
+    grid: Res<PowerGrid>,
+    mut inventory: ResMut<Inventory>,
+    mut q_drones: Query<(
+        Entity,
+        &mut RepairDrone,
+        &Transform,
+        Option<&IsPowered>,
+        Option<&PowerPriority>,
+    )>,
+    mut q_buildings: Query<
+        (Entity, &mut Health, &MaxHealth, &Transform),
+        (With<Building>, With<IsPowered>),
+    >,
+) {
+    if !timer.0.just_finished() {
+        return;
+    }
+
+    q_drones
+        .iter_mut()
+        .for_each(|(drone_ent, mut drone, drone_tf, powered, priority)| {
+            drone.target = None;
+            let heal = (DRONE_HP_PER_TICK as f32 * grid.satisfaction_of(priority)).round() as u32;
+            if powered.is_none() || heal == 0 {
+                return;
+            }
+
+            let Some((target, mut hp, max, _)) = q_buildings
+                .iter_mut()
+                .filter(|(ent, hp, max, tf)| {
+                    *ent != drone_ent
+                        && hp.missing(max) > 0
+                        && tf.translation.distance(drone_tf.translation) < DRONE_RANGE
+                })
+                .min_by(|(_, a_hp, a_max, _), (_, b_hp, b_max, _)| {
+                    let a = a_hp.0 as f32 / a_max.0 as f32;
+                    let b = b_hp.0 as f32 / b_max.0 as f32;
+                    a.total_cmp(&b)
+                })
+            else {
+                return;
+            };
+
+            let affordable = inventory.money() / REPAIR_COST_PER_HP;
+            let amount = heal.min(hp.missing(max)).min(affordable);
+            if amount == 0 {
+                return;
+            }
+            inventory.remove(ResourceType::CorporationPoints, amount * REPAIR_COST_PER_HP);
+            hp.restore(amount, max);
+            drone.target = Some(target);
+        });
+}
+
+/// A line from every working [RepairDrone] to whatever it's fixing
+fn draw_repair_beams(
+    mut gizmos: Gizmos,
+    q_drones: Query<(&RepairDrone, &Transform)>,
+    q_targets: Query<&Transform, With<Building>>,
+) {
+    q_drones.iter().for_each(|(drone, drone_tf)| {
+        let Some(target_tf) = drone.target.and_then(|target| q_targets.get(target).ok()) else {
+            return;
+        };
+        gizmos.line_2d(
+            drone_tf.translation.truncate(),
+            target_tf.translation.truncate(),
+            DRONE_TINT,
+        );
+    });
+}
@@ -0,0 +1,477 @@
+//! A long range building that sends robots out to collect resources.
+//!
+//! **LOGIC:**
+//!
+//! Each powered [Roboport] keeps a fleet of [Robot]s topped up. An idle robot heads for the
+//! closest [ResourceDrop] in range, or failing that chops down the closest mature tree (which
+//! leaves a drop behind). Once it's full, or there's nothing left to do, it flies home and
+//! unloads into the roboport's [Stockpile] so the haul can travel along the network. When a
+//! roboport loses power or is switched off its robots drop whatever they were doing and stay
+//! parked at home until it's back on.
+//!
+//! Trees don't like robots. Flying through a forest hurts, so the fleet gets chewed up when
+//! a roboport sits in the middle of the woods.
+
+use std::collections::HashMap;
+
+use bevy::prelude::*;
+
+use crate::{
+    creeps::{lifecycle::Sapling, ResourceDrop},
+    game::{
+        keybinds::FloraCommand,
+        logistics::Stockpile,
+        power::{IsPowered, PowerDraw, PowerGrid, PowerPriority, RequiresPower},
+        research::{Ability, Research},
+        resources::{Cost, ExpendResource, Inventory, ResourceType},
+    },
+    AppState, Health, Teardown, Tree, CREEP_Z,
+};
+
+use super::{BuildingDefinition, Footprint};
+
+/// How far from its roboport a robot will go looking for work
+const ROBOPORT_RANGE: f32 = 800.0;
+/// Seconds between new robots rolling off the line
+const ROBOT_BUILD_TIME: f32 = 4.0;
+const ROBOT_HEALTH: u32 = 30;
+/// How close a robot has to be to something to work on it
+const ROBOT_REACH: f32 = 12.0;
+/// Every *this* many seconds robots chop, pick up and unload
+const ROBOT_WORK_TICK_RATE: f32 = 0.5;
+/// Damage a robot does to a tree each work tick
+const ROBOT_CHOP_DPT: u32 = 10;
+/// Trees this close to a robot take a swipe at it
+const THORN_RANGE: f32 = 40.0;
+/// Damage each tree in [THORN_RANGE] does to a robot each work tick
+const THORN_DPT: u32 = 1;
+
+const BASE_ROBOTS: usize = 3;
+const BASE_ROBOT_SPEED: f32 = 120.0;
+const BASE_ROBOT_CAPACITY: u32 = 20;
+/// How many times each robot stat can be upgraded
+const MAX_UPGRADE_LEVEL: u32 = 5;
+
+#[derive(Component)]
+pub struct Roboport {
+    robots: Vec<Entity>,
+    build_timer: Timer,
+}
+
+impl Default for Roboport {
+    fn default() -> Self {
+        Self {
+            robots: vec![],
+            build_timer: Timer::from_seconds(ROBOT_BUILD_TIME, TimerMode::Repeating),
+        }
+    }
+}
+
+impl BuildingDefinition for Roboport {
+    const SPRITE_PATH: &'static str = "textures/big_building.png";
+    const BASE_HEALTH: u32 = 150;
+    const COST: Cost = &[
+        (ResourceType::CorporationPoints, 400),
+        (ResourceType::Wood, 30),
+    ];
+    const BUILD_TIME: u32 = 8;
+    const UPKEEP: u32 = 4;
+    const NAME: &'static str = "Roboport";
+    const DESCRIPTION: &'static str = "Sends robots out to chop trees and collect whatever's
+        lying around. Robots don't last long in thick forest.";
+    const FOOTPRINT: Footprint = Footprint {
+        width: 2,
+        height: 2,
+    };
+
+    fn add_extra_components(commands: &mut Commands, ent_id: Entity) {
+        commands.entity(ent_id).insert((
+            RequiresPower,
+            PowerDraw(5.0),
+            Stockpile::default(),
+            Roboport::default(),
+        ));
+    }
+}
+
+/// Which upgrades the player has bought for every robot
+#[derive(Resource, Default)]
+pub struct RobotUpgrades {
+    pub count: u32,
+    pub speed: u32,
+    pub capacity: u32,
+}
+
+impl RobotUpgrades {
+    pub fn robots_per_port(&self) -> usize {
+        BASE_ROBOTS + 2 * self.count as usize
+    }
+
+    pub fn speed(&self) -> f32 {
+        BASE_ROBOT_SPEED * (1.0 + 0.25 * self.speed as f32)
+    }
+
+    pub fn capacity(&self) -> u32 {
+        BASE_ROBOT_CAPACITY + 10 * self.capacity
+    }
+
+    /// What the next level of an upgrade costs, given its current level
+    pub fn cost(level: u32) -> [(ResourceType, u32); 2] {
+        [
+            (ResourceType::CorporationPoints, 150 * (level + 1)),
+            (ResourceType::Minerals, 15 * (level + 1)),
+        ]
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum RobotTask {
+    Idle,
+    /// Picking up a [ResourceDrop]
+    Collect(Entity),
+    /// Chopping down a tree
+    Chop(Entity),
+    /// Flying home to unload
+    Return,
+}
+
+#[derive(Component)]
+pub struct Robot {
+    pub home: Entity,
+    pub task: RobotTask,
+    pub cargo: HashMap<ResourceType, u32>,
+}
+
+impl Robot {
+    fn carried(&self) -> u32 {
+        self.cargo.values().sum()
+    }
+}
+
+#[derive(Resource)]
+struct RobotWorkTick(Timer);
+
+pub struct RoboportPlugin;
+impl Plugin for RoboportPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<RobotUpgrades>()
+            .insert_resource(RobotWorkTick(Timer::from_seconds(
+                ROBOT_WORK_TICK_RATE,
+                TimerMode::Repeating,
+            )))
+            .add_systems(OnEnter(AppState::Gameplay), reset_upgrades)
+            .add_systems(
+                Update,
+                (
+                    buy_upgrades,
+                    build_robots,
+                    recall_robots,
+                    assign_tasks,
+                    fly_robots,
+                    do_work,
+                    thorns,
+                    cleanup_robots,
+                )
+                    .chain()
+                    .run_if(in_state(AppState::Gameplay)),
+            );
+    }
+}
+
+fn reset_upgrades(mut upgrades: ResMut<RobotUpgrades>) {
+    *upgrades = RobotUpgrades::default();
+}
+
+fn buy_upgrades(
+    input: Res<Input<FloraCommand>>,
+    mut upgrades: ResMut<RobotUpgrades>,
+    inventory: Res<Inventory>,
+    research: Res<Research>,
+    mut expend_resource: EventWriter<ExpendResource>,
+) {
+    if !research.has(Ability::RobotUpgrades) {
+        return;
+    }
+
+    let upgrades = &mut *upgrades;
+    [
+        (
+            FloraCommand::UpgradeRobotCount,
+            &mut upgrades.count,
+            "count",
+        ),
+        (
+            FloraCommand::UpgradeRobotSpeed,
+            &mut upgrades.speed,
+            "speed",
+        ),
+        (
+            FloraCommand::UpgradeRobotCapacity,
+            &mut upgrades.capacity,
+            "capacity",
+        ),
+    ]
+    .into_iter()
+    .filter(|(command, _, _)| input.just_pressed(*command))
+    .for_each(|(_, level, name)| {
+        let cost = RobotUpgrades::cost(*level);
+        if *level >= MAX_UPGRADE_LEVEL || !inventory.can_afford(&cost) {
+            return;
+        }
+
+        cost.iter().for_each(|(resource, amount)| {
+            expend_resource.send(ExpendResource(*resource, *amount));
+        });
+        *level += 1;
+        info!("Upgraded robot {} to level {}", name, level);
+    });
+}
+
+/// Powered roboports build a new robot every [ROBOT_BUILD_TIME] until their fleet is full
+fn build_robots(
+    mut commands: Commands,
+    time: Res<Time>,
+    upgrades: Res<RobotUpgrades>,
+    mut q_ports: Query<(Entity, &mut Roboport, &Transform), With<IsPowered>>,
+    q_robots: Query<(), With<Robot>>,
+) {
+    q_ports
+        .iter_mut()
+        .for_each(|(port_ent, mut port, port_tf)| {
+            port.robots.retain(|robot| q_robots.contains(*robot));
+            if port.robots.len() >= upgrades.robots_per_port() {
+                return;
+            }
+            if !port.build_timer.tick(time.delta()).just_finished() {
+                return;
+            }
+
+            let pos = port_tf.translation.truncate();
+            let robot = commands
+                .spawn((
+                    SpriteBundle {
+                        sprite: Sprite {
+                            color: Color::CYAN,
+                            custom_size: Some(Vec2::new(8.0, 8.0)),
+                            ..default()
+                        },
+                        transform: Transform::from_xyz(pos.x, pos.y, CREEP_Z + 0.2),
+                        ..default()
+                    },
+                    Robot {
+                        home: port_ent,
+                        task: RobotTask::Idle,
+                        cargo: HashMap::new(),
+                    },
+                    Health(ROBOT_HEALTH),
+                    Teardown,
+                ))
+                .id();
+            port.robots.push(robot);
+        });
+}
+
+/// Robots whose roboport is unpowered, or switched off, fly home and stay there
+fn recall_robots(
+    mut q_robots: Query<&mut Robot>,
+    q_ports: Query<(), (With<Roboport>, Without<IsPowered>)>,
+) {
+    q_robots
+        .iter_mut()
+        .filter(|robot| robot.task != RobotTask::Return && q_ports.contains(robot.home))
+        .for_each(|mut robot| robot.task = RobotTask::Return);
+}
+
+/// Finds something to do for every idle robot
+fn assign_tasks(
+    upgrades: Res<RobotUpgrades>,
+    mut q_robots: Query<&mut Robot>,
+    q_ports: Query<&Transform, With<Roboport>>,
+    q_drops: Query<(Entity, &Transform, &ResourceDrop)>,
+    q_trees: Query<(Entity, &Transform), (With<Tree>, Without<Sapling>)>,
+) {
+    q_robots
+        .iter_mut()
+        .filter(|robot| robot.task == RobotTask::Idle)
+        .for_each(|mut robot| {
+            let Ok(home_tf) = q_ports.get(robot.home) else {
+                return;
+            };
+            if robot.carried() >= upgrades.capacity() {
+                robot.task = RobotTask::Return;
+                return;
+            }
+
+            let home = home_tf.translation.truncate();
+            let drop = closest_in_range(
+                home,
+                q_drops
+                    .iter()
+                    .filter(|(_, _, drop)| !drop.is_empty())
+                    .map(|(ent, tf, _)| (ent, tf)),
+            );
+
+            robot.task = if let Some(drop) = drop {
+                RobotTask::Collect(drop)
+            } else if let Some(tree) = closest_in_range(home, q_trees.iter()) {
+                RobotTask::Chop(tree)
+            } else if robot.carried() > 0 {
+                RobotTask::Return
+            } else {
+                RobotTask::Idle
+            };
+        });
+}
+
+fn closest_in_range<'a>(
+    home: Vec2,
+    targets: impl Iterator<Item = (Entity, &'a Transform)>,
+) -> Option<Entity> {
+    targets
+        .map(|(ent, tf)| (ent, tf.translation.truncate().distance(home)))
+        .filter(|(_, distance)| *distance < ROBOPORT_RANGE)
+        .min_by(|(_, a), (_, b)| a.total_cmp(b))
+        .map(|(ent, _)| ent)
+}
+
+/// Moves robots towards whatever they're working on. Robots whose target disappeared go idle.
+fn fly_robots(
+    time: Res<Time>,
+    upgrades: Res<RobotUpgrades>,
+    grid: Res<PowerGrid>,
+    mut q_robots: Query<(&mut Robot, &mut Transform)>,
+    q_targets: Query<&Transform, Without<Robot>>,
+    q_priorities: Query<&PowerPriority>,
+) {
+    q_robots.iter_mut().for_each(|(mut robot, mut robot_tf)| {
+        let target = match robot.task {
+            RobotTask::Idle => return,
+            RobotTask::Collect(ent) | RobotTask::Chop(ent) => ent,
+            RobotTask::Return => robot.home,
+        };
+        let Ok(target_tf) = q_targets.get(target) else {
+            robot.task = RobotTask::Idle;
+            return;
+        };
+
+        let to_target = target_tf.translation.truncate() - robot_tf.translation.truncate();
+        // a struggling network slows the whole fleet down, going by their port's priority
+        let satisfaction = grid.satisfaction_of(q_priorities.get(robot.home).ok());
+        let step = upgrades.speed() * satisfaction * time.delta_seconds();
+        if to_target.length() > ROBOT_REACH {
+            robot_tf.translation +=
+                (to_target.normalize() * step.min(to_target.length())).extend(0.0);
+        }
+    });
+}
+
+/// Every [ROBOT_WORK_TICK_RATE] robots within reach of their target chop, collect or unload
+fn do_work(
+    mut timer: ResMut<RobotWorkTick>,
+    time: Res<Time>,
+    upgrades: Res<RobotUpgrades>,
+    mut q_robots: Query<(&mut Robot, &Transform)>,
+    mut q_drops: Query<(&mut ResourceDrop, &Transform), Without<Robot>>,
+    mut q_trees: Query<(&mut Health, &Transform), (With<Tree>, Without<Robot>)>,
+    mut q_ports: Query<(&mut Stockpile, &Transform), (With<Roboport>, Without<Robot>)>,
+) {
+    if !timer.0.tick(time.delta()).just_finished() {
+        return;
+    }
+
+    let capacity = upgrades.capacity();
+    q_robots.iter_mut().for_each(|(mut robot, robot_tf)| {
+        let robot_pos = robot_tf.translation.truncate();
+        let in_reach =
+            |tf: &Transform| tf.translation.truncate().distance(robot_pos) <= ROBOT_REACH;
+
+        match robot.task {
+            RobotTask::Idle => {}
+            RobotTask::Collect(drop_ent) => {
+                let Ok((mut drop, drop_tf)) = q_drops.get_mut(drop_ent) else {
+                    return;
+                };
+                if !in_reach(drop_tf) {
+                    return;
+                }
+
+                ResourceType::ALL.iter().for_each(|resource| {
+                    let room = capacity.saturating_sub(robot.carried());
+                    if let Some(held) = drop.stock.get_mut(resource) {
+                        let taken = (*held).min(room);
+                        *held -= taken;
+                        *robot.cargo.entry(*resource).or_default() += taken;
+                    }
+                });
+                robot.task = RobotTask::Idle;
+            }
+            RobotTask::Chop(tree_ent) => {
+                let Ok((mut hp, tree_tf)) = q_trees.get_mut(tree_ent) else {
+                    return;
+                };
+                if in_reach(tree_tf) {
+                    // once it's down it leaves a drop, which is the next thing to go for
+                    hp.deduct(ROBOT_CHOP_DPT);
+                    if hp.0 == 0 {
+                        robot.task = RobotTask::Idle;
+                    }
+                }
+            }
+            RobotTask::Return => {
+                let Ok((mut stockpile, port_tf)) = q_ports.get_mut(robot.home) else {
+                    return;
+                };
+                if !in_reach(port_tf) {
+                    return;
+                }
+
+                let robot = &mut *robot;
+                robot.cargo.iter_mut().for_each(|(resource, amount)| {
+                    *amount -= stockpile.store(*resource, *amount);
+                });
+                robot.cargo.retain(|_, amount| *amount > 0);
+                robot.task = RobotTask::Idle;
+            }
+        }
+    });
+}
+
+/// Mature trees take a swipe at every robot that gets too close
+fn thorns(
+    timer: Res<RobotWorkTick>,
+    mut q_robots: Query<(&Transform, &mut Health, &mut Sprite), With<Robot>>,
+    q_trees: Query<&Transform, (With<Tree>, Without<Sapling>, Without<Robot>)>,
+) {
+    if !timer.0.just_finished() {
+        return;
+    }
+
+    q_robots
+        .iter_mut()
+        .for_each(|(robot_tf, mut hp, mut sprite)| {
+            let robot_pos = robot_tf.translation.truncate();
+            let attackers = q_trees
+                .iter()
+                .filter(|tree_tf| tree_tf.translation.truncate().distance(robot_pos) < THORN_RANGE)
+                .count() as u32;
+            hp.deduct(attackers * THORN_DPT);
+
+            // fade to red as it gets beaten up
+            let health = hp.0 as f32 / ROBOT_HEALTH as f32;
+            sprite.color = Color::rgb(1.0 - health, health, health);
+        });
+}
+
+/// Robots that died or lost their roboport fall out of the sky, along with their cargo
+fn cleanup_robots(
+    mut commands: Commands,
+    q_robots: Query<(Entity, &Robot, &Health)>,
+    q_ports: Query<(), With<Roboport>>,
+) {
+    q_robots
+        .iter()
+        .filter(|(_, robot, hp)| hp.0 == 0 || !q_ports.contains(robot.home))
+        .for_each(|(ent, _, _)| {
+            commands.entity(ent).despawn_recursive();
+        });
+}
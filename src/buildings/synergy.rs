@@ -0,0 +1,272 @@
+//! Bonuses for building things next to each other.
+//!
+//! **LOGIC:**
+//!
+//! The rules in `assets/data/synergy.ron` give a building a bonus for every matching neighbour
+//! in range, another building or a kind of terrain. Every time a building is added or removed
+//! each building's [Synergy] is worked out again:
+//!
+//! - [Synergy::drain_speed] makes a drain tower drain faster, [Synergy::drain_yield] gets it
+//!   more out of every bit it drains, see [super::drain]
+//! - [Synergy::generator_output] makes a burning generator put more into the grid
+//!
+//! While placing a building the bonuses it would get under the cursor are shown next to it.
+
+use bevy::prelude::*;
+use serde::Deserialize;
+
+use crate::{
+    game::{
+        map::{CurrentTileHover, MapData, TerrainTileType, TILE_SIZE},
+        placement::PlacementState,
+        power::{AddBuilding, RemoveBuilding},
+    },
+    global_systems::ui_util::GameFont,
+    AppState, Teardown, PLACEMENT_Z,
+};
+
+use super::BuildingType;
+
+pub struct SynergyPlugin;
+impl Plugin for SynergyPlugin {
+    fn build(&self, app: &mut App) {
+        let rules: SynergyRules = ron::from_str(include_str!("../../assets/data/synergy.ron"))
+            .expect("Failed to parse synergy.ron!");
+
+        app.insert_resource(rules)
+            .add_systems(OnEnter(AppState::Gameplay), setup_preview_label)
+            .add_systems(
+                Update,
+                (preview_synergy).run_if(in_state(AppState::Gameplay)),
+            )
+            // after buildings spawned or destroyed this frame actually exist or are gone
+            .add_systems(
+                PostUpdate,
+                (update_synergies).run_if(in_state(AppState::Gameplay)),
+            );
+    }
+}
+
+/// What a [SynergyRule] looks for around a building
+#[derive(Clone, Debug, Deserialize)]
+pub enum Neighbour {
+    Building(BuildingType),
+    /// Counted per tile
+    Terrain(TerrainTileType),
+}
+
+impl Neighbour {
+    fn name(&self) -> String {
+        match self {
+            Neighbour::Building(building) => building.name().to_string(),
+            Neighbour::Terrain(terrain) => format!("{:?} tile", terrain),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Deserialize)]
+pub enum SynergyEffect {
+    DrainSpeed(f32),
+    DrainYield(f32),
+    GeneratorOutput(f32),
+}
+
+impl SynergyEffect {
+    fn value(&self) -> f32 {
+        match self {
+            SynergyEffect::DrainSpeed(value)
+            | SynergyEffect::DrainYield(value)
+            | SynergyEffect::GeneratorOutput(value) => *value,
+        }
+    }
+
+    fn describe(&self, bonus: f32) -> String {
+        let what = match self {
+            SynergyEffect::DrainSpeed(_) => "drain speed",
+            SynergyEffect::DrainYield(_) => "drain yield",
+            SynergyEffect::GeneratorOutput(_) => "power output",
+        };
+        format!("+{:.0}% {}", bonus * 100.0, what)
+    }
+}
+
+fn no_falloff() -> f32 {
+    1.0
+}
+
+/// One entry of `assets/data/synergy.ron`
+#[derive(Clone, Debug, Deserialize)]
+pub struct SynergyRule {
+    pub building: BuildingType,
+    pub near: Neighbour,
+    /// In tiles
+    pub range: f32,
+    /// What a single neighbour is worth
+    pub effect: SynergyEffect,
+    /// The most neighbours that count
+    pub max_stacks: u32,
+    /// Each neighbour after the first counts this much of the one before it
+    #[serde(default = "no_falloff")]
+    pub falloff: f32,
+}
+
+#[derive(Resource, Deserialize)]
+pub struct SynergyRules {
+    pub rules: Vec<SynergyRule>,
+}
+
+impl SynergyRules {
+    /// The bonuses a `building` standing at `pos` gets from the buildings around it and the
+    /// terrain. `buildings` may include the building itself, nothing else can stand on the
+    /// same spot.
+    pub fn evaluate(
+        &self,
+        map: &MapData,
+        building: &BuildingType,
+        pos: Vec2,
+        buildings: &[(BuildingType, Vec2)],
+    ) -> Synergy {
+        let mut synergy = Synergy::default();
+        self.rules
+            .iter()
+            .filter(|rule| &rule.building == building)
+            .for_each(|rule| {
+                let found = match &rule.near {
+                    Neighbour::Building(near) => buildings
+                        .iter()
+                        .filter(|(other, other_pos)| {
+                            other == near
+                                && *other_pos != pos
+                                && other_pos.distance(pos) <= rule.range * TILE_SIZE
+                        })
+                        .count(),
+                    Neighbour::Terrain(terrain) => map.building_tile(pos).map_or(0, |tile| {
+                        map.tiles_in_radius(&tile, rule.range.round() as u32)
+                            .iter()
+                            .filter(|tile| map.terrain(tile) == *terrain)
+                            .count()
+                    }),
+                } as u32;
+                let stacks = found.min(rule.max_stacks);
+                if stacks == 0 {
+                    return;
+                }
+
+                let bonus: f32 = (0..stacks)
+                    .map(|stack| rule.effect.value() * rule.falloff.powi(stack as i32))
+                    .sum();
+                match rule.effect {
+                    SynergyEffect::DrainSpeed(_) => synergy.drain_speed += bonus,
+                    SynergyEffect::DrainYield(_) => synergy.drain_yield += bonus,
+                    SynergyEffect::GeneratorOutput(_) => synergy.generator_output += bonus,
+                }
+                synergy.sources.push(format!(
+                    "{} ({} x{})",
+                    rule.effect.describe(bonus),
+                    rule.near.name(),
+                    stacks
+                ));
+            });
+        synergy
+    }
+}
+
+/// Bonuses a building gets from what's around it, as fractions on top of the normal rate
+#[derive(Component, Clone, Debug, Default, PartialEq)]
+pub struct Synergy {
+    pub drain_speed: f32,
+    pub drain_yield: f32,
+    pub generator_output: f32,
+    /// Every bonus and what it's from, for showing the player
+    pub sources: Vec<String>,
+}
+
+fn update_synergies(
+    mut commands: Commands,
+    tower_spawned: EventReader<AddBuilding>,
+    tower_removed: EventReader<RemoveBuilding>,
+    rules: Res<SynergyRules>,
+    map: Res<MapData>,
+    q_buildings: Query<(Entity, &BuildingType, &Transform, Option<&Synergy>)>,
+) {
+    if tower_spawned.is_empty() && tower_removed.is_empty() {
+        return;
+    }
+
+    let buildings: Vec<_> = q_buildings
+        .iter()
+        .map(|(_, building, tf, _)| (building.clone(), tf.translation.truncate()))
+        .collect();
+    q_buildings.iter().for_each(|(ent, building, tf, current)| {
+        let synergy = rules.evaluate(&map, building, tf.translation.truncate(), &buildings);
+        if current != Some(&synergy) {
+            commands.entity(ent).insert(synergy);
+        }
+    });
+}
+
+/// Lists the bonuses the building being placed would get under the cursor
+#[derive(Component)]
+struct SynergyPreviewLabel;
+
+fn setup_preview_label(mut commands: Commands, font: Res<GameFont>) {
+    commands.spawn((
+        Text2dBundle {
+            text: Text::from_section(
+                "",
+                TextStyle {
+                    font: font.0.clone(),
+                    font_size: 18.0,
+                    color: Color::rgb(0.6, 1.0, 0.6),
+                },
+            ),
+            visibility: Visibility::Hidden,
+            ..default()
+        },
+        SynergyPreviewLabel,
+        Teardown,
+    ));
+}
+
+fn preview_synergy(
+    placement: Res<PlacementState>,
+    tile_hover: Res<CurrentTileHover>,
+    rules: Res<SynergyRules>,
+    map: Res<MapData>,
+    q_buildings: Query<(&BuildingType, &Transform)>,
+    mut q_label: Query<
+        (&mut Text, &mut Transform, &mut Visibility),
+        (With<SynergyPreviewLabel>, Without<BuildingType>),
+    >,
+) {
+    let Ok((mut text, mut label_tf, mut label_vis)) = q_label.get_single_mut() else {
+        return;
+    };
+    let preview = placement
+        .being_placed_building_type
+        .as_ref()
+        .zip(tile_hover.tile_pos)
+        .map(|(building, tile)| {
+            // where it would actually stand, not wherever on the tile the cursor is
+            let pos = map.building_pos(&tile);
+            let buildings: Vec<_> = q_buildings
+                .iter()
+                .map(|(building, tf)| (building.clone(), tf.translation.truncate()))
+                .collect();
+            (rules.evaluate(&map, building, pos, &buildings), pos)
+        })
+        .filter(|(synergy, _)| !synergy.sources.is_empty());
+    let Some((synergy, pos)) = preview else {
+        if *label_vis != Visibility::Hidden {
+            *label_vis = Visibility::Hidden;
+        }
+        return;
+    };
+
+    let value = synergy.sources.join("\n");
+    if text.sections[0].value != value {
+        text.sections[0].value = value;
+    }
+    label_tf.translation = (pos - Vec2::new(0.0, TILE_SIZE * 1.5)).extend(PLACEMENT_Z);
+    *label_vis = Visibility::Visible;
+}
@@ -0,0 +1,472 @@
+//! Buildings that actively fight the flora, rather than slowly draining it.
+//!
+//! **LOGIC:**
+//!
+//! Every powered [Turret] waits out its cooldown, picks the closest target in range and fires
+//! [Projectile]s at it:
+//!
+//! - the [HerbicideSprayer] sprays a cone of droplets that hurt every tree they pass through
+//! - the [Flamethrower] lobs a fireball that sets the first tree it hits [OnFire], and fire
+//!   spreads to close trees
+//! - the [SeedInterceptor] shoots airborne [Seed]s out of the sky before they can germinate
+
+use bevy::prelude::*;
+use rand::Rng;
+
+use crate::{
+    creeps::{cleanup_dead_creeps, lifecycle::Seed},
+    game::{
+        map::MapData,
+        power::{IsPowered, PowerDraw, PowerGrid, PowerPriority, RequiresPower},
+        resources::{Cost, ResourceType},
+    },
+    AppState, Health, Teardown, Tree, CREEP_Z,
+};
+
+use super::{barrier::BarrierMap, BuildingDefinition};
+
+const SPRAYER_RANGE: f32 = 350.0;
+const SPRAYER_COOLDOWN: f32 = 1.5;
+/// Droplets per spray, spread evenly over [SPRAY_CONE]
+const SPRAY_DROPLETS: usize = 5;
+/// Full width of the spray cone, in radians
+const SPRAY_CONE: f32 = 0.9;
+const SPRAY_SPEED: f32 = 300.0;
+/// Damage a droplet does to every tree it passes through
+const SPRAY_DAMAGE: u32 = 8;
+const SPRAY_HIT_RADIUS: f32 = 20.0;
+
+const FLAMETHROWER_RANGE: f32 = 250.0;
+const FLAMETHROWER_COOLDOWN: f32 = 2.0;
+const FIREBALL_SPEED: f32 = 250.0;
+const FIREBALL_DAMAGE: u32 = 20;
+const FIREBALL_HIT_RADIUS: f32 = 16.0;
+/// Every *this* many seconds burning trees take damage and may spread the fire
+const FIRE_TICK_RATE: f32 = 0.5;
+/// Damage a burning tree takes each fire tick
+const FIRE_DPT: u32 = 4;
+/// Fire ticks a tree burns for once lit
+const FIRE_DURATION: u32 = 10;
+/// Trees this close to a burning tree can catch fire
+const FIRE_SPREAD_RANGE: f32 = 60.0;
+/// Chance for each close tree to catch fire each fire tick
+const FIRE_SPREAD_CHANCE: f64 = 0.08;
+
+const INTERCEPTOR_RANGE: f32 = 400.0;
+const INTERCEPTOR_COOLDOWN: f32 = 0.6;
+const FLAK_SPEED: f32 = 500.0;
+const FLAK_REACH: f32 = 8.0;
+
+const PROJECTILE_Z: f32 = CREEP_Z + 0.2;
+
+pub struct TurretsPlugin;
+impl Plugin for TurretsPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(FireTick(Timer::from_seconds(
+            FIRE_TICK_RATE,
+            TimerMode::Repeating,
+        )))
+        .add_systems(
+            Update,
+            (aim_turrets, fly_projectiles, burn_trees)
+                .chain()
+                // lighting trees up before the dead ones are cleared away, not after
+                .before(cleanup_dead_creeps)
+                .run_if(in_state(AppState::Gameplay)),
+        );
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TurretKind {
+    HerbicideSprayer,
+    Flamethrower,
+    SeedInterceptor,
+}
+
+impl TurretKind {
+    fn range(&self) -> f32 {
+        match self {
+            TurretKind::HerbicideSprayer => SPRAYER_RANGE,
+            TurretKind::Flamethrower => FLAMETHROWER_RANGE,
+            TurretKind::SeedInterceptor => INTERCEPTOR_RANGE,
+        }
+    }
+
+    fn cooldown(&self) -> f32 {
+        match self {
+            TurretKind::HerbicideSprayer => SPRAYER_COOLDOWN,
+            TurretKind::Flamethrower => FLAMETHROWER_COOLDOWN,
+            TurretKind::SeedInterceptor => INTERCEPTOR_COOLDOWN,
+        }
+    }
+
+    fn tint(&self) -> Color {
+        match self {
+            TurretKind::HerbicideSprayer => Color::rgb(0.6, 1.0, 0.4),
+            TurretKind::Flamethrower => Color::rgb(1.0, 0.5, 0.2),
+            TurretKind::SeedInterceptor => Color::rgb(0.5, 0.8, 1.0),
+        }
+    }
+}
+
+#[derive(Component)]
+pub struct Turret {
+    pub kind: TurretKind,
+    cooldown: Timer,
+}
+
+impl Turret {
+    fn new(kind: TurretKind) -> Self {
+        Self {
+            kind,
+            cooldown: Timer::from_seconds(kind.cooldown(), TimerMode::Once),
+        }
+    }
+
+    /// Everything a turret of this kind needs on top of the basic building
+    fn components(kind: TurretKind, draw: f32) -> impl Bundle {
+        (
+            RequiresPower,
+            PowerDraw(draw),
+            Turret::new(kind),
+            Sprite {
+                color: kind.tint(),
+                ..default()
+            },
+        )
+    }
+}
+
+#[derive(Default)]
+pub struct HerbicideSprayer;
+impl BuildingDefinition for HerbicideSprayer {
+    const SPRITE_PATH: &'static str = "textures/tower_single.png";
+    const BASE_HEALTH: u32 = 100;
+    const COST: Cost = &[
+        (ResourceType::CorporationPoints, 200),
+        (ResourceType::Biomass, 10),
+    ];
+    const BUILD_TIME: u32 = 5;
+    const UPKEEP: u32 = 2;
+    const NAME: &'static str = "Herbicide Sprayer";
+    const DESCRIPTION: &'static str = "Sprays a cone of herbicide that hurts every tree it
+        touches.";
+
+    fn add_extra_components(commands: &mut Commands, ent_id: Entity) {
+        commands
+            .entity(ent_id)
+            .insert(Turret::components(TurretKind::HerbicideSprayer, 3.0));
+    }
+}
+
+#[derive(Default)]
+pub struct Flamethrower;
+impl BuildingDefinition for Flamethrower {
+    const SPRITE_PATH: &'static str = "textures/tower_single.png";
+    const BASE_HEALTH: u32 = 120;
+    const COST: Cost = &[
+        (ResourceType::CorporationPoints, 300),
+        (ResourceType::Wood, 20),
+    ];
+    const BUILD_TIME: u32 = 6;
+    const UPKEEP: u32 = 3;
+    const NAME: &'static str = "Flamethrower";
+    const DESCRIPTION: &'static str = "Sets trees on fire, and fire spreads between
+        close trees.";
+
+    fn add_extra_components(commands: &mut Commands, ent_id: Entity) {
+        commands
+            .entity(ent_id)
+            .insert(Turret::components(TurretKind::Flamethrower, 4.0));
+    }
+}
+
+#[derive(Default)]
+pub struct SeedInterceptor;
+impl BuildingDefinition for SeedInterceptor {
+    const SPRITE_PATH: &'static str = "textures/tower_single.png";
+    const BASE_HEALTH: u32 = 80;
+    const COST: Cost = &[
+        (ResourceType::CorporationPoints, 250),
+        (ResourceType::Minerals, 15),
+    ];
+    const BUILD_TIME: u32 = 5;
+    const UPKEEP: u32 = 2;
+    const NAME: &'static str = "Seed Interceptor";
+    const DESCRIPTION: &'static str = "Shoots seeds out of the air before they can take
+        root.";
+
+    fn add_extra_components(commands: &mut Commands, ent_id: Entity) {
+        commands
+            .entity(ent_id)
+            .insert(Turret::components(TurretKind::SeedInterceptor, 2.0));
+    }
+}
+
+/// What a projectile does when it hits
+enum Payload {
+    /// Hurts every tree it passes through, but each only once
+    Spray { hit: Vec<Entity> },
+    /// Bursts on the first tree it hits and sets it [OnFire]
+    Fireball,
+    /// Homes in on a [Seed]
+    Flak { target: Entity },
+}
+
+#[derive(Component)]
+pub struct Projectile {
+    velocity: Vec2,
+    lifetime: Timer,
+    payload: Payload,
+}
+
+/// A burning tree, hurts itself and the trees around it every [FIRE_TICK_RATE]
+#[derive(Component)]
+pub struct OnFire {
+    ticks_left: u32,
+}
+
+impl Default for OnFire {
+    fn default() -> Self {
+        Self {
+            ticks_left: FIRE_DURATION,
+        }
+    }
+}
+
+#[derive(Resource)]
+struct FireTick(Timer);
+
+fn spawn_projectile(
+    commands: &mut Commands,
+    pos: Vec2,
+    velocity: Vec2,
+    range: f32,
+    payload: Payload,
+    color: Color,
+    size: f32,
+) {
+    commands.spawn((
+        SpriteBundle {
+            sprite: Sprite {
+                color,
+                custom_size: Some(Vec2::splat(size)),
+                ..default()
+            },
+            transform: Transform::from_xyz(pos.x, pos.y, PROJECTILE_Z),
+            ..default()
+        },
+        Projectile {
+            velocity,
+            lifetime: Timer::from_seconds(range / velocity.length(), TimerMode::Once),
+            payload,
+        },
+        Teardown,
+    ));
+}
+
+fn closest_in_range<'a>(
+    pos: Vec2,
+    range: f32,
+    targets: impl Iterator<Item = (Entity, &'a Transform)>,
+) -> Option<(Entity, Vec2)> {
+    targets
+        .map(|(ent, tf)| (ent, tf.translation.truncate()))
+        .filter(|(_, target)| target.distance(pos) < range)
+        .min_by(|(_, a), (_, b)| a.distance(pos).total_cmp(&b.distance(pos)))
+}
+
+/// Powered turrets fire at the closest thing in range once their cooldown is up. A struggling
+/// power network makes the cooldowns longer.
+fn aim_turrets(
+    mut commands: Commands,
+    time: Res<Time>,
+    grid: Res<PowerGrid>,
+    mut q_turrets: Query<(&mut Turret, &Transform, Option<&PowerPriority>), With<IsPowered>>,
+    q_trees: Query<(Entity, &Transform, &Health), (With<Tree>, Without<OnFire>)>,
+    q_seeds: Query<(Entity, &Transform), With<Seed>>,
+) {
+    q_turrets
+        .iter_mut()
+        .for_each(|(mut turret, turret_tf, priority)| {
+            if !turret
+                .cooldown
+                .tick(time.delta().mul_f32(grid.satisfaction_of(priority)))
+                .finished()
+            {
+                return;
+            }
+
+            let pos = turret_tf.translation.truncate();
+            let range = turret.kind.range();
+            let living_trees = q_trees
+                .iter()
+                .filter(|(_, _, hp)| hp.0 > 0)
+                .map(|(ent, tf, _)| (ent, tf));
+            let target = match turret.kind {
+                TurretKind::SeedInterceptor => closest_in_range(pos, range, q_seeds.iter()),
+                _ => closest_in_range(pos, range, living_trees),
+            };
+            let Some((target, target_pos)) = target else {
+                return;
+            };
+
+            let aim = (target_pos - pos).normalize_or_zero();
+            match turret.kind {
+                TurretKind::HerbicideSprayer => {
+                    (0..SPRAY_DROPLETS).for_each(|i| {
+                        let offset = SPRAY_CONE * (i as f32 / (SPRAY_DROPLETS - 1) as f32 - 0.5);
+                        spawn_projectile(
+                            &mut commands,
+                            pos,
+                            Vec2::from_angle(offset).rotate(aim) * SPRAY_SPEED,
+                            range,
+                            Payload::Spray { hit: vec![] },
+                            Color::rgba(0.6, 1.0, 0.4, 0.8),
+                            6.0,
+                        );
+                    });
+                }
+                TurretKind::Flamethrower => spawn_projectile(
+                    &mut commands,
+                    pos,
+                    aim * FIREBALL_SPEED,
+                    range,
+                    Payload::Fireball,
+                    Color::ORANGE,
+                    8.0,
+                ),
+                TurretKind::SeedInterceptor => spawn_projectile(
+                    &mut commands,
+                    pos,
+                    aim * FLAK_SPEED,
+                    range * 1.5,
+                    Payload::Flak { target },
+                    Color::CYAN,
+                    3.0,
+                ),
+            }
+            turret.cooldown.reset();
+        });
+}
+
+/// Moves projectiles along and applies whatever they hit
+fn fly_projectiles(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut q_projectiles: Query<(Entity, &mut Projectile, &mut Transform)>,
+    mut q_trees: Query<(Entity, &Transform, &mut Health), (With<Tree>, Without<Projectile>)>,
+    q_seeds: Query<&Transform, (With<Seed>, Without<Projectile>)>,
+) {
+    q_projectiles
+        .iter_mut()
+        .for_each(|(ent, mut projectile, mut tf)| {
+            if projectile.lifetime.tick(time.delta()).finished() {
+                commands.entity(ent).despawn_recursive();
+                return;
+            }
+
+            // flak steers towards its seed, which is being blown about by the wind
+            if let Payload::Flak { target } = projectile.payload {
+                let Ok(seed_tf) = q_seeds.get(target) else {
+                    commands.entity(ent).despawn_recursive();
+                    return;
+                };
+                let to_seed = seed_tf.translation.truncate() - tf.translation.truncate();
+                if to_seed.length() < FLAK_REACH {
+                    commands.entity(target).despawn_recursive();
+                    commands.entity(ent).despawn_recursive();
+                    return;
+                }
+                projectile.velocity = to_seed.normalize() * FLAK_SPEED;
+            }
+            tf.translation += (projectile.velocity * time.delta_seconds()).extend(0.0);
+            let pos = tf.translation.truncate();
+
+            match &mut projectile.payload {
+                Payload::Spray { hit } => {
+                    q_trees
+                        .iter_mut()
+                        .filter(|(tree, tree_tf, _)| {
+                            !hit.contains(tree)
+                                && tree_tf.translation.truncate().distance(pos) < SPRAY_HIT_RADIUS
+                        })
+                        .for_each(|(tree, _, mut hp)| {
+                            hp.deduct(SPRAY_DAMAGE);
+                            hit.push(tree);
+                        });
+                }
+                Payload::Fireball => {
+                    let struck = q_trees.iter_mut().find(|(_, tree_tf, hp)| {
+                        hp.0 > 0
+                            && tree_tf.translation.truncate().distance(pos) < FIREBALL_HIT_RADIUS
+                    });
+                    if let Some((tree, _, mut hp)) = struck {
+                        hp.deduct(FIREBALL_DAMAGE);
+                        // nothing left to burn once it's dead
+                        if hp.0 > 0 {
+                            commands.entity(tree).insert(OnFire::default());
+                        }
+                        commands.entity(ent).despawn_recursive();
+                    }
+                }
+                Payload::Flak { .. } => {}
+            }
+        });
+}
+
+/// Burning trees hurt themselves and might light up their neighbours, unless there's a
+/// firebreak in the way
+fn burn_trees(
+    mut commands: Commands,
+    mut timer: ResMut<FireTick>,
+    time: Res<Time>,
+    map: Res<MapData>,
+    barriers: Res<BarrierMap>,
+    mut q_burning: Query<(
+        Entity,
+        &mut OnFire,
+        &mut Health,
+        &Transform,
+        &mut TextureAtlasSprite,
+    )>,
+    q_trees: Query<(Entity, &Transform, &Health), (With<Tree>, Without<OnFire>)>,
+) {
+    // burning trees glow
+    q_burning.iter_mut().for_each(|(_, _, _, _, mut sprite)| {
+        sprite.color = Color::rgb(1.0, 0.55, 0.3);
+    });
+    if !timer.0.tick(time.delta()).just_finished() {
+        return;
+    }
+
+    let mut rng = rand::thread_rng();
+    q_burning
+        .iter_mut()
+        .for_each(|(ent, mut fire, mut hp, tf, mut sprite)| {
+            hp.deduct(FIRE_DPT);
+            fire.ticks_left = fire.ticks_left.saturating_sub(1);
+            if fire.ticks_left == 0 {
+                sprite.color = Color::WHITE;
+                commands.entity(ent).remove::<OnFire>();
+            }
+
+            q_trees
+                .iter()
+                .filter(|(_, tree_tf, tree_hp)| {
+                    tree_hp.0 > 0
+                        && tree_tf.translation.distance(tf.translation) < FIRE_SPREAD_RANGE
+                        && !barriers.blocks_fire(
+                            &map,
+                            tf.translation.truncate(),
+                            tree_tf.translation.truncate(),
+                        )
+                })
+                .for_each(|(tree, _, _)| {
+                    if rng.gen_bool(FIRE_SPREAD_CHANCE) {
+                        commands.entity(tree).insert(OnFire::default());
+                    }
+                });
+        });
+}
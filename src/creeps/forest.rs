@@ -0,0 +1,120 @@
+//! Where the initial forest grows. Trees are placed by sampling the terrain under each tile
+//! and a clustering noise on top, so they form groves instead of an even carpet.
+
+use bevy::prelude::*;
+use bevy_ecs_tilemap::prelude::*;
+use rand::{rngs::StdRng, Rng, SeedableRng};
+
+use crate::game::map::{MapData, TerrainTileType, TILE_SIZE};
+
+use super::TreeSpecies;
+
+/// Size of a grove, in tiles
+const GROVE_SCALE: f32 = 24.0;
+/// Noise below this never has trees, above [GROVE_FULL] is as dense as the terrain allows
+const GROVE_EDGE: f32 = 0.4;
+const GROVE_FULL: f32 = 0.75;
+
+impl TerrainTileType {
+    /// Chance of a tile in the middle of a grove having a tree
+    fn forest_density(&self) -> f64 {
+        match self {
+            TerrainTileType::Grass => 0.3,
+            TerrainTileType::Dirt => 0.06,
+            TerrainTileType::Sand => 0.01,
+            TerrainTileType::Rock => 0.0,
+        }
+    }
+
+    /// Which species grow here, with relative weights
+    fn species(&self) -> &'static [(TreeSpecies, u32)] {
+        match self {
+            TerrainTileType::Grass => &[(TreeSpecies::Amberleaf, 3), (TreeSpecies::Bluecap, 2)],
+            TerrainTileType::Dirt => &[(TreeSpecies::Pinkbloom, 3), (TreeSpecies::Amberleaf, 1)],
+            TerrainTileType::Sand => &[(TreeSpecies::Bluecap, 1)],
+            TerrainTileType::Rock => &[],
+        }
+    }
+}
+
+/// Places the initial forest for a map without one, returning the (world position, species) of
+/// every tree. Nothing grows within `clearing_radius` of the core.
+pub fn initial_forest(map: &MapData, seed: u64, clearing_radius: f32) -> Vec<(Vec2, TreeSpecies)> {
+    let mut rng = StdRng::seed_from_u64(seed);
+    let noise_seed = rng.gen();
+    let core = map.core_world_pos();
+
+    (0..map.size.y)
+        .flat_map(|y| (0..map.size.x).map(move |x| TilePos { x, y }))
+        .filter_map(|tile| {
+            let terrain = map.terrain(&tile);
+            let grove = value_noise(
+                noise_seed,
+                tile.x as f32 / GROVE_SCALE,
+                tile.y as f32 / GROVE_SCALE,
+            );
+            let density =
+                terrain.forest_density() * smoothstep(GROVE_EDGE, GROVE_FULL, grove) as f64;
+            if density <= 0.0 || !rng.gen_bool(density) {
+                return None;
+            }
+
+            // jitter inside the tile so the forest doesn't look like a grid
+            let jitter = Vec2::new(rng.gen_range(-0.4..0.4), rng.gen_range(-0.4..0.4)) * TILE_SIZE;
+            let pos = map.tile_center(&tile) + jitter;
+            if pos.distance(core) <= clearing_radius {
+                return None;
+            }
+
+            pick_weighted(terrain.species(), &mut rng).map(|species| (pos, species))
+        })
+        .collect()
+}
+
+fn pick_weighted(options: &[(TreeSpecies, u32)], rng: &mut impl Rng) -> Option<TreeSpecies> {
+    let total: u32 = options.iter().map(|(_, weight)| weight).sum();
+    if total == 0 {
+        return None;
+    }
+
+    let mut pick = rng.gen_range(0..total);
+    options.iter().find_map(|(species, weight)| {
+        if pick < *weight {
+            Some(*species)
+        } else {
+            pick -= weight;
+            None
+        }
+    })
+}
+
+fn smoothstep(edge0: f32, edge1: f32, x: f32) -> f32 {
+    let t = ((x - edge0) / (edge1 - edge0)).clamp(0.0, 1.0);
+    t * t * (3.0 - 2.0 * t)
+}
+
+/// Smoothly interpolated random values on an integer lattice, in 0.0 - 1.0
+fn value_noise(seed: u64, x: f32, y: f32) -> f32 {
+    let (x0, y0) = (x.floor(), y.floor());
+    let (tx, ty) = (smoothstep(0.0, 1.0, x - x0), smoothstep(0.0, 1.0, y - y0));
+    let (x0, y0) = (x0 as i64, y0 as i64);
+
+    let top = lerp(lattice(seed, x0, y0), lattice(seed, x0 + 1, y0), tx);
+    let bottom = lerp(lattice(seed, x0, y0 + 1), lattice(seed, x0 + 1, y0 + 1), tx);
+    lerp(top, bottom, ty)
+}
+
+fn lerp(a: f32, b: f32, t: f32) -> f32 {
+    a + (b - a) * t
+}
+
+/// Hashes a lattice point to a random value in 0.0 - 1.0
+fn lattice(seed: u64, x: i64, y: i64) -> f32 {
+    let mut h = seed
+        ^ (x as u64).wrapping_mul(0x9E37_79B9_7F4A_7C15)
+        ^ (y as u64).wrapping_mul(0xC2B2_AE3D_27D4_EB4F);
+    h ^= h >> 33;
+    h = h.wrapping_mul(0xFF51_AFD7_ED55_8CCD);
+    h ^= h >> 33;
+    (h >> 40) as f32 / (1u64 << 24) as f32
+}
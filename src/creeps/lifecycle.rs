@@ -0,0 +1,204 @@
+//! The life cycle of a tree. Saplings grow up, mature trees get pollinated and release
+//! seeds, and seeds drift for a while before germinating into new saplings where they land.
+//!
+//! How fast all of that happens is down to the [WorldClock]'s season and the [Weather].
+
+use bevy::prelude::*;
+use rand::Rng;
+
+use crate::{
+    buildings::barrier::BarrierMap,
+    game::{
+        clock::{SeasonModifiers, WorldClock},
+        map::{MapData, WorldBounds},
+        occupancy::OccupancyGrid,
+        weather::{Weather, Wind},
+    },
+    AppState, Teardown, Tree, CREEP_Z,
+};
+
+use super::{can_sprout, spawn_creep, SpawnCreep, TreeSpecies};
+
+/// Seconds a sapling takes to mature at a growth modifier of 1.0
+const GROWTH_TIME: f32 = 45.0;
+/// How big a freshly sprouted sapling is compared to a mature tree
+const SAPLING_SCALE: f32 = 0.4;
+/// Every *this* many seconds mature trees may release seeds
+const SEEDING_TICK_RATE: f32 = 2.0;
+/// Chance for a mature tree to be pollinated each seeding tick, at a pollination modifier of 1.0
+const POLLINATION_CHANCE: f64 = 0.0005;
+/// Seeds released by a pollinated tree at a seeding modifier of 1.0
+const SEEDS_PER_TREE: f32 = 2.0;
+/// Fastest a seed drifts, in world units per second
+const SEED_SPEED: f32 = 40.0;
+/// Range of seconds a seed stays airborne for
+const SEED_FLIGHT_TIME: (f32, f32) = (2.0, 6.0);
+
+pub struct TreeLifecyclePlugin;
+impl Plugin for TreeLifecyclePlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(SeedingTick(Timer::from_seconds(
+            SEEDING_TICK_RATE,
+            TimerMode::Repeating,
+        )))
+        .add_systems(
+            Update,
+            (grow_saplings, release_seeds, fly_seeds).run_if(in_state(AppState::Gameplay)),
+        );
+    }
+}
+
+/// A tree that hasn't matured yet, so it can't be pollinated
+#[derive(Component, Default)]
+pub struct Sapling {
+    /// 0.0 is freshly sprouted, 1.0 is fully grown
+    pub progress: f32,
+}
+
+/// An airborne seed, it germinates into a [Sapling] wherever it lands
+#[derive(Component)]
+pub struct Seed {
+    pub species: TreeSpecies,
+    pub velocity: Vec2,
+    pub flight: Timer,
+}
+
+#[derive(Resource)]
+struct SeedingTick(Timer);
+
+/// The season's effect on the plants, made better or worse by the weather
+fn plant_modifiers(clock: &WorldClock, weather: Option<&Weather>) -> SeasonModifiers {
+    let modifiers = clock.modifiers();
+    weather.map_or(modifiers, |weather| weather.modify(modifiers))
+}
+
+fn grow_saplings(
+    mut commands: Commands,
+    clock: Res<WorldClock>,
+    weather: Option<Res<Weather>>,
+    time: Res<Time>,
+    mut q_saplings: Query<(Entity, &mut Sapling, &mut Transform)>,
+) {
+    let modifiers = plant_modifiers(&clock, weather.as_deref());
+    let growth = modifiers.growth * time.delta_seconds() / GROWTH_TIME;
+
+    q_saplings
+        .iter_mut()
+        .for_each(|(ent, mut sapling, mut transform)| {
+            sapling.progress = (sapling.progress + growth).min(1.0);
+            let scale = SAPLING_SCALE + (1.0 - SAPLING_SCALE) * sapling.progress;
+            transform.scale = Vec3::new(scale, scale, 1.0);
+
+            if sapling.progress >= 1.0 {
+                commands.entity(ent).remove::<Sapling>();
+            }
+        });
+}
+
+/// Every [SEEDING_TICK_RATE] some mature trees get pollinated and scatter seeds
+fn release_seeds(
+    mut commands: Commands,
+    mut timer: ResMut<SeedingTick>,
+    time: Res<Time>,
+    clock: Res<WorldClock>,
+    weather: Option<Res<Weather>>,
+    q_trees: Query<(&Transform, &TreeSpecies), (With<Tree>, Without<Sapling>)>,
+) {
+    if !timer.0.tick(time.delta()).just_finished() {
+        return;
+    }
+
+    let modifiers = plant_modifiers(&clock, weather.as_deref());
+    let chance = (POLLINATION_CHANCE * modifiers.pollination as f64).min(1.0);
+    let seeds_per_tree = (SEEDS_PER_TREE * modifiers.seeding).round() as u32;
+    if chance <= 0.0 || seeds_per_tree == 0 {
+        return;
+    }
+
+    let mut rng = rand::thread_rng();
+    q_trees.iter().for_each(|(tree_tf, species)| {
+        if rng.gen_bool(chance) {
+            (0..seeds_per_tree).for_each(|_| {
+                spawn_seed(
+                    &mut commands,
+                    tree_tf.translation.truncate(),
+                    *species,
+                    &mut rng,
+                )
+            });
+        }
+    });
+}
+
+pub fn spawn_seed(commands: &mut Commands, pos: Vec2, species: TreeSpecies, rng: &mut impl Rng) {
+    let angle = rng.gen_range(0.0..std::f32::consts::TAU);
+    let speed = rng.gen_range(0.25..=1.0) * SEED_SPEED;
+
+    commands.spawn((
+        SpriteBundle {
+            sprite: Sprite {
+                color: Color::rgb(0.95, 0.9, 0.5),
+                custom_size: Some(Vec2::new(4.0, 4.0)),
+                ..default()
+            },
+            transform: Transform::from_xyz(pos.x, pos.y, CREEP_Z + 0.1),
+            ..default()
+        },
+        Seed {
+            species,
+            velocity: Vec2::from_angle(angle) * speed,
+            flight: Timer::from_seconds(
+                rng.gen_range(SEED_FLIGHT_TIME.0..=SEED_FLIGHT_TIME.1),
+                TimerMode::Once,
+            ),
+        },
+        Teardown,
+    ));
+}
+
+/// Blows seeds along and germinates the ones that land somewhere they're allowed to grow.
+/// Seeds that drift into a wall are stopped.
+#[allow(clippy::too_many_arguments)]
+fn fly_seeds(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    mut texture_atlases: ResMut<Assets<TextureAtlas>>,
+    mut spawn_ev: EventWriter<SpawnCreep>,
+    time: Res<Time>,
+    bounds: Res<WorldBounds>,
+    map: Res<MapData>,
+    barriers: Res<BarrierMap>,
+    occupancy: Res<OccupancyGrid>,
+    wind: Res<Wind>,
+    mut q_seeds: Query<(Entity, &mut Seed, &mut Transform)>,
+) {
+    q_seeds
+        .iter_mut()
+        .for_each(|(ent, mut seed, mut transform)| {
+            let velocity = seed.velocity + wind.velocity();
+            transform.translation += (velocity * time.delta_seconds()).extend(0.0);
+
+            // seeds that hit a wall just drop there, and nothing grows on walls
+            if barriers.blocks_seeds(&map, transform.translation.truncate()) {
+                commands.entity(ent).despawn_recursive();
+                return;
+            }
+
+            if seed.flight.tick(time.delta()).just_finished() {
+                commands.entity(ent).despawn_recursive();
+
+                let landed = transform.translation.truncate();
+                if can_sprout(&bounds, &map, &occupancy, landed) {
+                    let tree = spawn_creep(
+                        &mut commands,
+                        &asset_server,
+                        &mut texture_atlases,
+                        &mut spawn_ev,
+                        landed,
+                        seed.species,
+                    );
+                    commands.entity(tree).insert(Sapling::default());
+                }
+            }
+        });
+}
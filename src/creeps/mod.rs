@@ -1,18 +1,31 @@
 //! Creeps are the enemy! They are also known as "Tree"s.
-use std::{ops::ControlFlow, time::Duration};
+use std::{collections::HashMap, ops::ControlFlow, time::Duration};
+
+pub mod forest;
+pub mod lifecycle;
 
 use crate::{
-    buildings::Building,
+    buildings::{
+        drain::{DrainTower, TreeDrained},
+        Building,
+    },
     game::{
+        clock::WorldClock,
         hp_bars::HpBarUISettings,
-        resources::{Harvest, ResourceType},
+        logistics::Stockpile,
+        map::{MapData, TerrainTileType, WorldBounds, TILE_SIZE},
+        occupancy::OccupancyGrid,
+        resources::ResourceType,
     },
     global_systems::eargasm::{AudioComponent, AudioRequest, Money},
     prelude::*,
-    Range, Teardown, CREEP_Z,
+    AppState, Difficulty, GameSeed, Range, Teardown, CREEP_Z,
 };
 use bevy::{asset::processor::ProcessorTransactionLog, ecs::bundle, prelude::*, time::Stopwatch};
 use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+use self::lifecycle::{Sapling, TreeLifecyclePlugin};
 
 /// Handles the setup, spawning, despawning, attacking of our 'creeps'.
 pub struct CreepPlugin;
@@ -20,9 +33,15 @@ impl Plugin for CreepPlugin {
     fn build(&self, app: &mut App) {
         app.add_event::<SpawnCreep>();
         app.add_event::<CreepDie>();
+        app.add_plugins(TreeLifecyclePlugin);
 
-        app.add_systems(Startup, (initial_creep_spawn, creep_spawning_timer))
-            .add_systems(Update, (cleanup_dead_creeps, periodically_spawn_creep));
+        app.add_systems(Startup, creep_spawning_timer)
+            .add_systems(OnEnter(AppState::Gameplay), initial_creep_spawn)
+            .add_systems(
+                Update,
+                (cleanup_dead_creeps, periodically_spawn_creep, rot_drops)
+                    .run_if(in_state(AppState::Gameplay)),
+            );
     }
 }
 
@@ -37,20 +56,134 @@ struct CreepSpawnTimer {
 #[derive(Resource)]
 struct TreeCreepAtlas(Handle<TextureAtlas>);
 
+/// The kinds of tree on the planet, each one is a row of variants in `textures/trees.png`
+#[derive(Component, Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum TreeSpecies {
+    #[default]
+    Amberleaf,
+    Pinkbloom,
+    Bluecap,
+}
+
+impl TreeSpecies {
+    pub const ALL: [TreeSpecies; 3] = [
+        TreeSpecies::Amberleaf,
+        TreeSpecies::Pinkbloom,
+        TreeSpecies::Bluecap,
+    ];
+
+    /// A random sprite from this species' row of the trees atlas
+    pub fn sprite_index(&self, rng: &mut impl Rng) -> usize {
+        let row = match self {
+            TreeSpecies::Amberleaf => 0,
+            TreeSpecies::Pinkbloom => 1,
+            TreeSpecies::Bluecap => 2,
+        };
+        row * 3 + rng.gen_range(0..3)
+    }
+
+    /// Aggressive trees tear down barriers they grow up against
+    pub fn is_aggressive(&self) -> bool {
+        *self == TreeSpecies::Pinkbloom
+    }
+
+    pub fn random(rng: &mut impl Rng) -> Self {
+        Self::ALL[rng.gen_range(0..Self::ALL.len())]
+    }
+
+    /// What a whole tree of this species is made of, on top of its [CorpoPoints]. Drain
+    /// towers get it bit by bit as they drain the tree.
+    pub fn base_yield(&self) -> &'static [(ResourceType, u32)] {
+        match self {
+            TreeSpecies::Amberleaf => &[(ResourceType::Wood, 4), (ResourceType::Biomass, 1)],
+            TreeSpecies::Pinkbloom => &[(ResourceType::Biomass, 3), (ResourceType::Wood, 1)],
+            TreeSpecies::Bluecap => &[(ResourceType::Minerals, 2), (ResourceType::Wood, 2)],
+        }
+    }
+
+    /// Whether a dying tree of this species leaves some [ResourceType::RareSpores] behind
+    pub fn roll_spores(&self, rng: &mut impl Rng) -> bool {
+        let spore_chance = match self {
+            TreeSpecies::Amberleaf => 0.01,
+            TreeSpecies::Pinkbloom => 0.1,
+            TreeSpecies::Bluecap => 0.03,
+        };
+        rng.gen_bool(spore_chance)
+    }
+
+    /// What a tree of this species gives up when it dies, on top of its [CorpoPoints]
+    pub fn yields(&self, rng: &mut impl Rng) -> Vec<(ResourceType, u32)> {
+        let mut yields = self.base_yield().to_vec();
+        if self.roll_spores(rng) {
+            yields.push((ResourceType::RareSpores, 1));
+        }
+        yields
+    }
+}
+
+/// Health every tree starts out with
+pub const TREE_HEALTH: u32 = 100;
+/// How much of a tree's health drain towers have already turned into resources, so whatever
+/// else kills it only leaves the rest behind
+#[derive(Component, Default)]
+pub struct Drained(pub u32);
+
+impl Drained {
+    /// Cuts `contents` down to the share of the tree that hasn't been drained yet
+    pub fn leftover(&self, contents: Vec<(ResourceType, u32)>) -> Vec<(ResourceType, u32)> {
+        let left = 1.0 - self.0.min(TREE_HEALTH) as f32 / TREE_HEALTH as f32;
+        contents
+            .into_iter()
+            .map(|(resource, amount)| (resource, (amount as f32 * left).round() as u32))
+            .filter(|(_, amount)| *amount > 0)
+            .collect()
+    }
+}
+
+/// Share of a tree's [CorpoPoints] paid out on top to the drain tower that finishes it off
+const KILL_BONUS: f32 = 0.25;
+
+/// New trees don't spawn this close to [crate::buildings::core::TheCore], the initial forest
+/// leaves a bigger clearing depending on the [Difficulty]
+const CORE_CLEARING_RADIUS: f32 = 240.0;
+
 /// System: Setup
+/// Hand made maps come with their own forest, otherwise it grows from the terrain, see
+/// [forest::initial_forest]
 fn initial_creep_spawn(
     mut commands: Commands,
     asset_server: Res<AssetServer>,
     mut texture_atlases: ResMut<Assets<TextureAtlas>>,
     mut spawn_ev: EventWriter<SpawnCreep>,
+    map: Res<MapData>,
+    seed: Res<GameSeed>,
+    difficulty: Res<Difficulty>,
 ) {
-    (0..10_000).for_each(|_| {
+    if !map.trees.is_empty() {
+        map.trees.iter().for_each(|tree| {
+            spawn_creep(
+                &mut commands,
+                &asset_server,
+                &mut texture_atlases,
+                &mut spawn_ev,
+                map.tile_center(&tree.tile),
+                tree.species,
+            );
+        });
+        return;
+    }
+
+    let forest = forest::initial_forest(&map, seed.0, difficulty.core_clearing_radius());
+    info!("Growing an initial forest of {} trees", forest.len());
+    forest.into_iter().for_each(|(pos, species)| {
         spawn_creep(
             &mut commands,
             &asset_server,
             &mut texture_atlases,
             &mut spawn_ev,
-        )
+            pos,
+            species,
+        );
     });
 }
 
@@ -60,7 +193,9 @@ fn creep_spawning_timer(mut commands: Commands) {
     });
 }
 /// System: Update
-/// Does what it says on the can...
+/// Does what it says on the can... New trees start out as [Sapling]s, and how many there
+/// are depends on the season.
+#[allow(clippy::too_many_arguments)]
 fn periodically_spawn_creep(
     mut commands: Commands,
     asset_server: Res<AssetServer>,
@@ -68,66 +203,110 @@ fn periodically_spawn_creep(
     mut creep_timer: ResMut<CreepSpawnTimer>,
     mut texture_atlases: ResMut<Assets<TextureAtlas>>,
     mut spawn_ev: EventWriter<SpawnCreep>,
+    bounds: Res<WorldBounds>,
+    map: Res<MapData>,
+    occupancy: Res<OccupancyGrid>,
+    clock: Res<WorldClock>,
 ) {
     creep_timer.timer.tick(time.delta());
     if creep_timer.timer.elapsed_secs() > 2.3 {
-        (0..80).for_each(|_| {
-            spawn_creep(
-                &mut commands,
-                &asset_server,
-                &mut texture_atlases,
-                &mut spawn_ev,
-            )
+        let mut rng = rand::thread_rng();
+        let count = (80.0 * clock.modifiers().spawn).round() as u32;
+        (0..count).for_each(|_| {
+            if let Some(pos) = random_spawn_pos(&bounds, &map, &occupancy, &mut rng) {
+                let tree = spawn_creep(
+                    &mut commands,
+                    &asset_server,
+                    &mut texture_atlases,
+                    &mut spawn_ev,
+                    pos,
+                    TreeSpecies::random(&mut rng),
+                );
+                commands.entity(tree).insert(Sapling::default());
+            }
         });
         creep_timer.timer.reset()
     }
 }
 
-fn spawn_creep(
-    commands: &mut Commands,
-    asset_server: &Res<AssetServer>,
-    texture_atlases: &mut ResMut<Assets<TextureAtlas>>,
-    spawn_ev: &mut EventWriter<SpawnCreep>,
-) {
-    const MAP_LIMIT: f32 = 8192.0;
+/// Somewhere a new tree may sprout, inside one of the map's [SpawnZone]s if it has any.
+/// None when the roll lands in the clearing around the core.
+fn random_spawn_pos(
+    bounds: &WorldBounds,
+    map: &MapData,
+    occupancy: &OccupancyGrid,
+    rng: &mut impl Rng,
+) -> Option<Vec2> {
+    let pos = if map.spawn_zones.is_empty() {
+        bounds.random_pos(rng)
+    } else {
+        let zone = &map.spawn_zones[rng.gen_range(0..map.spawn_zones.len())];
+        let radius = zone.radius as f32 * TILE_SIZE * rng.gen_range(0.0f32..1.0).sqrt();
+        let angle = rng.gen_range(0.0..std::f32::consts::TAU);
+        map.tile_center(&zone.center) + Vec2::from_angle(angle) * radius
+    };
 
-    let mut rng = rand::thread_rng();
-    let x = rng.gen_range(-MAP_LIMIT..=MAP_LIMIT);
-    let y = rng.gen_range(-MAP_LIMIT..=MAP_LIMIT);
+    can_sprout(bounds, map, occupancy, pos).then_some(pos)
+}
 
-    let current = Vec3::new(x, y, 0.0);
-    let target = Vec3::ZERO;
-    let direction = target - current;
+/// Trees only grow inside the world, never on rock or under buildings (barriers included), and
+/// never in the clearing around the core
+fn can_sprout(bounds: &WorldBounds, map: &MapData, occupancy: &OccupancyGrid, pos: Vec2) -> bool {
+    bounds.contains(pos)
+        && pos.distance(map.core_world_pos()) > CORE_CLEARING_RADIUS
+        && map.world_to_tile(pos).map_or(false, |tile| {
+            map.terrain(&tile) != TerrainTileType::Rock && occupancy.building_at(&tile).is_none()
+        })
+}
 
+/// Handle to the trees atlas, see [TreeSpecies::sprite_index] for what's in it
+pub fn tree_atlas(
+    asset_server: &AssetServer,
+    texture_atlases: &mut Assets<TextureAtlas>,
+) -> Handle<TextureAtlas> {
     let texture_handle = asset_server.load("textures/trees.png");
     let texture_atlas =
         TextureAtlas::from_grid(texture_handle, Vec2::new(32.0, 32.0), 3, 3, None, None);
-    let texture_atlas_handle = texture_atlases.add(texture_atlas);
+    texture_atlases.add(texture_atlas)
+}
 
-    if direction.length() > 240.0 {
-        let atlas_handle = texture_atlas_handle.clone();
-        let sprite_index = rng.gen_range(0..9);
+fn spawn_creep(
+    commands: &mut Commands,
+    asset_server: &Res<AssetServer>,
+    texture_atlases: &mut ResMut<Assets<TextureAtlas>>,
+    spawn_ev: &mut EventWriter<SpawnCreep>,
+    pos: Vec2,
+    species: TreeSpecies,
+) -> Entity {
+    let mut rng = rand::thread_rng();
+    let atlas_handle = tree_atlas(asset_server, texture_atlases);
+    let sprite_index = species.sprite_index(&mut rng);
 
-        commands.spawn((
+    let ent_id = commands
+        .spawn((
             SpriteSheetBundle {
                 texture_atlas: atlas_handle,
                 sprite: TextureAtlasSprite::new(sprite_index),
-                transform: Transform::from_xyz(x, y, CREEP_Z),
+                transform: Transform::from_xyz(pos.x, pos.y, CREEP_Z),
                 ..default()
             },
             Teardown,
             Tree,
+            species,
             AttackSpeed(10),
-            Health(100),
+            Health(TREE_HEALTH),
+            Drained::default(),
             HpBarUISettings {
-                max: 100,
+                max: TREE_HEALTH,
                 offset: Some(Vec2::new(0.0, -32.0)),
             },
             Range(300),
             CorpoPoints(rng.gen_range(1.0..50.0) as u32),
-        ));
-        spawn_ev.send(SpawnCreep);
-    }
+        ))
+        .id();
+    spawn_ev.send(SpawnCreep);
+
+    ent_id
 }
 
 /// System: Update 'attack' the closest tower.
@@ -165,21 +344,125 @@ fn attack_towers(
 #[derive(Event)]
 pub struct CreepDie;
 
+/// Seconds before a [ResourceDrop] rots away
+const DROP_LIFETIME: f32 = 120.0;
+
+/// What's left of a mature tree that died without a [DrainTower] collecting it
+#[derive(Component)]
+pub struct ResourceDrop {
+    pub stock: HashMap<ResourceType, u32>,
+    rot: Timer,
+}
+
+impl ResourceDrop {
+    pub fn is_empty(&self) -> bool {
+        self.stock.values().all(|amount| *amount == 0)
+    }
+}
+
+fn spawn_drop(commands: &mut Commands, pos: Vec2, contents: Vec<(ResourceType, u32)>) {
+    let mut stock = HashMap::new();
+    contents.into_iter().for_each(|(resource, amount)| {
+        *stock.entry(resource).or_default() += amount;
+    });
+
+    commands.spawn((
+        SpriteBundle {
+            sprite: Sprite {
+                color: Color::rgb(0.45, 0.3, 0.15),
+                custom_size: Some(Vec2::new(10.0, 10.0)),
+                ..default()
+            },
+            transform: Transform::from_xyz(pos.x, pos.y, CREEP_Z - 0.5),
+            ..default()
+        },
+        ResourceDrop {
+            stock,
+            rot: Timer::from_seconds(DROP_LIFETIME, TimerMode::Once),
+        },
+        Teardown,
+    ));
+}
+
+/// System: Update, drops that are picked clean or left too long disappear
+fn rot_drops(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut q_drops: Query<(Entity, &mut ResourceDrop)>,
+) {
+    q_drops.iter_mut().for_each(|(ent, mut drop)| {
+        if drop.rot.tick(time.delta()).finished() || drop.is_empty() {
+            commands.entity(ent).despawn_recursive();
+        }
+    });
+}
+
 /// System: Update, remove anything with Health 0.
-fn cleanup_dead_creeps(
+/// Drain towers were paid for every bit of a tree they drained as they went, see
+/// [crate::buildings::drain]. The one that finishes a tree off gets a kill bonus in its
+/// [Stockpile] on top. Mature trees that die any other way leave their whole yield lying around.
+pub fn cleanup_dead_creeps(
     mut commands: Commands,
-    mut harvest: EventWriter<Harvest>,
-    q: Query<(Entity, &Health, &CorpoPoints), With<Tree>>,
+    mut drained: EventReader<TreeDrained>,
+    q: Query<
+        (
+            Entity,
+            &Health,
+            &CorpoPoints,
+            &TreeSpecies,
+            &Transform,
+            Option<&Sapling>,
+        ),
+        With<Tree>,
+    >,
+    q_drained: Query<&Drained>,
+    mut q_stockpiles: Query<(&mut Stockpile, Option<&mut DrainTower>)>,
     mut audio_mngr: EventWriter<AudioRequest>,
     mut creep_die: EventWriter<CreepDie>,
 ) {
+    let drained_by: HashMap<Entity, Entity> = drained
+        .read()
+        .map(|drained| (drained.tree, drained.tower))
+        .collect();
+
+    let mut rng = rand::thread_rng();
     q.iter()
-        .filter(|(_entity, health, _)| health.0 == 0)
-        .for_each(|(entity, _health, corpo_pts)| {
-            harvest.send(Harvest(ResourceType::CorporationPoints, corpo_pts.0));
-            audio_mngr.send(AudioRequest {
-                component: AudioComponent::Money(Money),
-            });
+        .filter(|(_entity, health, _, _, _, _)| health.0 == 0)
+        .for_each(|(entity, _health, corpo_pts, species, tree_tf, sapling)| {
+            let stockpile = drained_by
+                .get(&entity)
+                .and_then(|tower| q_stockpiles.get_mut(*tower).ok());
+            if let Some((mut stockpile, mut drain)) = stockpile {
+                let mut bonus = vec![(
+                    ResourceType::CorporationPoints,
+                    (corpo_pts.0 as f32 * KILL_BONUS).round() as u32,
+                )];
+                if species.roll_spores(&mut rng) {
+                    bonus.push((ResourceType::RareSpores, 1));
+                }
+                bonus.into_iter().for_each(|(resource, amount)| {
+                    let stored = stockpile.store(resource, amount);
+                    if let Some(drain) = drain.as_mut() {
+                        drain.credit(resource, stored);
+                    }
+                });
+                if let Some(drain) = drain.as_mut() {
+                    drain.kills += 1;
+                }
+                audio_mngr.send(AudioRequest {
+                    component: AudioComponent::Money(Money),
+                });
+            } else if sapling.is_none() {
+                // nobody collected it, leave what wasn't drained lying around for the robots
+                let mut tree_yield = species.yields(&mut rng);
+                tree_yield.push((ResourceType::CorporationPoints, corpo_pts.0));
+                if let Ok(drained) = q_drained.get(entity) {
+                    tree_yield = drained.leftover(tree_yield);
+                }
+                if !tree_yield.is_empty() {
+                    spawn_drop(&mut commands, tree_tf.translation.truncate(), tree_yield);
+                }
+            }
             creep_die.send(CreepDie);
 
             commands.entity(entity).despawn_recursive();
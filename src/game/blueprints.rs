@@ -0,0 +1,836 @@
+//! Copying and pasting whole groups of buildings.
+//!
+//! **LOGIC:**
+//!
+//! - dragging out a box while nothing is being placed selects every building in it, see
+//!   [BoxSelection]
+//! - [FloraCommand::Copy] turns the selection into a [Layout], which is what each building is
+//!   and where it stands relative to the others
+//! - [FloraCommand::Paste] previews the copied layout under the cursor, clicking builds all of it
+//!   at once (or plans it, when it isn't affordable yet) and right clicking puts it away
+//! - [FloraCommand::SaveBlueprint] names the copied layout and keeps it as a [Blueprint]. The
+//!   blueprint panel ([FloraCommand::ToggleBlueprints]) lists them for pasting again, and they
+//!   are saved to disk so they carry over between games.
+
+#[cfg(not(target_arch = "wasm32"))]
+use std::{error::Error, fs, path::Path};
+
+use bevy::prelude::*;
+use bevy_ecs_tilemap::prelude::TilePos;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    buildings::{
+        twr_custom_mats::TowerRadiusMaterial, Building, BuildingType, Footprint, SelectedBuilding,
+    },
+    global_systems::{eargasm::AudioRequest, ui_util::GameFont},
+    AppState, Teardown, PLACEMENT_Z,
+};
+
+use super::{
+    build_queue::PlanBuilding,
+    hud::is_cursor_over_ui,
+    keybinds::{FloraCommand, KeybindsPaused},
+    map::{CurrentTileHover, MapData, TILE_SIZE},
+    occupancy::OccupancyGrid,
+    placement::{PlacementState, PlacementStateChanged},
+    power::AddBuilding,
+    research::Research,
+    resources::{ExpendResource, Inventory, ResourceType},
+};
+
+/// Where blueprints are kept between games
+#[cfg(not(target_arch = "wasm32"))]
+const BLUEPRINT_FILE_PATH: &str = "assets/blueprints/blueprints.ron";
+const BOX_SELECT_COLOR: Color = Color::CYAN;
+
+pub struct BlueprintPlugin;
+impl Plugin for BlueprintPlugin {
+    fn build(&self, app: &mut App) {
+        #[cfg(not(target_arch = "wasm32"))]
+        let blueprints = Blueprints::load(BLUEPRINT_FILE_PATH).unwrap_or_default();
+        #[cfg(target_arch = "wasm32")]
+        let blueprints = Blueprints::default();
+
+        app.insert_resource(blueprints)
+            .init_resource::<Clipboard>()
+            .init_resource::<BoxSelection>()
+            .init_resource::<BlueprintNaming>()
+            .add_systems(OnEnter(AppState::Gameplay), (reset_selection, setup_ui))
+            .add_systems(OnExit(AppState::Gameplay), (stop_naming, teardown_ui))
+            .add_systems(
+                Update,
+                (
+                    box_select,
+                    highlight_box_selection,
+                    copy_selection,
+                    start_paste,
+                    click_blueprint_buttons,
+                    update_paste_ghosts,
+                    place_layout,
+                    name_blueprint,
+                    toggle_panel,
+                    rebuild_blueprint_list,
+                    update_ui,
+                )
+                    .chain()
+                    .run_if(in_state(AppState::Gameplay)),
+            );
+    }
+}
+
+/// A group of buildings and where they stand relative to each other
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Layout {
+    /// Every building and its offset in tiles from the layout's bottom left corner
+    pub buildings: Vec<(BuildingType, (i32, i32))>,
+}
+
+impl Layout {
+    /// What the whole layout costs to build
+    pub fn cost(&self) -> Vec<(ResourceType, u32)> {
+        let mut total: Vec<(ResourceType, u32)> = vec![];
+        self.buildings
+            .iter()
+            .flat_map(|(building, _)| building.cost().iter())
+            .for_each(|(resource, amount)| {
+                match total.iter_mut().find(|(held, _)| held == resource) {
+                    Some((_, sum)) => *sum += amount,
+                    None => total.push((*resource, *amount)),
+                }
+            });
+        total
+    }
+
+    /// The tile every building would go on with the layout's corner on `origin`, or None for
+    /// the ones that would end up off the map
+    pub fn tiles<'a>(
+        &'a self,
+        map: &'a MapData,
+        origin: &TilePos,
+    ) -> impl Iterator<Item = (&'a BuildingType, Option<TilePos>)> + 'a {
+        let origin = (origin.x as i32, origin.y as i32);
+        self.buildings.iter().map(move |(building, (dx, dy))| {
+            let (x, y) = (origin.0 + dx, origin.1 + dy);
+            let tile = map.contains_tile(x, y).then_some(TilePos {
+                x: x as u32,
+                y: y as u32,
+            });
+            (building, tile)
+        })
+    }
+}
+
+/// A [Layout] kept for later under a name
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Blueprint {
+    pub name: String,
+    pub layout: Layout,
+}
+
+#[derive(Resource, Default, Serialize, Deserialize)]
+pub struct Blueprints(pub Vec<Blueprint>);
+
+impl Blueprints {
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn save(&self, path: &str) -> Result<(), Box<dyn Error>> {
+        let contents = ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default())?;
+        if let Some(dir) = Path::new(path).parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(path, contents)?;
+        Ok(())
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn load(path: &str) -> Result<Self, Box<dyn Error>> {
+        Ok(ron::from_str(&fs::read_to_string(path)?)?)
+    }
+
+    /// Writes the blueprints to disk, where that's possible
+    fn persist(&self) {
+        #[cfg(not(target_arch = "wasm32"))]
+        if let Err(e) = self.save(BLUEPRINT_FILE_PATH) {
+            error!("Failed to save blueprints to {BLUEPRINT_FILE_PATH}: {e}");
+        }
+    }
+}
+
+/// The last copied [Layout]
+#[derive(Resource, Default)]
+pub struct Clipboard(pub Option<Layout>);
+
+/// Buildings picked by dragging a box over them
+#[derive(Resource, Default)]
+pub struct BoxSelection {
+    /// Where the box currently being dragged started
+    start: Option<TilePos>,
+    pub buildings: Vec<Entity>,
+}
+
+/// The name being typed for a new [Blueprint], while there is one
+#[derive(Resource, Default)]
+struct BlueprintNaming(Option<String>);
+
+/// One of the preview sprites of the layout being pasted, indexing into [Layout::buildings]
+#[derive(Component)]
+struct PasteGhost(usize);
+
+fn reset_selection(
+    mut selection: ResMut<BoxSelection>,
+    mut clipboard: ResMut<Clipboard>,
+    mut placement: ResMut<PlacementState>,
+) {
+    *selection = BoxSelection::default();
+    clipboard.0 = None;
+    placement.pasting = None;
+}
+
+/// A name half typed when the game ended is dropped, and keybinds come back on with it
+fn stop_naming(mut naming: ResMut<BlueprintNaming>, mut paused: ResMut<KeybindsPaused>) {
+    naming.0 = None;
+    paused.0 = false;
+}
+
+/// Smallest and largest corner of the tiles spanned by `a` and `b`
+fn tile_rect(a: &TilePos, b: &TilePos) -> (TilePos, TilePos) {
+    (
+        TilePos {
+            x: a.x.min(b.x),
+            y: a.y.min(b.y),
+        },
+        TilePos {
+            x: a.x.max(b.x),
+            y: a.y.max(b.y),
+        },
+    )
+}
+
+/// Dragging from one tile to another selects every building in between. A plain click clears
+/// the box selection, and picks a single building instead (see [SelectedBuilding]).
+#[allow(clippy::too_many_arguments)]
+fn box_select(
+    mut selection: ResMut<BoxSelection>,
+    mut gizmos: Gizmos,
+    placement: Res<PlacementState>,
+    mouse_btns: Res<Input<MouseButton>>,
+    tile_hover: Res<CurrentTileHover>,
+    map: Res<MapData>,
+    q_interactions: Query<&Interaction>,
+    q_buildings: Query<(Entity, &Transform), With<Building>>,
+) {
+    // sold or destroyed buildings drop out of the selection
+    if selection
+        .buildings
+        .iter()
+        .any(|ent| !q_buildings.contains(*ent))
+    {
+        selection.buildings.retain(|ent| q_buildings.contains(*ent));
+    }
+
+    if placement.is_placing() {
+        selection.start = None;
+        return;
+    }
+    if mouse_btns.just_pressed(MouseButton::Left) && !is_cursor_over_ui(&q_interactions) {
+        selection.start = tile_hover.tile_pos;
+    }
+    let (Some(start), Some(end)) = (selection.start, tile_hover.tile_pos) else {
+        return;
+    };
+
+    let (min, max) = tile_rect(&start, &end);
+    if start != end {
+        let (min_pos, max_pos) = (map.tile_center(&min), map.tile_center(&max));
+        gizmos.rect_2d(
+            (min_pos + max_pos) / 2.0,
+            0.0,
+            max_pos - min_pos + Vec2::splat(TILE_SIZE),
+            BOX_SELECT_COLOR,
+        );
+    }
+    if !mouse_btns.just_released(MouseButton::Left) {
+        return;
+    }
+
+    selection.start = None;
+    selection.buildings = if start == end {
+        vec![]
+    } else {
+        q_buildings
+            .iter()
+            .filter(|(_, tf)| {
+                map.building_tile(tf.translation.truncate())
+                    .map_or(false, |tile| {
+                        (min.x..=max.x).contains(&tile.x) && (min.y..=max.y).contains(&tile.y)
+                    })
+            })
+            .map(|(ent, _)| ent)
+            .collect()
+    };
+}
+
+fn highlight_box_selection(
+    mut gizmos: Gizmos,
+    selection: Res<BoxSelection>,
+    q_buildings: Query<(&Transform, &Footprint), With<Building>>,
+) {
+    q_buildings
+        .iter_many(&selection.buildings)
+        .for_each(|(tf, footprint)| {
+            gizmos.rect_2d(
+                tf.translation.truncate(),
+                0.0,
+                footprint.sprite_size() + Vec2::splat(TILE_SIZE / 2.0),
+                BOX_SELECT_COLOR,
+            );
+        });
+}
+
+/// Copies the box selection, or the single selected building when there's no box, as a
+/// [Layout]. The core can't be copied.
+fn copy_selection(
+    input: Res<Input<FloraCommand>>,
+    selection: Res<BoxSelection>,
+    selected: Res<SelectedBuilding>,
+    map: Res<MapData>,
+    mut clipboard: ResMut<Clipboard>,
+    q_buildings: Query<(&BuildingType, &Transform)>,
+) {
+    if !input.just_pressed(FloraCommand::Copy) {
+        return;
+    }
+
+    let entities = if selection.buildings.is_empty() {
+        selected.0.into_iter().collect()
+    } else {
+        selection.buildings.clone()
+    };
+    let placed: Vec<(BuildingType, TilePos)> = q_buildings
+        .iter_many(&entities)
+        .filter_map(|(building, tf)| {
+            map.building_tile(tf.translation.truncate())
+                .map(|tile| (building.clone(), tile))
+        })
+        .collect();
+    if placed.is_empty() {
+        return;
+    }
+
+    let min_x = placed.iter().map(|(_, tile)| tile.x).min().unwrap_or(0) as i32;
+    let min_y = placed.iter().map(|(_, tile)| tile.y).min().unwrap_or(0) as i32;
+    let layout = Layout {
+        buildings: placed
+            .into_iter()
+            .map(|(building, tile)| (building, (tile.x as i32 - min_x, tile.y as i32 - min_y)))
+            .collect(),
+    };
+    info!("Copied {} buildings", layout.buildings.len());
+    clipboard.0 = Some(layout);
+}
+
+/// Starts pasting `layout`, instead of placing whatever building was picked before
+fn begin_paste(
+    placement: &mut PlacementState,
+    changed: &mut EventWriter<PlacementStateChanged>,
+    layout: Layout,
+) {
+    if placement.being_placed_building_type.is_some() {
+        placement.being_placed_building_type = None;
+        changed.send(PlacementStateChanged { value: None });
+    }
+    placement.pasting = Some(layout);
+}
+
+/// Pressing paste picks up the clipboard, pressing it again (or right clicking) puts it away
+fn start_paste(
+    input: Res<Input<FloraCommand>>,
+    mouse_btns: Res<Input<MouseButton>>,
+    clipboard: Res<Clipboard>,
+    mut placement: ResMut<PlacementState>,
+    mut changed: EventWriter<PlacementStateChanged>,
+) {
+    if placement.pasting.is_some()
+        && (input.just_pressed(FloraCommand::Paste) || mouse_btns.just_pressed(MouseButton::Right))
+    {
+        placement.pasting = None;
+        return;
+    }
+    if !input.just_pressed(FloraCommand::Paste) {
+        return;
+    }
+    if let Some(layout) = &clipboard.0 {
+        begin_paste(&mut placement, &mut changed, layout.clone());
+    }
+}
+
+/// Whether every building of `layout` can go down with its corner on `origin`, money aside.
+/// Each one needs all of its [Footprint] on free tiles.
+fn fits_layout(
+    layout: &Layout,
+    origin: &TilePos,
+    map: &MapData,
+    occupancy: &OccupancyGrid,
+    research: &Research,
+) -> bool {
+    layout.tiles(map, origin).all(|(building, tile)| {
+        research.is_unlocked(building)
+            && tile.map_or(false, |tile| occupancy.fits(&building.footprint(), &tile))
+    })
+}
+
+/// Slightly transparent copies of every building in the layout being pasted, red when it
+/// can't go down where the cursor is
+#[allow(clippy::too_many_arguments)]
+fn update_paste_ghosts(
+    mut commands: Commands,
+    placement: Res<PlacementState>,
+    asset_server: Res<AssetServer>,
+    tile_hover: Res<CurrentTileHover>,
+    map: Res<MapData>,
+    occupancy: Res<OccupancyGrid>,
+    research: Res<Research>,
+    inventory: Res<Inventory>,
+    mut ghosts_of: Local<Option<Layout>>,
+    mut q_ghosts: Query<(
+        Entity,
+        &PasteGhost,
+        &mut Sprite,
+        &mut Transform,
+        &mut Visibility,
+    )>,
+) {
+    if *ghosts_of != placement.pasting {
+        q_ghosts
+            .iter()
+            .for_each(|(ent, _, _, _, _)| commands.entity(ent).despawn_recursive());
+        *ghosts_of = placement.pasting.clone();
+        let Some(layout) = &placement.pasting else {
+            return;
+        };
+        layout
+            .buildings
+            .iter()
+            .enumerate()
+            .for_each(|(idx, (building, _))| {
+                commands.spawn((
+                    SpriteBundle {
+                        texture: asset_server.load(building.sprite()),
+                        sprite: Sprite {
+                            custom_size: Some(building.footprint().sprite_size()),
+                            ..default()
+                        },
+                        visibility: Visibility::Hidden,
+                        ..default()
+                    },
+                    PasteGhost(idx),
+                    Teardown,
+                ));
+            });
+        return;
+    }
+
+    let (Some(layout), Some(origin)) = (&placement.pasting, tile_hover.tile_pos) else {
+        return;
+    };
+    // red where it can't go at all, blue where it would only be planned for now
+    let color = if !fits_layout(layout, &origin, &map, &occupancy, &research) {
+        Color::rgba(1.0, 0.3, 0.3, 0.5)
+    } else if !inventory.can_afford(&layout.cost()) {
+        Color::rgba(0.6, 0.8, 1.0, 0.5)
+    } else {
+        Color::rgba(1.0, 1.0, 1.0, 0.5)
+    };
+    let tiles: Vec<_> = layout.tiles(&map, &origin).map(|(_, tile)| tile).collect();
+    q_ghosts
+        .iter_mut()
+        .for_each(|(_, ghost, mut sprite, mut tf, mut vis)| {
+            let Some(Some(tile)) = tiles.get(ghost.0) else {
+                *vis = Visibility::Hidden;
+                return;
+            };
+            let pos = map.building_pos(tile);
+            tf.translation = Vec3::new(pos.x, pos.y, PLACEMENT_Z);
+            sprite.color = color;
+            *vis = Visibility::Visible;
+        });
+}
+
+/// Clicking while pasting builds the whole layout, as long as all of it fits. Layouts that
+/// can't be paid for yet are planned instead, see [PlanBuilding]. The layout stays picked up so
+/// it can be placed again.
+#[allow(clippy::too_many_arguments)]
+fn place_layout(
+    mut commands: Commands,
+    mut add_building: EventWriter<AddBuilding>,
+    mut expend_resource: EventWriter<ExpendResource>,
+    mut audio_mngr: EventWriter<AudioRequest>,
+    mut plan_building: EventWriter<PlanBuilding>,
+    // grouped to stay within how many parameters a system can take
+    (mut texture_atlases, mut meshes, mut materials): (
+        ResMut<Assets<TextureAtlas>>,
+        ResMut<Assets<Mesh>>,
+        ResMut<Assets<TowerRadiusMaterial>>,
+    ),
+    asset_server: Res<AssetServer>,
+    placement: Res<PlacementState>,
+    input: Res<Input<FloraCommand>>,
+    mouse_btns: Res<Input<MouseButton>>,
+    tile_hover: Res<CurrentTileHover>,
+    map: Res<MapData>,
+    occupancy: Res<OccupancyGrid>,
+    research: Res<Research>,
+    inventory: Res<Inventory>,
+    q_interactions: Query<&Interaction>,
+) {
+    let (Some(layout), Some(origin)) = (&placement.pasting, tile_hover.tile_pos) else {
+        return;
+    };
+    if !mouse_btns.just_pressed(MouseButton::Left)
+        || is_cursor_over_ui(&q_interactions)
+        || !fits_layout(layout, &origin, &map, &occupancy, &research)
+    {
+        return;
+    }
+
+    if input.pressed(FloraCommand::PlanBuilding) || !inventory.can_afford(&layout.cost()) {
+        layout
+            .tiles(&map, &origin)
+            .filter_map(|(building, tile)| tile.map(|tile| (building, tile)))
+            .for_each(|(building, tile)| {
+                plan_building.send(PlanBuilding {
+                    building: building.clone(),
+                    pos: map.building_pos(&tile),
+                });
+            });
+        return;
+    }
+
+    layout
+        .tiles(&map, &origin)
+        .filter_map(|(building, tile)| tile.map(|tile| (building, tile)))
+        .for_each(|(building, tile)| {
+            building.spawn(
+                &mut commands,
+                &mut texture_atlases,
+                &mut meshes,
+                &mut materials,
+                Res::clone(&asset_server),
+                map.building_pos(&tile),
+                &mut audio_mngr,
+            );
+        });
+    layout.cost().iter().for_each(|(resource, amount)| {
+        expend_resource.send(ExpendResource(*resource, *amount));
+    });
+    add_building.send(AddBuilding);
+}
+
+/// Saving a blueprint asks for a name first. Keybinds are off while it's being typed.
+fn name_blueprint(
+    input: Res<Input<FloraCommand>>,
+    keys: Res<Input<KeyCode>>,
+    mut typed: EventReader<ReceivedCharacter>,
+    mut naming: ResMut<BlueprintNaming>,
+    mut paused: ResMut<KeybindsPaused>,
+    mut blueprints: ResMut<Blueprints>,
+    clipboard: Res<Clipboard>,
+) {
+    if naming.0.is_none() {
+        typed.clear();
+        // hold off on keybinds until whatever finished typing has been let go, so Escape
+        // doesn't pause the game on its way out
+        if paused.0 && keys.get_pressed().next().is_none() {
+            paused.0 = false;
+        }
+        if input.just_pressed(FloraCommand::SaveBlueprint) && clipboard.0.is_some() {
+            naming.0 = Some(format!("Blueprint {}", blueprints.0.len() + 1));
+            paused.0 = true;
+        }
+        return;
+    }
+    let Some(name) = naming.0.as_mut() else {
+        return;
+    };
+
+    typed
+        .read()
+        .filter(|typed| !typed.char.is_control())
+        .for_each(|typed| name.push(typed.char));
+    if keys.just_pressed(KeyCode::Back) {
+        name.pop();
+    }
+
+    if keys.just_pressed(KeyCode::Escape) {
+        naming.0 = None;
+    } else if keys.just_pressed(KeyCode::Return) && !name.trim().is_empty() {
+        if let Some(layout) = &clipboard.0 {
+            info!("Saved blueprint {}", name.trim());
+            blueprints.0.push(Blueprint {
+                name: name.trim().to_string(),
+                layout: layout.clone(),
+            });
+            blueprints.persist();
+        }
+        naming.0 = None;
+    }
+}
+
+#[derive(Component)]
+struct BlueprintUI;
+
+/// The list of blueprints that gets toggled
+#[derive(Component)]
+struct BlueprintPanel;
+
+#[derive(Component)]
+struct BlueprintPrompt;
+
+#[derive(Component)]
+struct BlueprintPromptText;
+
+/// Clicking it pastes the blueprint at this index
+#[derive(Component)]
+struct BlueprintButton(usize);
+
+/// Clicking it throws away the blueprint at this index
+#[derive(Component)]
+struct DeleteBlueprintButton(usize);
+
+fn setup_ui(mut commands: Commands, font: Res<GameFont>) {
+    let text_style = |size: f32| TextStyle {
+        font_size: size,
+        font: font.0.clone(),
+        color: Color::WHITE,
+    };
+
+    commands.spawn((
+        NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                top: Val::Px(160.0),
+                right: Val::Px(15.0),
+                flex_direction: FlexDirection::Column,
+                display: Display::None,
+                ..default()
+            },
+            background_color: Color::rgba(0.0, 0.0, 0.0, 0.7).into(),
+            z_index: ZIndex::Global(i32::MAX - 1),
+            ..default()
+        },
+        BlueprintUI,
+        BlueprintPanel,
+    ));
+
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    width: Val::Percent(100.0),
+                    height: Val::Percent(100.0),
+                    align_items: AlignItems::Center,
+                    justify_content: JustifyContent::Center,
+                    ..default()
+                },
+                z_index: ZIndex::Global(i32::MAX - 1),
+                ..default()
+            },
+            BlueprintUI,
+        ))
+        .with_children(|parent| {
+            parent
+                .spawn((
+                    NodeBundle {
+                        style: Style {
+                            display: Display::None,
+                            padding: UiRect::all(Val::Px(20.0)),
+                            ..default()
+                        },
+                        background_color: Color::rgba(0.0, 0.0, 0.0, 0.8).into(),
+                        ..default()
+                    },
+                    BlueprintPrompt,
+                ))
+                .with_children(|parent| {
+                    parent.spawn((
+                        TextBundle::from_section("", text_style(28.0))
+                            .with_text_alignment(TextAlignment::Center),
+                        BlueprintPromptText,
+                    ));
+                });
+        });
+}
+
+fn toggle_panel(
+    input: Res<Input<FloraCommand>>,
+    naming: Res<BlueprintNaming>,
+    mut q_panel: Query<&mut Style, With<BlueprintPanel>>,
+) {
+    // the B of Ctrl+B doesn't count, whether or not there was anything to save
+    if !input.just_pressed(FloraCommand::ToggleBlueprints)
+        || input.pressed(FloraCommand::SaveBlueprint)
+        || naming.0.is_some()
+    {
+        return;
+    }
+
+    for mut style in &mut q_panel {
+        style.display = match style.display {
+            Display::None => Display::Flex,
+            _ => Display::None,
+        };
+    }
+}
+
+/// One row per blueprint, remade whenever blueprints are added or removed
+fn rebuild_blueprint_list(
+    mut commands: Commands,
+    blueprints: Res<Blueprints>,
+    font: Res<GameFont>,
+    q_panel: Query<Entity, With<BlueprintPanel>>,
+    q_new_panel: Query<(), Added<BlueprintPanel>>,
+) {
+    if !blueprints.is_changed() && q_new_panel.is_empty() {
+        return;
+    }
+    let text_style = TextStyle {
+        font_size: 20.0,
+        font: font.0.clone(),
+        color: Color::WHITE,
+    };
+
+    q_panel.iter().for_each(|panel| {
+        commands
+            .entity(panel)
+            .despawn_descendants()
+            .with_children(|parent| {
+                parent.spawn(TextBundle::from_section(
+                    "[B] Blueprints",
+                    TextStyle {
+                        font_size: 24.0,
+                        ..text_style.clone()
+                    },
+                ));
+                if blueprints.0.is_empty() {
+                    parent.spawn(TextBundle::from_section(
+                        "Copy buildings and press [Ctrl+B] to save some",
+                        text_style.clone(),
+                    ));
+                }
+                blueprints
+                    .0
+                    .iter()
+                    .enumerate()
+                    .for_each(|(idx, blueprint)| {
+                        parent.spawn(NodeBundle::default()).with_children(|parent| {
+                            parent
+                                .spawn((
+                                    ButtonBundle {
+                                        style: Style {
+                                            padding: UiRect::all(Val::Px(6.0)),
+                                            flex_grow: 1.0,
+                                            ..default()
+                                        },
+                                        background_color: Color::NONE.into(),
+                                        ..default()
+                                    },
+                                    BlueprintButton(idx),
+                                ))
+                                .with_children(|parent| {
+                                    parent.spawn(TextBundle::from_section(
+                                        format!(
+                                            "{} ({} buildings)",
+                                            blueprint.name,
+                                            blueprint.layout.buildings.len()
+                                        ),
+                                        text_style.clone(),
+                                    ));
+                                });
+                            parent
+                                .spawn((
+                                    ButtonBundle {
+                                        style: Style {
+                                            padding: UiRect::all(Val::Px(6.0)),
+                                            ..default()
+                                        },
+                                        background_color: Color::NONE.into(),
+                                        ..default()
+                                    },
+                                    DeleteBlueprintButton(idx),
+                                ))
+                                .with_children(|parent| {
+                                    parent.spawn(TextBundle::from_section(
+                                        "x",
+                                        TextStyle {
+                                            color: Color::ORANGE_RED,
+                                            ..text_style.clone()
+                                        },
+                                    ));
+                                });
+                        });
+                    });
+            });
+    });
+}
+
+/// Picking a blueprint copies it and starts pasting it
+fn click_blueprint_buttons(
+    mut blueprints: ResMut<Blueprints>,
+    mut clipboard: ResMut<Clipboard>,
+    mut placement: ResMut<PlacementState>,
+    mut changed: EventWriter<PlacementStateChanged>,
+    q_buttons: Query<(&Interaction, &BlueprintButton), Changed<Interaction>>,
+    q_delete_buttons: Query<(&Interaction, &DeleteBlueprintButton), Changed<Interaction>>,
+) {
+    for (interaction, button) in &q_buttons {
+        if *interaction != Interaction::Pressed {
+            continue;
+        }
+        if let Some(blueprint) = blueprints.0.get(button.0) {
+            clipboard.0 = Some(blueprint.layout.clone());
+            begin_paste(&mut placement, &mut changed, blueprint.layout.clone());
+        }
+    }
+
+    let deleted = q_delete_buttons
+        .iter()
+        .find(|(interaction, _)| **interaction == Interaction::Pressed)
+        .map(|(_, button)| button.0);
+    if let Some(idx) = deleted.filter(|idx| *idx < blueprints.0.len()) {
+        let blueprint = blueprints.0.remove(idx);
+        info!("Deleted blueprint {}", blueprint.name);
+        blueprints.persist();
+    }
+}
+
+fn update_ui(
+    naming: Res<BlueprintNaming>,
+    mut q_prompt: Query<&mut Style, With<BlueprintPrompt>>,
+    mut q_text: Query<&mut Text, With<BlueprintPromptText>>,
+) {
+    if !naming.is_changed() {
+        return;
+    }
+
+    for mut style in &mut q_prompt {
+        style.display = match naming.0 {
+            Some(_) => Display::Flex,
+            None => Display::None,
+        };
+    }
+    if let Some(name) = &naming.0 {
+        for mut text in &mut q_text {
+            text.sections[0].value = format!(
+                "Save blueprint as:\n{}_\n\n[Enter] Save  [Esc] Cancel",
+                name
+            );
+        }
+    }
+}
+
+fn teardown_ui(mut commands: Commands, nodes: Query<Entity, With<BlueprintUI>>) {
+    for ent in &nodes {
+        commands.entity(ent).despawn_recursive();
+    }
+}
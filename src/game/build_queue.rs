@@ -0,0 +1,359 @@
+//! Planned buildings. Anything placed that can't be paid for yet (or placed while holding
+//! [FloraCommand::PlanBuilding]) goes into the [BuildQueue] as a see-through ghost instead, and
+//! gets built as soon as the money is there.
+//!
+//! Plans are built strictly in order, so the first one in the queue always gets the money
+//! first. Plans hold their tiles with a [Reservation], so nothing else can be placed there, but
+//! trees can still grow over them and a plan whose tiles got taken in the meantime is dropped
+//! when its turn comes. The queue panel shows every plan, and lets them be moved up, moved down
+//! or cancelled.
+
+use bevy::prelude::*;
+
+use crate::{
+    buildings::{twr_custom_mats::TowerRadiusMaterial, BuildingType},
+    global_systems::{eargasm::AudioRequest, ui_util::GameFont},
+    AppState, Teardown, PLACEMENT_Z,
+};
+
+use super::{
+    hud::PIXEL,
+    map::MapData,
+    occupancy::{OccupancyGrid, Reservation},
+    power::AddBuilding,
+    research::{format_cost, Research},
+    resources::Inventory,
+};
+
+/// Planned buildings are drawn just under whatever is being placed
+const PLANNED_Z: f32 = PLACEMENT_Z - 0.05;
+const PLANNED_TINT: Color = Color::rgba(0.6, 0.8, 1.0, 0.4);
+
+pub struct BuildQueuePlugin;
+impl Plugin for BuildQueuePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<BuildQueue>()
+            .add_event::<PlanBuilding>()
+            .add_systems(OnEnter(AppState::Gameplay), (reset_queue, setup_ui))
+            .add_systems(OnExit(AppState::Gameplay), teardown_ui)
+            .add_systems(
+                Update,
+                (
+                    add_plans,
+                    click_queue_buttons,
+                    build_next_plan,
+                    rebuild_queue_list,
+                    color_next_plan,
+                )
+                    .chain()
+                    .run_if(in_state(AppState::Gameplay)),
+            );
+    }
+}
+
+/// Fire to queue up a building at `pos` instead of building it straight away
+#[derive(Event)]
+pub struct PlanBuilding {
+    pub building: BuildingType,
+    pub pos: Vec2,
+}
+
+/// A building waiting for the money to build it
+pub struct Plan {
+    pub building: BuildingType,
+    pub pos: Vec2,
+    /// The see-through sprite standing in for the building until it's built
+    ghost: Entity,
+}
+
+/// Every [Plan], first to be built first
+#[derive(Resource, Default)]
+pub struct BuildQueue(pub Vec<Plan>);
+
+/// Marks the ghost of a [Plan]
+#[derive(Component)]
+struct PlannedGhost;
+
+fn reset_queue(mut queue: ResMut<BuildQueue>) {
+    queue.0.clear();
+}
+
+fn add_plans(
+    mut commands: Commands,
+    mut planned: EventReader<PlanBuilding>,
+    mut queue: ResMut<BuildQueue>,
+    asset_server: Res<AssetServer>,
+) {
+    planned.read().for_each(|plan| {
+        let ghost = commands
+            .spawn((
+                SpriteBundle {
+                    texture: asset_server.load(plan.building.sprite()),
+                    sprite: Sprite {
+                        color: PLANNED_TINT,
+                        custom_size: Some(plan.building.footprint().sprite_size()),
+                        ..default()
+                    },
+                    transform: Transform::from_translation(plan.pos.extend(PLANNED_Z)),
+                    ..default()
+                },
+                PlannedGhost,
+                Reservation(plan.building.footprint()),
+                Teardown,
+            ))
+            .id();
+        info!("Planned a {:?}", plan.building);
+        queue.0.push(Plan {
+            building: plan.building.clone(),
+            pos: plan.pos,
+            ghost,
+        });
+    });
+}
+
+/// Builds the first plan in the queue as soon as it's affordable, or drops it if something else
+/// is standing where it was going to go
+#[allow(clippy::too_many_arguments)]
+fn build_next_plan(
+    mut commands: Commands,
+    mut queue: ResMut<BuildQueue>,
+    mut inventory: ResMut<Inventory>,
+    mut add_building: EventWriter<AddBuilding>,
+    mut audio_mngr: EventWriter<AudioRequest>,
+    mut texture_atlases: ResMut<Assets<TextureAtlas>>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<TowerRadiusMaterial>>,
+    asset_server: Res<AssetServer>,
+    research: Res<Research>,
+    map: Res<MapData>,
+    occupancy: Res<OccupancyGrid>,
+) {
+    let Some(plan) = queue.0.first() else {
+        return;
+    };
+    let fits = map.building_tile(plan.pos).map_or(false, |anchor| {
+        occupancy.fits_reserved(&plan.building.footprint(), &anchor, plan.ghost)
+    });
+    if !fits {
+        info!(
+            "Dropped a planned {:?}, something's in the way",
+            plan.building
+        );
+        let plan = queue.0.remove(0);
+        commands.entity(plan.ghost).despawn_recursive();
+        return;
+    }
+    if !research.is_unlocked(&plan.building) || !inventory.can_afford(plan.building.cost()) {
+        return;
+    }
+
+    let plan = queue.0.remove(0);
+    // paid straight away rather than through [ExpendResource], so the next plan in line can't
+    // be paid for with the same money before the bill arrives
+    plan.building
+        .cost()
+        .iter()
+        .for_each(|(resource, amount)| inventory.remove(*resource, *amount));
+    plan.building.spawn(
+        &mut commands,
+        &mut texture_atlases,
+        &mut meshes,
+        &mut materials,
+        asset_server,
+        plan.pos,
+        &mut audio_mngr,
+    );
+    commands.entity(plan.ghost).despawn_recursive();
+    add_building.send(AddBuilding);
+}
+
+#[derive(Component)]
+struct QueueUI;
+
+/// The list of plans, rebuilt whenever the queue changes
+#[derive(Component)]
+struct QueueList;
+
+#[derive(Clone, Copy)]
+enum QueueAction {
+    MoveUp,
+    MoveDown,
+    Cancel,
+}
+
+/// Does `action` to the plan at this index in the queue
+#[derive(Component)]
+struct QueueButton {
+    index: usize,
+    action: QueueAction,
+}
+
+/// The label of the plan at this index in the queue
+#[derive(Component)]
+struct QueueEntryText(usize);
+
+fn setup_ui(mut commands: Commands) {
+    commands.spawn((
+        NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                // just above the HUD bar
+                bottom: Val::Px(PIXEL * 26.0 + 10.0),
+                left: Val::Px(15.0),
+                flex_direction: FlexDirection::Column,
+                display: Display::None,
+                ..default()
+            },
+            background_color: Color::rgba(0.0, 0.0, 0.0, 0.7).into(),
+            z_index: ZIndex::Global(i32::MAX - 1),
+            ..default()
+        },
+        QueueUI,
+        QueueList,
+    ));
+}
+
+fn click_queue_buttons(
+    mut commands: Commands,
+    mut queue: ResMut<BuildQueue>,
+    q_buttons: Query<(&Interaction, &QueueButton), Changed<Interaction>>,
+) {
+    // only one button can be pressed per frame, and indices go stale once the queue moves
+    let Some(button) = q_buttons
+        .iter()
+        .find(|(interaction, _)| **interaction == Interaction::Pressed)
+        .map(|(_, button)| button)
+    else {
+        return;
+    };
+    if button.index >= queue.0.len() {
+        return;
+    }
+
+    match button.action {
+        QueueAction::MoveUp if button.index > 0 => queue.0.swap(button.index, button.index - 1),
+        QueueAction::MoveDown if button.index + 1 < queue.0.len() => {
+            queue.0.swap(button.index, button.index + 1)
+        }
+        QueueAction::Cancel => {
+            let plan = queue.0.remove(button.index);
+            commands.entity(plan.ghost).despawn_recursive();
+        }
+        _ => {}
+    }
+}
+
+fn queue_button(
+    parent: &mut ChildBuilder,
+    label: &str,
+    text_style: &TextStyle,
+    index: usize,
+    action: QueueAction,
+) {
+    parent
+        .spawn((
+            ButtonBundle {
+                style: Style {
+                    padding: UiRect::horizontal(Val::Px(6.0)),
+                    ..default()
+                },
+                background_color: Color::NONE.into(),
+                ..default()
+            },
+            QueueButton { index, action },
+        ))
+        .with_children(|parent| {
+            parent.spawn(TextBundle::from_section(label, text_style.clone()));
+        });
+}
+
+fn rebuild_queue_list(
+    mut commands: Commands,
+    queue: Res<BuildQueue>,
+    font: Res<GameFont>,
+    mut q_list: Query<(Entity, &mut Style), With<QueueList>>,
+) {
+    if !queue.is_changed() {
+        return;
+    }
+    let text_style = TextStyle {
+        font_size: 20.0,
+        font: font.0.clone(),
+        color: Color::WHITE,
+    };
+
+    q_list.iter_mut().for_each(|(list, mut style)| {
+        style.display = if queue.0.is_empty() {
+            Display::None
+        } else {
+            Display::Flex
+        };
+        commands
+            .entity(list)
+            .despawn_descendants()
+            .with_children(|parent| {
+                parent.spawn(TextBundle::from_section(
+                    "Planned",
+                    TextStyle {
+                        font_size: 24.0,
+                        ..text_style.clone()
+                    },
+                ));
+                queue.0.iter().enumerate().for_each(|(index, plan)| {
+                    parent
+                        .spawn(NodeBundle {
+                            style: Style {
+                                padding: UiRect::all(Val::Px(4.0)),
+                                ..default()
+                            },
+                            ..default()
+                        })
+                        .with_children(|parent| {
+                            parent.spawn((
+                                TextBundle::from_section(
+                                    format!(
+                                        "{}. {} ({})",
+                                        index + 1,
+                                        plan.building.name(),
+                                        format_cost(plan.building.cost())
+                                    ),
+                                    text_style.clone(),
+                                ),
+                                QueueEntryText(index),
+                            ));
+                            queue_button(parent, "^", &text_style, index, QueueAction::MoveUp);
+                            queue_button(parent, "v", &text_style, index, QueueAction::MoveDown);
+                            queue_button(parent, "x", &text_style, index, QueueAction::Cancel);
+                        });
+                });
+            });
+    });
+}
+
+/// The plan that's up next is orange while it's waiting on money
+fn color_next_plan(
+    queue: Res<BuildQueue>,
+    inventory: Res<Inventory>,
+    mut q_text: Query<(&mut Text, &QueueEntryText)>,
+) {
+    let waiting = queue
+        .0
+        .first()
+        .map_or(false, |plan| !inventory.can_afford(plan.building.cost()));
+    q_text.iter_mut().for_each(|(mut text, entry)| {
+        let color = if entry.0 == 0 && waiting {
+            Color::ORANGE
+        } else {
+            Color::WHITE
+        };
+        if text.sections[0].style.color != color {
+            text.sections[0].style.color = color;
+        }
+    });
+}
+
+fn teardown_ui(mut commands: Commands, nodes: Query<Entity, With<QueueUI>>) {
+    for ent in &nodes {
+        commands.entity(ent).despawn_recursive();
+    }
+}
@@ -20,16 +20,11 @@ use bevy_tweening::Lerp;
 
 use crate::{AppState, PauseMenuState};
 
-use super::keybinds::FloraCommand;
+use super::{keybinds::FloraCommand, map::WorldBounds};
 
 // how close to the edges of the screen before camera moves
 const PAN_THRESHOLD: Vec2 = Vec2::splat(2.0);
 
-// loops camera when x value is met, stops camera from progressing when y value is met
-const CAMERA_BOUNDS_MIN: Vec2 = Vec2::new(-2000.0, -2000.0);
-// loops camera when x value is met, stops camera from progressing when y value is met
-const CAMERA_BOUNDS_MAX: Vec2 = Vec2::new(2000.0, 2000.0);
-
 // current camera velocity is multiplied by this value to slow it down
 const FRICTION: Vec2 = Vec2::splat(0.90);
 // max accel to speed up the camera to
@@ -87,8 +82,9 @@ impl Plugin for GameCameraPlugin {
     fn build(&self, app: &mut App) {
         app.add_systems(Startup, setup).add_systems(
             Update,
-            (move_camera)
-                .run_if(in_state(AppState::Gameplay))
+            (move_camera, confine_camera)
+                .chain()
+                .run_if(in_state(AppState::Gameplay).or_else(in_state(AppState::MapEditor)))
                 .run_if(in_state(PauseMenuState::Unpaused)),
         );
     }
@@ -213,8 +209,10 @@ fn move_camera(
     mouse_input: Res<Input<MouseButton>>,
     mut mouse_ev: EventReader<MouseMotion>,
     mut wheel_ev: EventReader<MouseWheel>,
+    bounds: Res<WorldBounds>,
 ) {
     assert!(!query.is_empty());
+    let window = q_window.single();
 
     for v in query.iter_mut() {
         let _cam = v.0;
@@ -239,7 +237,11 @@ fn move_camera(
                 MouseScrollUnit::Line => ev.y * ZOOM_VELOCITY_LINE,
             }
         }
-        state.zoom_target = state.zoom_target.clamp(ZOOM_MAX, ZOOM_MIN);
+        // never zoom out further than the edges of the world
+        let zoom_out_limit = bounds
+            .max_zoom_out(Vec2::new(window.width(), window.height()))
+            .clamp(ZOOM_MAX, ZOOM_MIN);
+        state.zoom_target = state.zoom_target.clamp(ZOOM_MAX, zoom_out_limit);
         projection.scale = projection.scale.lerp(&state.zoom_target, &ZOOM_FACTOR);
 
         // read the mouse motion or it builds up speed
@@ -278,7 +280,6 @@ fn move_camera(
             accel -= Vec2::Y;
         }
 
-        let window = q_window.single();
         // edge pan
         let mut pan = Vec2::ZERO;
         let top_left = Vec2::ZERO;
@@ -325,3 +326,20 @@ fn move_camera(
         state.velocity *= FRICTION;
     }
 }
+
+/// Keeps the camera from panning past the edges of the [WorldBounds]
+fn confine_camera(
+    bounds: Res<WorldBounds>,
+    q_window: Query<&Window, With<PrimaryWindow>>,
+    mut q_camera: Query<(&mut Transform, &OrthographicProjection), With<ViewCamera>>,
+) {
+    let window = q_window.single();
+    let viewport = Vec2::new(window.width(), window.height());
+
+    for (mut transform, projection) in &mut q_camera {
+        let half_extents = viewport * projection.scale / 2.0;
+        let confined = bounds.confine_camera(transform.translation.xy(), half_extents);
+        transform.translation.x = confined.x;
+        transform.translation.y = confined.y;
+    }
+}
@@ -0,0 +1,255 @@
+//! Days, nights and seasons. The plant simulation reads the current [SeasonModifiers] to
+//! decide how fast things grow, pollinate and seed.
+
+use bevy::prelude::*;
+
+use crate::{global_systems::ui_util::GameFont, AppState};
+
+/// How long a full day/night lasts in seconds
+const DAY_LENGTH: f32 = 60.0;
+/// How many days before the season changes
+const DAYS_PER_SEASON: u32 = 3;
+/// How dark it gets in the middle of the night
+const NIGHT_DARKNESS: f32 = 0.45;
+
+pub struct WorldClockPlugin;
+impl Plugin for WorldClockPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<WorldClock>()
+            .add_systems(OnEnter(AppState::Gameplay), (reset_clock, setup_ui))
+            .add_systems(OnExit(AppState::Gameplay), teardown_ui)
+            .add_systems(
+                Update,
+                (tick_clock, apply_lighting, update_ui)
+                    .chain()
+                    .run_if(in_state(AppState::Gameplay)),
+            );
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum Season {
+    #[default]
+    Spring,
+    Summer,
+    Autumn,
+    Winter,
+}
+
+impl Season {
+    pub fn next(&self) -> Self {
+        match self {
+            Season::Spring => Season::Summer,
+            Season::Summer => Season::Autumn,
+            Season::Autumn => Season::Winter,
+            Season::Winter => Season::Spring,
+        }
+    }
+
+    pub fn modifiers(&self) -> SeasonModifiers {
+        match self {
+            // everything blooms
+            Season::Spring => SeasonModifiers {
+                growth: 1.5,
+                pollination: 2.0,
+                seeding: 1.0,
+                spawn: 1.25,
+            },
+            Season::Summer => SeasonModifiers {
+                growth: 1.0,
+                pollination: 1.0,
+                seeding: 1.5,
+                spawn: 1.0,
+            },
+            Season::Autumn => SeasonModifiers {
+                growth: 0.5,
+                pollination: 0.25,
+                seeding: 2.0,
+                spawn: 0.75,
+            },
+            // dormant
+            Season::Winter => SeasonModifiers {
+                growth: 0.0,
+                pollination: 0.0,
+                seeding: 0.0,
+                spawn: 0.25,
+            },
+        }
+    }
+
+    /// Colour the world is washed with during the day
+    fn tint(&self) -> Color {
+        match self {
+            Season::Spring => Color::rgba(0.6, 1.0, 0.6, 0.05),
+            Season::Summer => Color::rgba(1.0, 0.9, 0.4, 0.08),
+            Season::Autumn => Color::rgba(1.0, 0.5, 0.1, 0.12),
+            Season::Winter => Color::rgba(0.8, 0.9, 1.0, 0.2),
+        }
+    }
+}
+
+/// Multipliers on the base rates of the plant simulation, 1.0 is business as usual
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SeasonModifiers {
+    /// How fast saplings mature
+    pub growth: f32,
+    /// How likely a mature tree is to be pollinated, and therefore to seed at all
+    pub pollination: f32,
+    /// How many seeds a pollinated tree releases
+    pub seeding: f32,
+    /// How many new trees sprout in the spawn zones
+    pub spawn: f32,
+}
+
+/// In-game time since landing on the planet
+#[derive(Resource, Default)]
+pub struct WorldClock {
+    elapsed: f32,
+}
+
+impl WorldClock {
+    /// Starts at day 1
+    pub fn day(&self) -> u32 {
+        (self.elapsed / DAY_LENGTH) as u32 + 1
+    }
+
+    /// 0.0 is midnight, 0.5 is noon
+    pub fn time_of_day(&self) -> f32 {
+        (self.elapsed / DAY_LENGTH).fract()
+    }
+
+    /// 0.0 in the dead of night, 1.0 at noon
+    pub fn daylight(&self) -> f32 {
+        (1.0 - (self.time_of_day() * std::f32::consts::TAU).cos()) / 2.0
+    }
+
+    pub fn season(&self) -> Season {
+        match ((self.day() - 1) / DAYS_PER_SEASON) % 4 {
+            0 => Season::Spring,
+            1 => Season::Summer,
+            2 => Season::Autumn,
+            _ => Season::Winter,
+        }
+    }
+
+    pub fn modifiers(&self) -> SeasonModifiers {
+        self.season().modifiers()
+    }
+
+    pub fn secs_until_next_season(&self) -> f32 {
+        let season_length = DAY_LENGTH * DAYS_PER_SEASON as f32;
+        season_length - self.elapsed % season_length
+    }
+}
+
+fn reset_clock(mut clock: ResMut<WorldClock>) {
+    // land in the morning so the player can see what they're doing
+    clock.elapsed = DAY_LENGTH * 0.25;
+}
+
+fn tick_clock(mut clock: ResMut<WorldClock>, time: Res<Time>) {
+    let prev_season = clock.season();
+    clock.elapsed += time.delta_seconds();
+
+    if clock.season() != prev_season {
+        info!("Day {}: {:?} has arrived", clock.day(), clock.season());
+    }
+}
+
+/// Full screen overlay that tints the world by season and darkens it at night. Sits under
+/// the rest of the HUD.
+#[derive(Component)]
+struct LightingOverlay;
+
+#[derive(Component)]
+struct ClockUI;
+
+#[derive(Component)]
+struct ClockText;
+
+fn apply_lighting(
+    clock: Res<WorldClock>,
+    mut q_overlay: Query<&mut BackgroundColor, With<LightingOverlay>>,
+) {
+    let night = Color::rgba(0.02, 0.02, 0.12, NIGHT_DARKNESS);
+    let day = clock.season().tint();
+    let t = clock.daylight();
+
+    for mut bg in &mut q_overlay {
+        bg.0 = Color::rgba(
+            night.r() + (day.r() - night.r()) * t,
+            night.g() + (day.g() - night.g()) * t,
+            night.b() + (day.b() - night.b()) * t,
+            night.a() + (day.a() - night.a()) * t,
+        );
+    }
+}
+
+fn setup_ui(mut commands: Commands, font: Res<GameFont>) {
+    commands.spawn((
+        NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                width: Val::Percent(100.0),
+                height: Val::Percent(100.0),
+                ..default()
+            },
+            background_color: Color::NONE.into(),
+            z_index: ZIndex::Global(i32::MAX - 3),
+            ..default()
+        },
+        LightingOverlay,
+        ClockUI,
+    ));
+
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    width: Val::Percent(100.0),
+                    justify_content: JustifyContent::FlexEnd,
+                    ..default()
+                },
+                z_index: ZIndex::Global(i32::MAX - 1),
+                ..default()
+            },
+            ClockUI,
+        ))
+        .with_children(|parent| {
+            parent.spawn((
+                TextBundle::from_section(
+                    "",
+                    TextStyle {
+                        font_size: 28.0,
+                        font: font.0.clone(),
+                        color: Color::WHITE,
+                    },
+                )
+                .with_style(Style {
+                    margin: UiRect::all(Val::Px(15.)),
+                    ..default()
+                }),
+                ClockText,
+            ));
+        });
+}
+
+fn update_ui(clock: Res<WorldClock>, mut q_text: Query<&mut Text, With<ClockText>>) {
+    let season = clock.season();
+    for mut text in &mut q_text {
+        text.sections[0].value = format!(
+            "Day {} - {:?}\n{:?} in {:.0}s",
+            clock.day(),
+            season,
+            season.next(),
+            clock.secs_until_next_season()
+        );
+    }
+}
+
+fn teardown_ui(mut commands: Commands, nodes: Query<Entity, With<ClockUI>>) {
+    for ent in &nodes {
+        commands.entity(ent).despawn_recursive();
+    }
+}
@@ -0,0 +1,418 @@
+//! Contracts. Every so often The Company wants something done by a deadline, defined in
+//! `assets/data/contracts.ron`. Finishing one in time pays a bonus, missing the deadline gets
+//! the player's upkeep raised or budget cut.
+
+use bevy::prelude::*;
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use serde::Deserialize;
+
+use crate::{
+    global_systems::{
+        eargasm::{AudioComponent, AudioRequest, TheCompanyThanksYou},
+        ui_util::GameFont,
+    },
+    AppState, GameSeed, Tree,
+};
+
+use super::{
+    depletion::UpkeepPenalty,
+    map::WorldBounds,
+    resources::{Harvest, Inventory, ResourceType},
+};
+
+/// How long the result of a contract stays on screen, in seconds
+const RESULT_DISPLAY_TIME: f32 = 6.0;
+
+pub struct ContractsPlugin;
+impl Plugin for ContractsPlugin {
+    fn build(&self, app: &mut App) {
+        let table: ContractTable = ron::from_str(include_str!("../../assets/data/contracts.ron"))
+            .expect("Failed to parse contracts.ron!");
+
+        app.insert_resource(table)
+            .add_systems(OnEnter(AppState::Gameplay), (reset_contracts, setup_ui))
+            .add_systems(OnExit(AppState::Gameplay), teardown_ui)
+            .add_systems(
+                Update,
+                (issue_contract, track_progress, settle_contract, update_ui)
+                    .chain()
+                    .run_if(in_state(AppState::Gameplay))
+                    .run_if(resource_exists::<Contracts>()),
+            );
+    }
+}
+
+#[derive(Clone, Copy, Debug, Deserialize)]
+enum Goal {
+    /// Get this much of a resource to the core
+    Deliver { resource: ResourceType, amount: u32 },
+    /// Cut down this fraction of the trees in a random [Sector]
+    ClearSector { fraction: f32 },
+}
+
+#[derive(Clone, Copy, Debug, Deserialize)]
+pub enum Penalty {
+    /// Added to the [UpkeepPenalty] for the rest of the game
+    UpkeepHike(f32),
+    /// Corporation Points taken away
+    BudgetCut(u32),
+}
+
+#[derive(Clone, Debug, Deserialize)]
+struct ContractDef {
+    goal: Goal,
+    /// Seconds
+    time_limit: f32,
+    reward: Vec<(ResourceType, u32)>,
+    penalty: Penalty,
+}
+
+/// Everything `assets/data/contracts.ron` defines
+#[derive(Resource, Clone, Debug, Deserialize)]
+struct ContractTable {
+    first_after: f32,
+    between: f32,
+    contracts: Vec<ContractDef>,
+}
+
+/// The outer ring of a 3x3 grid over the world, the middle is where the core is
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Sector {
+    N,
+    NE,
+    E,
+    SE,
+    S,
+    SW,
+    W,
+    NW,
+}
+
+impl Sector {
+    pub const ALL: [Sector; 8] = [
+        Sector::N,
+        Sector::NE,
+        Sector::E,
+        Sector::SE,
+        Sector::S,
+        Sector::SW,
+        Sector::W,
+        Sector::NW,
+    ];
+
+    /// (column, row) in the grid, with (0, 0) in the bottom left
+    fn cell(&self) -> (i32, i32) {
+        match self {
+            Sector::N => (1, 2),
+            Sector::NE => (2, 2),
+            Sector::E => (2, 1),
+            Sector::SE => (2, 0),
+            Sector::S => (1, 0),
+            Sector::SW => (0, 0),
+            Sector::W => (0, 1),
+            Sector::NW => (0, 2),
+        }
+    }
+
+    pub fn contains(&self, bounds: &WorldBounds, pos: Vec2) -> bool {
+        let cell = ((pos - bounds.min) / bounds.size() * 3.0).floor();
+        (cell.x as i32, cell.y as i32) == self.cell()
+    }
+}
+
+#[derive(Clone, Debug)]
+pub enum Objective {
+    Deliver {
+        resource: ResourceType,
+        amount: u32,
+        delivered: u32,
+    },
+    ClearSector {
+        sector: Sector,
+        fraction: f32,
+        /// Trees in the sector when the contract was issued, anything that sprouts after
+        /// doesn't count
+        trees: Vec<Entity>,
+        /// How many of those are still standing
+        remaining: usize,
+    },
+}
+
+impl Objective {
+    /// 0.0 - 1.0, the contract is done at 1.0
+    pub fn progress(&self) -> f32 {
+        match self {
+            Objective::Deliver {
+                amount, delivered, ..
+            } => (*delivered as f32 / (*amount).max(1) as f32).min(1.0),
+            Objective::ClearSector {
+                fraction,
+                trees,
+                remaining,
+                ..
+            } => {
+                let cleared = 1.0 - *remaining as f32 / trees.len().max(1) as f32;
+                (cleared / fraction).clamp(0.0, 1.0)
+            }
+        }
+    }
+
+    pub fn describe(&self) -> String {
+        match self {
+            Objective::Deliver {
+                resource, amount, ..
+            } => format!("Deliver {} {}", amount, resource.name()),
+            Objective::ClearSector {
+                sector, fraction, ..
+            } => format!(
+                "Clear {:.0}% of flora in sector {:?}",
+                fraction * 100.0,
+                sector
+            ),
+        }
+    }
+}
+
+pub struct Contract {
+    pub objective: Objective,
+    pub deadline: Timer,
+    pub reward: Vec<(ResourceType, u32)>,
+    pub penalty: Penalty,
+}
+
+#[derive(Resource)]
+pub struct Contracts {
+    pub active: Option<Contract>,
+    /// Until the next contract is issued
+    next: Timer,
+    /// What happened to the last contract, shown for a little while
+    last_result: Option<(String, Timer)>,
+    rng: StdRng,
+}
+
+fn reset_contracts(mut commands: Commands, seed: Res<GameSeed>, table: Res<ContractTable>) {
+    commands.insert_resource(Contracts {
+        active: None,
+        next: Timer::from_seconds(table.first_after, TimerMode::Once),
+        last_result: None,
+        // a different stream from the weather's, so they don't line up
+        rng: StdRng::seed_from_u64(seed.0.rotate_left(17)),
+    });
+}
+
+fn issue_contract(
+    mut contracts: ResMut<Contracts>,
+    table: Res<ContractTable>,
+    time: Res<Time>,
+    bounds: Res<WorldBounds>,
+    q_trees: Query<(Entity, &Transform), With<Tree>>,
+) {
+    let contracts = &mut *contracts;
+    if contracts.active.is_some() || !contracts.next.tick(time.delta()).finished() {
+        return;
+    }
+    if table.contracts.is_empty() {
+        return;
+    }
+
+    let def = &table.contracts[contracts.rng.gen_range(0..table.contracts.len())];
+    let objective = match def.goal {
+        Goal::Deliver { resource, amount } => Objective::Deliver {
+            resource,
+            amount,
+            delivered: 0,
+        },
+        Goal::ClearSector { fraction } => {
+            let in_sector = |sector: &Sector| -> Vec<Entity> {
+                q_trees
+                    .iter()
+                    .filter(|(_, tf)| sector.contains(&bounds, tf.translation.truncate()))
+                    .map(|(tree, _)| tree)
+                    .collect()
+            };
+            // nothing to clear in an empty sector, try again next frame if they all are
+            let mut sectors: Vec<_> = Sector::ALL
+                .iter()
+                .map(|sector| (*sector, in_sector(sector)))
+                .filter(|(_, trees)| !trees.is_empty())
+                .collect();
+            if sectors.is_empty() {
+                return;
+            }
+            let (sector, trees) = sectors.swap_remove(contracts.rng.gen_range(0..sectors.len()));
+            Objective::ClearSector {
+                sector,
+                fraction,
+                remaining: trees.len(),
+                trees,
+            }
+        }
+    };
+
+    info!(
+        "New contract: {} in {}s",
+        objective.describe(),
+        def.time_limit
+    );
+    contracts.active = Some(Contract {
+        objective,
+        deadline: Timer::from_seconds(def.time_limit, TimerMode::Once),
+        reward: def.reward.clone(),
+        penalty: def.penalty,
+    });
+}
+
+fn track_progress(
+    mut contracts: ResMut<Contracts>,
+    mut harvests: EventReader<Harvest>,
+    q_trees: Query<(), With<Tree>>,
+) {
+    let harvested: Vec<_> = harvests
+        .read()
+        .map(|harvest| (harvest.0, harvest.1))
+        .collect();
+    let Some(contract) = &mut contracts.active else {
+        return;
+    };
+
+    match &mut contract.objective {
+        Objective::Deliver {
+            resource,
+            delivered,
+            ..
+        } => {
+            *delivered += harvested
+                .iter()
+                .filter(|(harvested, _)| harvested == resource)
+                .map(|(_, amount)| amount)
+                .sum::<u32>();
+        }
+        Objective::ClearSector {
+            trees, remaining, ..
+        } => {
+            *remaining = trees.iter().filter(|tree| q_trees.contains(**tree)).count();
+        }
+    }
+}
+
+/// Pays out finished contracts and punishes the ones that ran out of time
+fn settle_contract(
+    mut contracts: ResMut<Contracts>,
+    table: Res<ContractTable>,
+    time: Res<Time>,
+    mut inventory: ResMut<Inventory>,
+    mut penalty: ResMut<UpkeepPenalty>,
+    mut audio_mngr: EventWriter<AudioRequest>,
+) {
+    let contracts = &mut *contracts;
+    if let Some((_, timer)) = &mut contracts.last_result {
+        if timer.tick(time.delta()).finished() {
+            contracts.last_result = None;
+        }
+    }
+
+    let Some(contract) = &mut contracts.active else {
+        return;
+    };
+    let succeeded = contract.objective.progress() >= 1.0;
+    if !succeeded && !contract.deadline.tick(time.delta()).finished() {
+        return;
+    }
+
+    let result = if succeeded {
+        contract
+            .reward
+            .iter()
+            .for_each(|(resource, amount)| inventory.add(*resource, *amount));
+        audio_mngr.send(AudioRequest {
+            component: AudioComponent::TheCompanyThanksYou(TheCompanyThanksYou),
+        });
+        "Contract complete! The Company thanks you".to_string()
+    } else {
+        match contract.penalty {
+            Penalty::UpkeepHike(hike) => {
+                penalty.0 += hike;
+                format!("Contract failed: upkeep raised {:.0}%", hike * 100.0)
+            }
+            Penalty::BudgetCut(cut) => {
+                inventory.remove(ResourceType::CorporationPoints, cut);
+                format!("Contract failed: budget cut by {} CP", cut)
+            }
+        }
+    };
+    info!("{}", result);
+
+    contracts.last_result = Some((
+        result,
+        Timer::from_seconds(RESULT_DISPLAY_TIME, TimerMode::Once),
+    ));
+    contracts.active = None;
+    contracts.next = Timer::from_seconds(table.between, TimerMode::Once);
+}
+
+#[derive(Component)]
+struct ContractUI;
+
+#[derive(Component)]
+struct ContractText;
+
+fn setup_ui(mut commands: Commands, font: Res<GameFont>) {
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    top: Val::Px(60.0),
+                    width: Val::Percent(100.0),
+                    justify_content: JustifyContent::Center,
+                    ..default()
+                },
+                z_index: ZIndex::Global(i32::MAX - 1),
+                ..default()
+            },
+            ContractUI,
+        ))
+        .with_children(|parent| {
+            parent.spawn((
+                TextBundle::from_section(
+                    "",
+                    TextStyle {
+                        font_size: 28.0,
+                        font: font.0.clone(),
+                        color: Color::GOLD,
+                    },
+                )
+                .with_text_alignment(TextAlignment::Center)
+                .with_style(Style {
+                    margin: UiRect::all(Val::Px(15.)),
+                    ..default()
+                }),
+                ContractText,
+            ));
+        });
+}
+
+fn update_ui(contracts: Res<Contracts>, mut q_text: Query<&mut Text, With<ContractText>>) {
+    let mut lines = vec![];
+    if let Some(contract) = &contracts.active {
+        lines.push(format!(
+            "CONTRACT: {} ({:.0}%) - {:.0}s left",
+            contract.objective.describe(),
+            contract.objective.progress() * 100.0,
+            contract.deadline.remaining_secs()
+        ));
+    }
+    if let Some((result, _)) = &contracts.last_result {
+        lines.push(result.clone());
+    }
+
+    for mut text in &mut q_text {
+        text.sections[0].value = lines.join("\n");
+    }
+}
+
+fn teardown_ui(mut commands: Commands, nodes: Query<Entity, With<ContractUI>>) {
+    for ent in &nodes {
+        commands.entity(ent).despawn_recursive();
+    }
+}
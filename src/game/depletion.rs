@@ -1,12 +1,32 @@
+//! Upkeep. Every second each building charges its [Upkeep], scaled by a curve defined in
+//! `assets/data/economy.ron`, and the [EconomyForecast] keeps track of where that's heading.
+//! Buildings switched off with [BuildingState::Inactive] only pay part of theirs.
+//!
+//! Not being able to pay doesn't end the game straight away, it starts a [Bankruptcy]
+//! countdown. Paying a full bill before it runs out (by selling buildings, harvesting or taking
+//! a [Debt] from The Company) recovers.
+
 use bevy::prelude::*;
+use serde::Deserialize;
 
 use crate::{
-    buildings::Building,
-    global_systems::fade_transition::{transition_to, TransitionState},
+    buildings::{BuildingState, Upkeep},
+    global_systems::{
+        fade_transition::{transition_to, TransitionState},
+        ui_util::GameFont,
+    },
     AppState, GameOver,
 };
 
-use super::resources::Inventory;
+use super::{
+    keybinds::FloraCommand,
+    resources::{Harvest, Inventory, ResourceType},
+};
+
+/// How much the latest second of income counts towards the forecast, the rest is history
+const INCOME_SMOOTHING: f32 = 0.2;
+/// Fraction of its [Upkeep] a switched off building still costs
+const INACTIVE_UPKEEP: f32 = 0.25;
 
 #[derive(Resource)]
 struct DepleteTick(Timer);
@@ -14,48 +34,331 @@ struct DepleteTick(Timer);
 pub struct DepletionPlugin;
 impl Plugin for DepletionPlugin {
     fn build(&self, app: &mut App) {
+        let config: EconomyConfig = ron::from_str(include_str!("../../assets/data/economy.ron"))
+            .expect("Failed to parse economy.ron!");
+
         app.add_event::<GameOver>()
+            .insert_resource(config)
             .insert_resource(DepleteTick(Timer::from_seconds(1.0, TimerMode::Repeating)))
+            .init_resource::<EconomyForecast>()
+            .init_resource::<UpkeepPenalty>()
+            .init_resource::<Debt>()
+            .init_resource::<Bankruptcy>()
+            .add_systems(OnEnter(AppState::Gameplay), (reset_economy, setup_ui))
+            .add_systems(OnExit(AppState::Gameplay), teardown_ui)
             .add_systems(
                 Update,
-                (deplete_your_bank_account).run_if(in_state(AppState::Gameplay)),
+                (
+                    take_loan,
+                    deplete_your_bank_account,
+                    count_down_bankruptcy,
+                    update_ui,
+                )
+                    .chain()
+                    .run_if(in_state(AppState::Gameplay)),
             );
     }
 }
 
+/// Everything `assets/data/economy.ron` defines
+#[derive(Resource, Deserialize)]
+struct EconomyConfig {
+    scale_with_time: bool,
+    /// (seconds, multiplier) sorted by seconds
+    curve: Vec<(f32, f32)>,
+    /// Seconds to recover once bankrupt
+    bankruptcy_grace: f32,
+    loan: LoanTerms,
+}
+
+/// What The Company offers a bankrupt player
+#[derive(Clone, Copy, Debug, Deserialize)]
+pub struct LoanTerms {
+    /// Corporation Points lent
+    pub principal: u32,
+    /// Fraction of the principal owed on top of it
+    pub interest: f32,
+    /// Seconds to pay it all back in
+    pub term: f32,
+}
+
+impl LoanTerms {
+    /// Everything that has to be paid back
+    pub fn total_owed(&self) -> f32 {
+        self.principal as f32 * (1.0 + self.interest)
+    }
+}
+
+impl EconomyConfig {
+    /// What all upkeep is multiplied by this far into the game
+    fn upkeep_multiplier(&self, seconds: f32) -> f32 {
+        if !self.scale_with_time {
+            return 1.0;
+        }
+
+        let after = self.curve.iter().position(|(secs, _)| *secs > seconds);
+        match after {
+            None => self.curve.last().map_or(1.0, |(_, mult)| *mult),
+            Some(0) => self.curve[0].1,
+            Some(i) => {
+                let (from_secs, from) = self.curve[i - 1];
+                let (to_secs, to) = self.curve[i];
+                from + (to - from) * (seconds - from_secs) / (to_secs - from_secs)
+            }
+        }
+    }
+}
+
+/// Where the player's Corporation Points are heading
+#[derive(Resource, Default)]
+pub struct EconomyForecast {
+    /// Seconds since the game started, drives the upkeep curve
+    pub elapsed: f32,
+    /// CP/sec coming in, smoothed over the last few seconds
+    pub income: f32,
+    /// CP/sec going out in upkeep
+    pub expenses: f32,
+    /// None when the player is breaking even or better
+    pub secs_until_bankrupt: Option<f32>,
+    /// CP harvested since the last tick, events don't stick around long enough to wait for it
+    earned_this_tick: u32,
+}
+
+/// Extra upkeep multiplier on top of the curve, The Company raises it when contracts fail
+#[derive(Resource)]
+pub struct UpkeepPenalty(pub f32);
+impl Default for UpkeepPenalty {
+    fn default() -> Self {
+        Self(1.0)
+    }
+}
+
+/// What's still owed to The Company from a loan, paid off every second alongside upkeep
+#[derive(Resource, Default)]
+pub struct Debt {
+    pub owed: f32,
+    /// CP/sec
+    pub installment: f32,
+}
+
+/// Some while the player can't pay their bills, the game is lost when the timer runs out
+#[derive(Resource, Default)]
+pub struct Bankruptcy(pub Option<Timer>);
+
+fn reset_economy(
+    mut forecast: ResMut<EconomyForecast>,
+    mut penalty: ResMut<UpkeepPenalty>,
+    mut debt: ResMut<Debt>,
+    mut bankruptcy: ResMut<Bankruptcy>,
+) {
+    *forecast = EconomyForecast::default();
+    *penalty = UpkeepPenalty::default();
+    *debt = Debt::default();
+    *bankruptcy = Bankruptcy::default();
+}
+
+/// The Company only lends to the bankrupt, and only one loan at a time
+fn take_loan(
+    input: Res<Input<FloraCommand>>,
+    config: Res<EconomyConfig>,
+    bankruptcy: Res<Bankruptcy>,
+    mut debt: ResMut<Debt>,
+    mut inventory: ResMut<Inventory>,
+) {
+    if !input.just_pressed(FloraCommand::TakeLoan) || bankruptcy.0.is_none() || debt.owed > 0.0 {
+        return;
+    }
+
+    let terms = config.loan;
+    info!("Took a loan of {} CP", terms.principal);
+    inventory.add(ResourceType::CorporationPoints, terms.principal);
+    debt.owed = terms.total_owed();
+    debt.installment = debt.owed / terms.term;
+}
+
+#[allow(clippy::too_many_arguments)]
 fn deplete_your_bank_account(
     mut timer: ResMut<DepleteTick>,
     mut inventory: ResMut<Inventory>,
-    mut game_over: EventWriter<GameOver>,
-    q_all_buildings: Query<(Entity, &Building)>,
+    mut forecast: ResMut<EconomyForecast>,
+    mut harvests: EventReader<Harvest>,
+    config: Res<EconomyConfig>,
+    penalty: Res<UpkeepPenalty>,
+    mut debt: ResMut<Debt>,
+    mut bankruptcy: ResMut<Bankruptcy>,
+    q_upkeep: Query<(&Upkeep, Option<&BuildingState>)>,
+    time: Res<Time>,
+) {
+    forecast.elapsed += time.delta_seconds();
+    forecast.earned_this_tick += harvests
+        .read()
+        .filter(|harvest| harvest.0 == ResourceType::CorporationPoints)
+        .map(|harvest| harvest.1)
+        .sum::<u32>();
+    if !timer.0.tick(time.delta()).just_finished() {
+        return;
+    }
+
+    let earned = std::mem::take(&mut forecast.earned_this_tick);
+    forecast.income += (earned as f32 - forecast.income) * INCOME_SMOOTHING;
+
+    info!("Depleting player's corpo points");
+    let base_upkeep: f32 = q_upkeep
+        .iter()
+        .map(|(upkeep, state)| match state {
+            Some(BuildingState::Inactive) => upkeep.0 as f32 * INACTIVE_UPKEEP,
+            _ => upkeep.0 as f32,
+        })
+        .sum();
+    let upkeep = base_upkeep * config.upkeep_multiplier(forecast.elapsed) * penalty.0;
+    let installment = debt.installment.min(debt.owed);
+    let to_subtract = upkeep + installment;
+    forecast.expenses = to_subtract;
+
+    let net_loss = forecast.expenses - forecast.income;
+    forecast.secs_until_bankrupt = (net_loss > 0.0).then(|| inventory.money() as f32 / net_loss);
+
+    if to_subtract as u32 > inventory.money() {
+        // whatever's left goes, The Company isn't picky
+        inventory.remove(ResourceType::CorporationPoints, inventory.money());
+        if bankruptcy.0.is_none() {
+            info!("Can't pay the bills, BANKRUPT");
+            bankruptcy.0 = Some(Timer::from_seconds(
+                config.bankruptcy_grace,
+                TimerMode::Once,
+            ));
+        }
+    } else {
+        inventory.remove(ResourceType::CorporationPoints, to_subtract as u32);
+        debt.owed -= installment;
+        if debt.owed <= 0.0 {
+            *debt = Debt::default();
+        }
+        if bankruptcy.0.take().is_some() {
+            info!("Recovered from bankruptcy");
+        }
+    }
+}
+
+/// Runs down the clock on a bankrupt player
+fn count_down_bankruptcy(
+    mut bankruptcy: ResMut<Bankruptcy>,
     time: Res<Time>,
+    mut game_over: EventWriter<GameOver>,
     mut transition_state: ResMut<TransitionState>,
 ) {
-    if timer.0.tick(time.delta()).just_finished() {
-        info!("Depleting player's corpo points");
-        let multiplier = time_multiplier(time.elapsed_seconds() as u32);
-        let num_buildings = q_all_buildings.iter().count();
-        let to_subtract = multiplier * num_buildings as f32;
-
-        if to_subtract as u32 > inventory.money {
-            info!("GAME OVER");
-            game_over.send(GameOver);
-            transition_to(AppState::GameOver, &mut transition_state);
-        } else {
-            inventory.money -= to_subtract as u32;
+    let Some(timer) = &mut bankruptcy.0 else {
+        return;
+    };
+    if timer.tick(time.delta()).just_finished() {
+        info!("Never recovered from bankruptcy, GAME OVER");
+        game_over.send(GameOver);
+        transition_to(AppState::GameOver, &mut transition_state);
+    }
+}
+
+#[derive(Component)]
+struct BankruptcyUI;
+
+/// The modal itself, only shown while bankrupt
+#[derive(Component)]
+struct BankruptcyModal;
+
+#[derive(Component)]
+struct BankruptcyText;
+
+fn setup_ui(mut commands: Commands, font: Res<GameFont>) {
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    width: Val::Percent(100.0),
+                    height: Val::Percent(100.0),
+                    align_items: AlignItems::Center,
+                    justify_content: JustifyContent::Center,
+                    ..default()
+                },
+                z_index: ZIndex::Global(i32::MAX - 1),
+                ..default()
+            },
+            BankruptcyUI,
+        ))
+        .with_children(|parent| {
+            parent
+                .spawn((
+                    NodeBundle {
+                        style: Style {
+                            display: Display::None,
+                            padding: UiRect::all(Val::Px(20.0)),
+                            ..default()
+                        },
+                        background_color: Color::rgba(0.0, 0.0, 0.0, 0.8).into(),
+                        ..default()
+                    },
+                    BankruptcyModal,
+                ))
+                .with_children(|parent| {
+                    parent.spawn((
+                        TextBundle::from_section(
+                            "",
+                            TextStyle {
+                                font_size: 32.0,
+                                font: font.0.clone(),
+                                color: Color::ORANGE_RED,
+                            },
+                        )
+                        .with_text_alignment(TextAlignment::Center),
+                        BankruptcyText,
+                    ));
+                });
+        });
+}
+
+fn update_ui(
+    bankruptcy: Res<Bankruptcy>,
+    debt: Res<Debt>,
+    config: Res<EconomyConfig>,
+    mut q_modal: Query<&mut Style, With<BankruptcyModal>>,
+    mut q_text: Query<&mut Text, With<BankruptcyText>>,
+) {
+    for mut style in &mut q_modal {
+        let display = match bankruptcy.0 {
+            Some(_) => Display::Flex,
+            None => Display::None,
+        };
+        if style.display != display {
+            style.display = display;
         }
     }
+    let Some(timer) = &bankruptcy.0 else {
+        return;
+    };
+
+    let terms = config.loan;
+    let loan = if debt.owed > 0.0 {
+        format!("Still owing The Company {:.0} CP", debt.owed)
+    } else {
+        format!(
+            "[L] Take a loan: {} CP now, {:.0} CP paid back over {:.0}s",
+            terms.principal,
+            terms.total_owed(),
+            terms.term
+        )
+    };
+    let lines = format!(
+        "BANKRUPT\nPay your bills within {:.0}s or The Company shuts you down\n\n{}\n\
+        [X] Sell the selected building",
+        timer.remaining_secs(),
+        loan
+    );
+    for mut text in &mut q_text {
+        text.sections[0].value = lines.clone();
+    }
 }
 
-fn time_multiplier(seconds: u32) -> f32 {
-    match seconds {
-        // 0..=60 => 1.0,
-        // 61..=120 => 2.0,
-        // 121..=240 => 2.5,
-        // _x => 3.5,
-        0..=60 => 1.0,
-        61..=120 => 1.5,
-        121..=240 => 2.5,
-        _x => 4.0,
+fn teardown_ui(mut commands: Commands, nodes: Query<Entity, With<BankruptcyUI>>) {
+    for ent in &nodes {
+        commands.entity(ent).despawn_recursive();
     }
 }
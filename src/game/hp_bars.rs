@@ -97,7 +97,7 @@ fn update_bars(
         };
 
         let percent = (health.0 as f32 / settings.max as f32).clamp(0.0, 1.0);
-        trace!("Entity hp reduced to {} percent", percent);
+        trace!("Entity hp changed to {} percent", percent);
         let x_offset = (1.0 - percent) * HP_BAR_WIDTHNESS;
         green_sprite.custom_size = Some(Vec2::new(HP_BAR_WIDTHNESS * percent, HP_BAR_THICCNESS));
         let offset = settings.offset.unwrap_or(Vec2::new(0.0, -64.0));
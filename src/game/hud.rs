@@ -2,7 +2,10 @@ use bevy::prelude::*;
 
 use crate::{buildings::BuildingType, AppState};
 
-use super::placement::{PlacementState, PlacementStateChanged};
+use super::{
+    placement::{PlacementState, PlacementStateChanged},
+    research::Research,
+};
 
 #[derive(Component, Default)]
 struct HudElement;
@@ -16,7 +19,8 @@ impl Plugin for HudPlugin {
             .add_systems(OnExit(AppState::Gameplay), teardown)
             .add_systems(
                 Update,
-                (interact, set_button_state).run_if(in_state(AppState::Gameplay)),
+                (interact, set_button_state, show_unlocked_buttons)
+                    .run_if(in_state(AppState::Gameplay)),
             );
     }
 }
@@ -140,8 +144,56 @@ fn setup(
                     ..default()
                 })
                 .with_children(|parent| {
-                    sprite_btn(parent, power_handle, BuildingType::Distribution);
-                    sprite_btn(parent, drain_handle, BuildingType::Drain);
+                    sprite_btn(
+                        parent,
+                        power_handle.clone(),
+                        BuildingType::Distribution,
+                        Color::WHITE,
+                    );
+                    sprite_btn(
+                        parent,
+                        drain_handle.clone(),
+                        BuildingType::Drain,
+                        Color::WHITE,
+                    );
+                    // only drains and pylons have their own button art so far, tinted copies
+                    // of theirs will do for everything else
+                    sprite_btn(
+                        parent,
+                        drain_handle.clone(),
+                        BuildingType::HerbicideSprayer,
+                        Color::rgb(0.6, 1.0, 0.4),
+                    );
+                    sprite_btn(
+                        parent,
+                        drain_handle.clone(),
+                        BuildingType::Flamethrower,
+                        Color::rgb(1.0, 0.5, 0.2),
+                    );
+                    sprite_btn(
+                        parent,
+                        drain_handle,
+                        BuildingType::SeedInterceptor,
+                        Color::rgb(0.5, 0.8, 1.0),
+                    );
+                    sprite_btn(
+                        parent,
+                        power_handle.clone(),
+                        BuildingType::Wall,
+                        Color::GRAY,
+                    );
+                    sprite_btn(
+                        parent,
+                        power_handle.clone(),
+                        BuildingType::Firebreak,
+                        Color::rgb(0.45, 0.3, 0.2),
+                    );
+                    sprite_btn(
+                        parent,
+                        power_handle,
+                        BuildingType::RepairDrone,
+                        Color::rgb(1.0, 0.9, 0.4),
+                    );
                 });
         });
 }
@@ -150,6 +202,7 @@ fn sprite_btn(
     parent: &mut ChildBuilder,
     sprite_atlas_handle: Handle<TextureAtlas>,
     building_type: BuildingType,
+    tint: Color,
 ) {
     parent
         .spawn((
@@ -170,6 +223,7 @@ fn sprite_btn(
             parent.spawn((
                 AtlasImageBundle {
                     texture_atlas: sprite_atlas_handle,
+                    background_color: tint.into(),
                     style: Style {
                         width: Val::Px(PIXEL * 15.0),
                         height: Val::Px(PIXEL * 15.0),
@@ -250,6 +304,35 @@ fn set_button_state(
     }
 }
 
+/// Buildings that haven't been researched yet don't get a button
+fn show_unlocked_buttons(
+    research: Res<Research>,
+    mut q_btn: Query<(&mut Style, &Children), With<MenuButton>>,
+    q_types: Query<&BuildingType>,
+) {
+    if !research.is_changed() {
+        return;
+    }
+
+    for (mut style, children) in &mut q_btn {
+        let unlocked = q_types
+            .iter_many(children)
+            .all(|building_type| research.is_unlocked(building_type));
+        style.display = if unlocked {
+            Display::Flex
+        } else {
+            Display::None
+        };
+    }
+}
+
+/// Whether the cursor is on one of the HUD's buttons, where clicks shouldn't reach the world
+pub fn is_cursor_over_ui(q_interactions: &Query<&Interaction>) -> bool {
+    q_interactions
+        .iter()
+        .any(|interaction| *interaction != Interaction::None)
+}
+
 fn teardown(mut commands: Commands, q_elements: Query<Entity, With<HudElement>>) {
     q_elements.for_each(|element| commands.entity(element).despawn_recursive());
 }
@@ -0,0 +1,420 @@
+//! Side panel with the details of the [SelectedBuilding]. Any building but the core can be
+//! switched off from here, anything drawing power given a [PowerPriority], and drain towers get
+//! buttons for how they pick their targets, see [TargetMode].
+
+use bevy::prelude::*;
+
+use crate::{
+    buildings::{
+        core::TheCore,
+        drain::{DrainTower, TargetMode, MAX_TARGETS_LIMIT},
+        repair::Repairing,
+        synergy::Synergy,
+        Building, BuildingDefinition, BuildingState, BuildingType, SelectedBuilding,
+    },
+    global_systems::ui_util::GameFont,
+    AppState, Health, MaxHealth,
+};
+
+use super::{
+    power::{IsPowered, PowerDraw, PowerPriority, PoweredBy, RequiresPower},
+    research::format_cost,
+    resources::ResourceType,
+};
+
+pub struct InspectorPlugin;
+impl Plugin for InspectorPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(OnEnter(AppState::Gameplay), setup_ui)
+            .add_systems(OnExit(AppState::Gameplay), teardown_ui)
+            .add_systems(
+                Update,
+                (click_building_buttons, click_drain_buttons, update_ui)
+                    .chain()
+                    .run_if(in_state(AppState::Gameplay)),
+            );
+    }
+}
+
+#[derive(Component)]
+struct InspectorUI;
+
+#[derive(Component)]
+struct InspectorText;
+
+/// Holds the [BuildingButton]s, shown for every building but the core
+#[derive(Component)]
+struct BuildingControls;
+
+#[derive(Component, Clone, Copy)]
+enum BuildingButton {
+    Toggle,
+    /// Only shown for buildings that use power
+    NextPriority,
+}
+
+/// Holds the [DrainButton]s, only shown for drain towers
+#[derive(Component)]
+struct DrainControls;
+
+#[derive(Component, Clone, Copy)]
+enum DrainButton {
+    NextMode,
+    FewerTargets,
+    MoreTargets,
+}
+
+fn control_button(
+    parent: &mut ChildBuilder,
+    label: &str,
+    text_style: &TextStyle,
+    button: impl Component,
+) {
+    parent
+        .spawn((
+            ButtonBundle {
+                style: Style {
+                    padding: UiRect::axes(Val::Px(8.0), Val::Px(2.0)),
+                    margin: UiRect::right(Val::Px(6.0)),
+                    ..default()
+                },
+                background_color: Color::rgba(1.0, 1.0, 1.0, 0.15).into(),
+                ..default()
+            },
+            button,
+        ))
+        .with_children(|parent| {
+            parent.spawn(TextBundle::from_section(label, text_style.clone()));
+        });
+}
+
+fn setup_ui(mut commands: Commands, font: Res<GameFont>) {
+    let text_style = TextStyle {
+        font_size: 24.0,
+        font: font.0.clone(),
+        color: Color::WHITE,
+    };
+
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    top: Val::Px(240.0),
+                    right: Val::Px(15.0),
+                    max_width: Val::Px(360.0),
+                    padding: UiRect::all(Val::Px(10.0)),
+                    flex_direction: FlexDirection::Column,
+                    display: Display::None,
+                    ..default()
+                },
+                background_color: Color::rgba(0.0, 0.0, 0.0, 0.6).into(),
+                z_index: ZIndex::Global(i32::MAX - 1),
+                ..default()
+            },
+            InspectorUI,
+        ))
+        .with_children(|parent| {
+            parent.spawn((
+                TextBundle::from_section("", text_style.clone()),
+                InspectorText,
+            ));
+            parent
+                .spawn((
+                    NodeBundle {
+                        style: Style {
+                            margin: UiRect::top(Val::Px(8.0)),
+                            display: Display::None,
+                            ..default()
+                        },
+                        ..default()
+                    },
+                    BuildingControls,
+                ))
+                .with_children(|parent| {
+                    control_button(parent, "On/Off", &text_style, BuildingButton::Toggle);
+                    control_button(
+                        parent,
+                        "Priority",
+                        &text_style,
+                        BuildingButton::NextPriority,
+                    );
+                });
+            parent
+                .spawn((
+                    NodeBundle {
+                        style: Style {
+                            margin: UiRect::top(Val::Px(8.0)),
+                            display: Display::None,
+                            ..default()
+                        },
+                        ..default()
+                    },
+                    DrainControls,
+                ))
+                .with_children(|parent| {
+                    control_button(parent, "Mode", &text_style, DrainButton::NextMode);
+                    control_button(parent, "- Targets", &text_style, DrainButton::FewerTargets);
+                    control_button(parent, "+ Targets", &text_style, DrainButton::MoreTargets);
+                });
+        });
+}
+
+/// Switches the selected building on or off, or moves it along to the next [PowerPriority].
+/// Buildings still under construction can't be switched off.
+fn click_building_buttons(
+    mut commands: Commands,
+    selected: Res<SelectedBuilding>,
+    q_buttons: Query<(&Interaction, &BuildingButton), Changed<Interaction>>,
+    mut q_buildings: Query<
+        (Option<&mut BuildingState>, Option<&PowerPriority>),
+        (With<Building>, Without<TheCore>),
+    >,
+) {
+    let Some(ent) = selected.0 else {
+        return;
+    };
+    let Ok((state, priority)) = q_buildings.get_mut(ent) else {
+        return;
+    };
+    let Some(button) = q_buttons
+        .iter()
+        .find(|(interaction, _)| **interaction == Interaction::Pressed)
+        .map(|(_, button)| *button)
+    else {
+        return;
+    };
+
+    match (button, state) {
+        (BuildingButton::Toggle, None) => {
+            commands.entity(ent).insert(BuildingState::Inactive);
+        }
+        (BuildingButton::Toggle, Some(mut state)) => match *state {
+            BuildingState::Building => {}
+            BuildingState::Active => *state = BuildingState::Inactive,
+            BuildingState::Inactive => *state = BuildingState::Active,
+        },
+        (BuildingButton::NextPriority, _) => {
+            commands
+                .entity(ent)
+                .insert(priority.copied().unwrap_or_default().next());
+        }
+    }
+}
+
+/// Changes how the selected drain tower picks its targets
+fn click_drain_buttons(
+    selected: Res<SelectedBuilding>,
+    q_buttons: Query<(&Interaction, &DrainButton), Changed<Interaction>>,
+    mut q_drains: Query<&mut DrainTower>,
+) {
+    let Some(mut drain) = selected.0.and_then(|ent| q_drains.get_mut(ent).ok()) else {
+        return;
+    };
+    q_buttons
+        .iter()
+        .filter(|(interaction, _)| **interaction == Interaction::Pressed)
+        .for_each(|(_, button)| match button {
+            DrainButton::NextMode => drain.mode = drain.mode.next(),
+            DrainButton::FewerTargets => {
+                drain.max_targets = drain.max_targets.saturating_sub(1).max(1)
+            }
+            DrainButton::MoreTargets => {
+                drain.max_targets = (drain.max_targets + 1).min(MAX_TARGETS_LIMIT)
+            }
+        });
+}
+
+#[allow(clippy::type_complexity)]
+fn update_ui(
+    selected: Res<SelectedBuilding>,
+    mut q_panel: Query<&mut Style, With<InspectorUI>>,
+    mut q_text: Query<&mut Text, With<InspectorText>>,
+    q_buildings: Query<(
+        Option<&BuildingType>,
+        Option<&TheCore>,
+        &Health,
+        &MaxHealth,
+        Option<&BuildingState>,
+        Option<&RequiresPower>,
+        Option<&IsPowered>,
+        Option<&PoweredBy>,
+        Option<&PowerDraw>,
+        Option<&PowerPriority>,
+        Option<&DrainTower>,
+        Option<&Repairing>,
+        Option<&Synergy>,
+    )>,
+    q_names: Query<(Option<&BuildingType>, Option<&TheCore>)>,
+    mut q_building_controls: Query<
+        &mut Style,
+        (
+            With<BuildingControls>,
+            Without<InspectorUI>,
+            Without<DrainControls>,
+        ),
+    >,
+    mut q_building_buttons: Query<
+        (&BuildingButton, &mut Style),
+        (
+            Without<BuildingControls>,
+            Without<InspectorUI>,
+            Without<DrainControls>,
+        ),
+    >,
+    mut q_drain_controls: Query<&mut Style, (With<DrainControls>, Without<InspectorUI>)>,
+) {
+    let Some((
+        building_type,
+        core,
+        hp,
+        max,
+        state,
+        requires_power,
+        powered,
+        powered_by,
+        power_draw,
+        priority,
+        drain,
+        repairing,
+        synergy,
+    )) = selected.0.and_then(|ent| q_buildings.get(ent).ok())
+    else {
+        q_panel.for_each_mut(|mut style| {
+            if style.display != Display::None {
+                style.display = Display::None;
+            }
+        });
+        return;
+    };
+    let shown = |visible: bool| {
+        if visible {
+            Display::Flex
+        } else {
+            Display::None
+        }
+    };
+    let controls = shown(core.is_none());
+    q_building_controls.for_each_mut(|mut style| {
+        if style.display != controls {
+            style.display = controls;
+        }
+    });
+    q_building_buttons.for_each_mut(|(button, mut style)| {
+        let display = match button {
+            BuildingButton::Toggle => Display::Flex,
+            BuildingButton::NextPriority => shown(power_draw.is_some()),
+        };
+        if style.display != display {
+            style.display = display;
+        }
+    });
+    let controls = shown(drain.is_some());
+    q_drain_controls.for_each_mut(|mut style| {
+        if style.display != controls {
+            style.display = controls;
+        }
+    });
+
+    let (name, description) = match (building_type, core) {
+        (Some(building_type), _) => (building_type.name(), building_type.description()),
+        (None, Some(_)) => (TheCore::NAME, TheCore::DESCRIPTION),
+        (None, None) => ("Building", ""),
+    };
+
+    let mut details = format!("{}\n", name);
+    if !description.is_empty() {
+        // descriptions are written as wrapped string literals
+        details += &description.split_whitespace().collect::<Vec<_>>().join(" ");
+        details += "\n";
+    }
+    details += &format!("\nHealth: {}/{}", hp.0, max.0);
+    if repairing.is_some() {
+        details += " (repairing)";
+    }
+    if let Some(state) = state {
+        details += match state {
+            BuildingState::Building => "\nState: Under construction",
+            BuildingState::Active => "\nState: Active",
+            BuildingState::Inactive => "\nState: Inactive",
+        };
+    }
+
+    details += match (requires_power, powered) {
+        (None, _) => "\nPower: Not needed",
+        (Some(_), Some(_)) => "\nPower: Connected",
+        (Some(_), None) if state == Some(&BuildingState::Inactive) => "\nPower: Switched off",
+        (Some(_), None) => "\nPower: NOT CONNECTED",
+    };
+    if let Some(draw) = power_draw {
+        details += &format!(
+            "\nPower use: {:.0}/s, {} priority",
+            draw.0,
+            priority.copied().unwrap_or_default().name()
+        );
+    }
+    if let Some(supplier) = powered_by.and_then(|powered_by| q_names.get(powered_by.0).ok()) {
+        let supplier_name = match supplier {
+            (Some(building_type), _) => building_type.name(),
+            (None, Some(_)) => TheCore::NAME,
+            (None, None) => "Unknown",
+        };
+        details += &format!("\nPowered by: {}", supplier_name);
+    }
+
+    if let Some(drain) = drain {
+        // in a fixed order, the earnings are kept in a map
+        let earned: Vec<_> = ResourceType::ALL
+            .iter()
+            .filter_map(|resource| {
+                drain
+                    .earned
+                    .get(resource)
+                    .map(|amount| (*resource, *amount))
+            })
+            .collect();
+        details += &format!(
+            "\nTrees in range: {}\nHealth drained: {}\nTrees finished off: {}",
+            drain.trees_in_range(),
+            drain.hp_drained,
+            drain.kills
+        );
+        if !earned.is_empty() {
+            details += &format!("\nEarned: {}", format_cost(&earned));
+        }
+        details += &match drain.mode {
+            TargetMode::All => format!(
+                "\nTargeting: {} ({} trees' worth of drain)",
+                drain.mode.name(),
+                drain.max_targets
+            ),
+            _ => format!(
+                "\nTargeting: {} (up to {} trees)",
+                drain.mode.name(),
+                drain.max_targets
+            ),
+        };
+    }
+
+    if let Some(synergy) = synergy.filter(|synergy| !synergy.sources.is_empty()) {
+        details += "\nBonuses:";
+        synergy.sources.iter().for_each(|source| {
+            details += &format!("\n  {}", source);
+        });
+    }
+
+    q_panel.for_each_mut(|mut style| {
+        if style.display != Display::Flex {
+            style.display = Display::Flex;
+        }
+    });
+    for mut text in &mut q_text {
+        text.sections[0].value = details.clone();
+    }
+}
+
+fn teardown_ui(mut commands: Commands, nodes: Query<Entity, With<InspectorUI>>) {
+    for ent in &nodes {
+        commands.entity(ent).despawn_recursive();
+    }
+}
@@ -1,5 +1,5 @@
 use bevy::{
-    app::{App, Plugin, PreUpdate, Update},
+    app::{App, Plugin, PreUpdate},
     ecs::{
         event::{Event, EventReader, EventWriter},
         system::{Res, ResMut},
@@ -8,10 +8,8 @@ use bevy::{
         keyboard::{KeyCode, KeyboardInput},
         Input,
     },
-    log::info,
     prelude::*,
     reflect::Reflect,
-    time::Time,
 };
 use lazy_static::lazy_static;
 use std::collections::HashMap;
@@ -20,8 +18,8 @@ pub struct KeybindPlugin;
 impl Plugin for KeybindPlugin {
     fn build(&self, app: &mut App) {
         app.init_resource::<Input<FloraCommand>>()
-            .add_systems(PreUpdate, keyboard_events)
-            .add_systems(Update, something);
+            .init_resource::<KeybindsPaused>()
+            .add_systems(PreUpdate, keyboard_events);
     }
 }
 
@@ -37,15 +35,49 @@ pub enum FloraCommand {
     Copy,
     Debug,
     Down,
+    EditorBrushGrow,
+    EditorBrushShrink,
+    EditorCore,
+    EditorErase,
+    EditorFill,
+    EditorForest,
+    EditorLoad,
+    EditorPlay,
+    EditorSave,
+    EditorSpawnZone,
+    EditorTerrainDirt,
+    EditorTerrainGrass,
+    EditorTerrainRock,
+    EditorTerrainSand,
+    EditorUndo,
     Left,
     Paste,
     Pause,
+    PlanBuilding,
+    Repair,
     ResetCamera,
     Right,
+    SaveBlueprint,
+    SellBuilding,
+    SetPlaceBattery,
     SetPlaceDistributionTower,
     SetPlaceDrainTower,
+    SetPlaceFirebreak,
+    SetPlaceFlamethrower,
+    SetPlaceGenerator,
+    SetPlaceHerbicideSprayer,
     SetPlaceRadarTower,
+    SetPlaceRepairDrone,
+    SetPlaceRoboport,
+    SetPlaceSeedInterceptor,
+    SetPlaceWall,
+    TakeLoan,
+    ToggleBlueprints,
+    ToggleResearch,
     Up,
+    UpgradeRobotCapacity,
+    UpgradeRobotCount,
+    UpgradeRobotSpeed,
 }
 
 lazy_static! {
@@ -76,12 +108,89 @@ lazy_static! {
         map.insert(FloraCommand::SetPlaceDistributionTower, vec![vec![KeyCode::Key1]]);
         // map.insert(FloraCommand::SetPlaceRadarTower, vec![vec![KeyCode::Key2]]);
         map.insert(FloraCommand::SetPlaceDrainTower, vec![vec![KeyCode::Key2]]);
+        map.insert(FloraCommand::SetPlaceRoboport, vec![vec![KeyCode::Key3]]);
+        map.insert(FloraCommand::SetPlaceGenerator, vec![vec![KeyCode::Key4]]);
+        map.insert(FloraCommand::SetPlaceBattery, vec![vec![KeyCode::Key5]]);
+        map.insert(FloraCommand::SetPlaceHerbicideSprayer, vec![vec![KeyCode::Key6]]);
+        map.insert(FloraCommand::SetPlaceFlamethrower, vec![vec![KeyCode::Key7]]);
+        map.insert(FloraCommand::SetPlaceSeedInterceptor, vec![vec![KeyCode::Key8]]);
+        map.insert(FloraCommand::SetPlaceWall, vec![vec![KeyCode::Key9]]);
+        map.insert(FloraCommand::SetPlaceFirebreak, vec![vec![KeyCode::Key0]]);
+        map.insert(FloraCommand::SetPlaceRepairDrone, vec![vec![KeyCode::Minus]]);
+        map.insert(FloraCommand::ToggleResearch, vec![vec![KeyCode::R]]);
+        map.insert(FloraCommand::ToggleBlueprints, vec![vec![KeyCode::B]]);
+        map.insert(
+            FloraCommand::SaveBlueprint,
+            vec![
+                vec![KeyCode::ControlLeft, KeyCode::B],
+                vec![KeyCode::ControlRight, KeyCode::B],
+            ],
+        );
+        // Staying afloat
+        map.insert(FloraCommand::SellBuilding, vec![vec![KeyCode::X]]);
+        map.insert(
+            FloraCommand::PlanBuilding,
+            vec![vec![KeyCode::ShiftLeft], vec![KeyCode::ShiftRight]],
+        );
+        map.insert(FloraCommand::Repair, vec![vec![KeyCode::F]]);
+        map.insert(FloraCommand::TakeLoan, vec![vec![KeyCode::L]]);
+        // Robot upgrades
+        map.insert(FloraCommand::UpgradeRobotCount, vec![vec![KeyCode::F6]]);
+        map.insert(FloraCommand::UpgradeRobotSpeed, vec![vec![KeyCode::F7]]);
+        map.insert(FloraCommand::UpgradeRobotCapacity, vec![vec![KeyCode::F8]]);
+        // Map editor
+        map.insert(FloraCommand::EditorTerrainDirt, vec![vec![KeyCode::Key1]]);
+        map.insert(FloraCommand::EditorTerrainGrass, vec![vec![KeyCode::Key2]]);
+        map.insert(FloraCommand::EditorTerrainRock, vec![vec![KeyCode::Key3]]);
+        map.insert(FloraCommand::EditorTerrainSand, vec![vec![KeyCode::Key4]]);
+        map.insert(FloraCommand::EditorFill, vec![vec![KeyCode::F]]);
+        map.insert(FloraCommand::EditorForest, vec![vec![KeyCode::T]]);
+        map.insert(FloraCommand::EditorErase, vec![vec![KeyCode::E]]);
+        map.insert(FloraCommand::EditorCore, vec![vec![KeyCode::H]]);
+        map.insert(FloraCommand::EditorSpawnZone, vec![vec![KeyCode::X]]);
+        map.insert(FloraCommand::EditorBrushGrow, vec![vec![KeyCode::BracketRight]]);
+        map.insert(FloraCommand::EditorBrushShrink, vec![vec![KeyCode::BracketLeft]]);
+        map.insert(FloraCommand::EditorPlay, vec![vec![KeyCode::Return]]);
+        map.insert(
+            FloraCommand::EditorUndo,
+            vec![
+                vec![KeyCode::ControlLeft, KeyCode::Z],
+                vec![KeyCode::ControlRight, KeyCode::Z],
+            ],
+        );
+        map.insert(
+            FloraCommand::EditorSave,
+            vec![
+                vec![KeyCode::ControlLeft, KeyCode::S],
+                vec![KeyCode::ControlRight, KeyCode::S],
+            ],
+        );
+        map.insert(
+            FloraCommand::EditorLoad,
+            vec![
+                vec![KeyCode::ControlLeft, KeyCode::L],
+                vec![KeyCode::ControlRight, KeyCode::L],
+            ],
+        );
 
         map
     };
 }
 
-fn keyboard_events(input: Res<Input<KeyCode>>, mut resource: ResMut<Input<FloraCommand>>) {
+/// While set, keys are left alone for whatever is reading typed text and no [FloraCommand]s
+/// get pressed
+#[derive(Resource, Default)]
+pub struct KeybindsPaused(pub bool);
+
+fn keyboard_events(
+    input: Res<Input<KeyCode>>,
+    paused: Res<KeybindsPaused>,
+    mut resource: ResMut<Input<FloraCommand>>,
+) {
+    if paused.0 {
+        resource.reset_all();
+        return;
+    }
     for flora in FLORA_COMMAND_MAPPING.keys() {
         let combos = &FLORA_COMMAND_MAPPING[flora];
 
@@ -114,18 +223,3 @@ fn keyboard_events(input: Res<Input<KeyCode>>, mut resource: ResMut<Input<FloraC
     }
     // info!("{:?}", event);
 }
-
-fn something(inputs: Res<Input<FloraCommand>>, time: Res<Time>) {
-    if inputs.just_pressed(FloraCommand::Copy) {
-        info!("Copy at {}", time.elapsed_seconds());
-    }
-    if inputs.pressed(FloraCommand::Copy) {
-        info!("Copying at {}", time.elapsed_seconds());
-    }
-    if inputs.just_released(FloraCommand::Copy) {
-        info!("Copyed at {}", time.elapsed_seconds());
-    }
-    if inputs.just_pressed(FloraCommand::Paste) {
-        info!("Paste at {}", time.elapsed_seconds());
-    }
-}
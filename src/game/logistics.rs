@@ -0,0 +1,379 @@
+//! Logistics. Harvested resources don't go straight into the
+//! [Inventory](super::resources::Inventory), they pile up in the [Stockpile] of the tower that
+//! harvested them and get passed along the power network, one link at a time, until they reach
+//! [TheCore].
+//!
+//! Every link can only carry [LINK_THROUGHPUT] per tick, so a single pylon serving a dozen
+//! drains becomes a bottleneck.
+
+use std::collections::{HashMap, HashSet, VecDeque};
+
+use bevy::prelude::*;
+
+use crate::{
+    buildings::{core::TheCore, Building},
+    global_systems::ui_util::GameFont,
+    AppState,
+};
+
+use super::{
+    power::{AddBuilding, RemoveBuilding, SupplyRadius},
+    resources::{Harvest, ResourceType},
+};
+
+/// Every *this* many seconds resources move one link along the network
+const TRANSFER_TICK_RATE: f32 = 1.0;
+/// Most resources a single link carries per tick
+pub const LINK_THROUGHPUT: u32 = 40;
+/// Most resources a tower can hold, anything harvested past this is lost
+pub const STOCKPILE_CAPACITY: u32 = 1000;
+/// Where the stock label sits relative to its tower
+const LABEL_OFFSET: Vec3 = Vec3::new(0.0, 44.0, 1.0);
+
+pub struct LogisticsPlugin;
+impl Plugin for LogisticsPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(TransferTick(Timer::from_seconds(
+            TRANSFER_TICK_RATE,
+            TimerMode::Repeating,
+        )))
+        .init_resource::<NetworkStats>()
+        .add_systems(OnEnter(AppState::Gameplay), (reset_stats, setup_ui))
+        .add_systems(OnExit(AppState::Gameplay), teardown_ui)
+        // after buildings spawned or destroyed this frame actually exist or are gone
+        .add_systems(
+            PostUpdate,
+            (rebuild_network).run_if(in_state(AppState::Gameplay)),
+        )
+        .add_systems(
+            Update,
+            (
+                transfer_resources,
+                add_stock_labels,
+                update_stock_labels,
+                draw_network,
+                update_ui,
+            )
+                .chain()
+                .run_if(in_state(AppState::Gameplay)),
+        );
+    }
+}
+
+/// Resources buffered in a building, waiting to be sent further up the network
+#[derive(Component, Default)]
+pub struct Stockpile {
+    pub stock: HashMap<ResourceType, u32>,
+    /// How much went up this building's link last tick
+    pub outflow: u32,
+}
+
+impl Stockpile {
+    pub fn total(&self) -> u32 {
+        self.stock.values().sum()
+    }
+
+    /// Stores as much of `amount` as there is room for, returns how much fit
+    pub fn store(&mut self, resource: ResourceType, amount: u32) -> u32 {
+        let stored = amount.min(STOCKPILE_CAPACITY.saturating_sub(self.total()));
+        if stored > 0 {
+            *self.stock.entry(resource).or_default() += stored;
+        }
+        stored
+    }
+
+    /// Takes out up to `limit` resources, in [ResourceType::ALL] order
+    fn take(&mut self, mut limit: u32) -> Vec<(ResourceType, u32)> {
+        ResourceType::ALL
+            .iter()
+            .filter_map(|resource| {
+                let held = self.stock.get_mut(resource)?;
+                let taken = (*held).min(limit);
+                *held -= taken;
+                limit -= taken;
+                (taken > 0).then_some((*resource, taken))
+            })
+            .collect()
+    }
+}
+
+/// The next building on the way to [TheCore]
+#[derive(Component, Clone, Copy)]
+pub struct Upstream(pub Entity);
+
+/// Whole network numbers for the HUD
+#[derive(Resource, Default)]
+pub struct NetworkStats {
+    /// Delivered to [TheCore] last tick
+    pub delivered: u32,
+    /// Everything still out in the network
+    pub buffered: u32,
+    /// Links that moved as much as they could last tick
+    pub saturated_links: usize,
+    /// Stockpiles with no way to get to the core
+    pub disconnected: usize,
+}
+
+#[derive(Resource)]
+struct TransferTick(Timer);
+
+fn reset_stats(mut stats: ResMut<NetworkStats>) {
+    *stats = NetworkStats::default();
+}
+
+/// Works out every building's [Upstream] with a breadth first search out from [TheCore], so
+/// resources always take the fewest hops. Only buildings that supply power pass things on.
+fn rebuild_network(
+    mut commands: Commands,
+    tower_spawned: EventReader<AddBuilding>,
+    tower_removed: EventReader<RemoveBuilding>,
+    radius_changed: Query<(), Changed<SupplyRadius>>,
+    q_core: Query<(Entity, &Transform, &SupplyRadius), With<TheCore>>,
+    q_nodes: Query<(Entity, &Transform, Option<&SupplyRadius>), With<Stockpile>>,
+) {
+    if tower_spawned.is_empty() && tower_removed.is_empty() && radius_changed.is_empty() {
+        return;
+    }
+    let Ok(core) = q_core.get_single() else {
+        return;
+    };
+
+    let mut visited = HashSet::new();
+    let mut frontier = VecDeque::from([(core.0, core.1.translation.truncate(), core.2 .0)]);
+    while let Some((supplier, supplier_pos, radius)) = frontier.pop_front() {
+        q_nodes
+            .iter()
+            .filter(|(ent, tf, _)| {
+                !visited.contains(ent) && tf.translation.truncate().distance(supplier_pos) < radius
+            })
+            .for_each(|(ent, tf, relay)| {
+                visited.insert(ent);
+                commands.entity(ent).insert(Upstream(supplier));
+                if let Some(relay) = relay {
+                    frontier.push_back((ent, tf.translation.truncate(), relay.0));
+                }
+            });
+    }
+
+    q_nodes
+        .iter()
+        .filter(|(ent, _, _)| !visited.contains(ent))
+        .for_each(|(ent, _, _)| {
+            commands.entity(ent).remove::<Upstream>();
+        });
+}
+
+/// Moves resources one link closer to the core, anything reaching it is [Harvest]ed
+fn transfer_resources(
+    mut timer: ResMut<TransferTick>,
+    time: Res<Time>,
+    mut harvest: EventWriter<Harvest>,
+    mut stats: ResMut<NetworkStats>,
+    mut q_nodes: Query<(Entity, &mut Stockpile, Option<&Upstream>)>,
+    q_core: Query<(), With<TheCore>>,
+) {
+    if !timer.0.tick(time.delta()).just_finished() {
+        return;
+    }
+
+    // everything leaves before anything arrives, so resources move exactly one hop per tick
+    let mut shipments = vec![];
+    let mut new_stats = NetworkStats::default();
+    q_nodes
+        .iter_mut()
+        .for_each(|(ent, mut stockpile, upstream)| {
+            stockpile.outflow = 0;
+            match upstream {
+                Some(upstream) => {
+                    let cargo = stockpile.take(LINK_THROUGHPUT);
+                    stockpile.outflow = cargo.iter().map(|(_, amount)| amount).sum();
+                    if stockpile.outflow >= LINK_THROUGHPUT {
+                        new_stats.saturated_links += 1;
+                    }
+                    shipments.push((ent, upstream.0, cargo));
+                }
+                None => new_stats.disconnected += 1,
+            }
+        });
+
+    // closest to the core first, so whatever a tower couldn't send on is back in its stockpile
+    // before anything further out arrives and fills up the room it left
+    let upstreams: HashMap<Entity, Entity> =
+        shipments.iter().map(|(from, to, _)| (*from, *to)).collect();
+    let hops_to_core = |mut ent: Entity| {
+        let mut hops = 0;
+        // the cap only matters if the links ever go round in a circle
+        while let Some(next) = upstreams.get(&ent).filter(|_| hops <= upstreams.len()) {
+            ent = *next;
+            hops += 1;
+        }
+        hops
+    };
+    shipments.sort_by_cached_key(|(from, _, _)| hops_to_core(*from));
+
+    shipments.into_iter().for_each(|(from, to, cargo)| {
+        if q_core.contains(to) {
+            cargo.into_iter().for_each(|(resource, amount)| {
+                new_stats.delivered += amount;
+                harvest.send(Harvest(resource, amount));
+            });
+            return;
+        }
+
+        // anything that doesn't fit (or lost its destination) stays where it was
+        cargo.into_iter().for_each(|(resource, amount)| {
+            let stored = q_nodes
+                .get_mut(to)
+                .map_or(0, |(_, mut stockpile, _)| stockpile.store(resource, amount));
+            if stored < amount {
+                if let Ok((_, mut stockpile, _)) = q_nodes.get_mut(from) {
+                    let returned = stockpile.store(resource, amount - stored);
+                    stockpile.outflow -= returned;
+                }
+            }
+        });
+    });
+
+    new_stats.buffered = q_nodes
+        .iter()
+        .map(|(_, stockpile, _)| stockpile.total())
+        .sum();
+    *stats = new_stats;
+}
+
+/// World space text above a tower showing what it's holding
+#[derive(Component)]
+struct StockLabel;
+
+fn add_stock_labels(
+    mut commands: Commands,
+    font: Res<GameFont>,
+    q_new: Query<Entity, Added<Stockpile>>,
+) {
+    q_new.iter().for_each(|ent| {
+        let label = commands
+            .spawn((
+                Text2dBundle {
+                    text: Text::from_section(
+                        "",
+                        TextStyle {
+                            font: font.0.clone(),
+                            font_size: 18.0,
+                            color: Color::WHITE,
+                        },
+                    ),
+                    transform: Transform::from_translation(LABEL_OFFSET),
+                    ..default()
+                },
+                StockLabel,
+            ))
+            .id();
+        commands.entity(ent).add_child(label);
+    });
+}
+
+fn update_stock_labels(
+    q_stockpiles: Query<(&Stockpile, &Children, Option<&Upstream>)>,
+    mut q_labels: Query<&mut Text, With<StockLabel>>,
+) {
+    q_stockpiles
+        .iter()
+        .for_each(|(stockpile, children, upstream)| {
+            let mut labels = q_labels.iter_many_mut(children);
+            while let Some(mut text) = labels.fetch_next() {
+                text.sections[0].value = if upstream.is_some() {
+                    format!("{}/{}", stockpile.total(), STOCKPILE_CAPACITY)
+                } else {
+                    format!("{}/{} (no link)", stockpile.total(), STOCKPILE_CAPACITY)
+                };
+                text.sections[0].style.color = if stockpile.total() >= STOCKPILE_CAPACITY {
+                    Color::RED
+                } else {
+                    Color::WHITE
+                };
+            }
+        });
+}
+
+/// Draws every link, green when it has room to spare through to red when it's maxed out
+fn draw_network(
+    mut gizmos: Gizmos,
+    q_nodes: Query<(&Transform, &Stockpile, &Upstream)>,
+    q_buildings: Query<&Transform, With<Building>>,
+) {
+    q_nodes.iter().for_each(|(tf, stockpile, upstream)| {
+        let Ok(upstream_tf) = q_buildings.get(upstream.0) else {
+            return;
+        };
+        let load = stockpile.outflow as f32 / LINK_THROUGHPUT as f32;
+        gizmos.line_2d(
+            tf.translation.truncate(),
+            upstream_tf.translation.truncate(),
+            Color::rgb(load, 1.0 - load, 0.1),
+        );
+    });
+}
+
+#[derive(Component)]
+struct LogisticsUI;
+
+#[derive(Component)]
+struct LogisticsText;
+
+fn setup_ui(mut commands: Commands, font: Res<GameFont>) {
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    width: Val::Percent(100.0),
+                    justify_content: JustifyContent::FlexStart,
+                    ..default()
+                },
+                z_index: ZIndex::Global(i32::MAX - 1),
+                ..default()
+            },
+            LogisticsUI,
+        ))
+        .with_children(|parent| {
+            parent.spawn((
+                TextBundle::from_section(
+                    "",
+                    TextStyle {
+                        font_size: 28.0,
+                        font: font.0.clone(),
+                        color: Color::WHITE,
+                    },
+                )
+                .with_style(Style {
+                    margin: UiRect::all(Val::Px(15.)),
+                    ..default()
+                }),
+                LogisticsText,
+            ));
+        });
+}
+
+fn update_ui(stats: Res<NetworkStats>, mut q_text: Query<&mut Text, With<LogisticsText>>) {
+    let mut summary = format!(
+        "Flow to core: {}/s\nIn transit: {}",
+        (stats.delivered as f32 / TRANSFER_TICK_RATE).round(),
+        stats.buffered
+    );
+    if stats.saturated_links > 0 {
+        summary += &format!("\n{} links at capacity", stats.saturated_links);
+    }
+    if stats.disconnected > 0 {
+        summary += &format!("\n{} towers not connected", stats.disconnected);
+    }
+
+    for mut text in &mut q_text {
+        text.sections[0].value = summary.clone();
+    }
+}
+
+fn teardown_ui(mut commands: Commands, nodes: Query<Entity, With<LogisticsUI>>) {
+    for ent in &nodes {
+        commands.entity(ent).despawn_recursive();
+    }
+}
@@ -1,24 +1,35 @@
 use bevy::{prelude::*, transform::commands, utils::HashMap, window::PrimaryWindow};
 use bevy_ecs_tilemap::prelude::*;
 use image::{GrayImage, ImageFormat, Luma};
+use serde::{Deserialize, Serialize};
+use std::{error::Error, fs, path::Path};
 
-use lazy_static::lazy_static;
 use rand::{thread_rng, Rng};
 
+/// Width and height of a single terrain tile in world units
+pub const TILE_SIZE: f32 = 32.0;
+
 use super::{camera::ViewCamera, placement::PlacementState};
-use crate::{AppState, Teardown};
+use crate::{creeps::TreeSpecies, AppState, Teardown};
 
 pub struct MapPlugin;
 impl Plugin for MapPlugin {
     fn build(&self, app: &mut App) {
         app.init_resource::<CurrentTileHover>()
+            .init_resource::<WorldBounds>()
+            .init_resource::<MapData>()
             .add_systems(
                 OnEnter(AppState::Gameplay),
                 (create_initial_map2, setup_highlight_tile),
             )
+            .add_systems(
+                OnEnter(AppState::MapEditor),
+                (create_initial_map2, setup_highlight_tile),
+            )
             .add_systems(
                 Update,
-                (highlight_tile_labels).run_if(in_state(AppState::Gameplay)),
+                (highlight_tile_labels)
+                    .run_if(in_state(AppState::Gameplay).or_else(in_state(AppState::MapEditor))),
             );
     }
 }
@@ -32,96 +43,489 @@ pub struct CurrentTileHover {
     pub world_pos: Option<Vec2>,
 }
 
-enum TerrainTileType {
+/// The playable area of the world, derived from the generated tilemap (which is centered
+/// on the origin). The camera, tree spawning and building placement all respect these.
+#[derive(Resource, Clone, Debug)]
+pub struct WorldBounds {
+    pub min: Vec2,
+    pub max: Vec2,
+}
+
+impl Default for WorldBounds {
+    fn default() -> Self {
+        Self::from_map_size(&map_size(&brightness_map()), &TILE_SIZE_2D)
+    }
+}
+
+impl WorldBounds {
+    pub fn from_map_size(map_size: &TilemapSize, tile_size: &TilemapTileSize) -> Self {
+        let half = Vec2::new(
+            map_size.x as f32 * tile_size.x,
+            map_size.y as f32 * tile_size.y,
+        ) / 2.0;
+
+        Self {
+            min: -half,
+            max: half,
+        }
+    }
+
+    pub fn size(&self) -> Vec2 {
+        self.max - self.min
+    }
+
+    pub fn contains(&self, pos: Vec2) -> bool {
+        pos.cmpge(self.min).all() && pos.cmple(self.max).all()
+    }
+
+    /// A uniformly random position somewhere in the world
+    pub fn random_pos(&self, rng: &mut impl Rng) -> Vec2 {
+        Vec2::new(
+            rng.gen_range(self.min.x..=self.max.x),
+            rng.gen_range(self.min.y..=self.max.y),
+        )
+    }
+
+    /// Keep a camera centered at `pos` that sees `half_extents` either side of itself from
+    /// looking past the edges of the world.
+    pub fn confine_camera(&self, pos: Vec2, half_extents: Vec2) -> Vec2 {
+        let x = clamp_axis(
+            pos.x,
+            self.min.x + half_extents.x,
+            self.max.x - half_extents.x,
+        );
+        let y = clamp_axis(
+            pos.y,
+            self.min.y + half_extents.y,
+            self.max.y - half_extents.y,
+        );
+
+        Vec2::new(x, y)
+    }
+
+    /// How far a camera with a `viewport` sized window can zoom out before the world no
+    /// longer fills the screen.
+    pub fn max_zoom_out(&self, viewport: Vec2) -> f32 {
+        let size = self.size();
+        (size.x / viewport.x.max(1.0)).min(size.y / viewport.y.max(1.0))
+    }
+}
+
+/// Like [f32::clamp] but settles in the middle instead of panicking when the range is inverted,
+/// which happens when the view is bigger than the world.
+fn clamp_axis(value: f32, min: f32, max: f32) -> f32 {
+    if min > max {
+        (min + max) / 2.0
+    } else {
+        value.clamp(min, max)
+    }
+}
+
+pub const TILE_SIZE_2D: TilemapTileSize = TilemapTileSize {
+    x: TILE_SIZE,
+    y: TILE_SIZE,
+};
+
+/// The kinds of ground a tile can be
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Deserialize)]
+pub enum TerrainTileType {
     Dirt,
     Grass,
     Rock,
     Sand,
 }
 
-lazy_static! {
-    static ref TERRAIN_TILE_TYPE_TO_INDICIE_MAP: HashMap<usize, TerrainTileType> = {
-        let mut m = HashMap::new();
-        m.insert(0usize, TerrainTileType::Sand);
-        m.insert(1usize, TerrainTileType::Sand);
+impl TerrainTileType {
+    pub const ALL: [TerrainTileType; 4] = [
+        TerrainTileType::Dirt,
+        TerrainTileType::Grass,
+        TerrainTileType::Rock,
+        TerrainTileType::Sand,
+    ];
+
+    /// Index of this terrain in `textures/terrain.png`
+    pub fn texture_index(&self) -> u32 {
+        match self {
+            TerrainTileType::Rock => 3,
+            TerrainTileType::Grass => 4,
+            TerrainTileType::Dirt => 5,
+            TerrainTileType::Sand => 6,
+        }
+    }
 
-        m.insert(2usize, TerrainTileType::Dirt);
-        m.insert(3usize, TerrainTileType::Dirt);
+    fn from_brightness(brightness: u8) -> Self {
+        match brightness % 8 {
+            2 | 7 => TerrainTileType::Dirt,
+            3..=5 => TerrainTileType::Grass,
+            6 => TerrainTileType::Rock,
+            _ => TerrainTileType::Sand,
+        }
+    }
 
-        m.insert(4usize, TerrainTileType::Grass);
-        m.insert(5usize, TerrainTileType::Grass);
+    /// Single character representation used by map files
+    fn as_char(&self) -> char {
+        match self {
+            TerrainTileType::Dirt => 'd',
+            TerrainTileType::Grass => 'g',
+            TerrainTileType::Rock => 'r',
+            TerrainTileType::Sand => 's',
+        }
+    }
 
-        m.insert(6usize, TerrainTileType::Grass);
-        m.insert(7usize, TerrainTileType::Dirt);
-        m.insert(8usize, TerrainTileType::Rock);
+    fn from_char(c: char) -> Option<Self> {
+        Self::ALL.into_iter().find(|terrain| terrain.as_char() == c)
+    }
+}
 
-        m
-    };
+/// A tree that's placed on the map before the game starts
+#[derive(Clone, Copy, Debug)]
+pub struct MapTree {
+    pub tile: TilePos,
+    pub species: TreeSpecies,
 }
 
-pub fn create_initial_map2(mut commands: Commands, asset_server: Res<AssetServer>) {
-    info!("Create initial tilemap");
+/// A circular area that new trees sprout in during the game
+#[derive(Clone, Copy, Debug)]
+pub struct SpawnZone {
+    pub center: TilePos,
+    /// In tiles
+    pub radius: u32,
+}
 
-    // Convert the image to grayscale
-    let greyscale_img = brightness_map();
-    let (height, width) = greyscale_img.dimensions();
+impl SpawnZone {
+    pub fn contains(&self, tile: &TilePos) -> bool {
+        let dx = tile.x as f32 - self.center.x as f32;
+        let dy = tile.y as f32 - self.center.y as f32;
+        dx * dx + dy * dy <= (self.radius * self.radius) as f32
+    }
+}
+
+/// Everything needed to start a game on a map. Either generated from our noise texture or
+/// painted by hand in the map editor.
+#[derive(Resource, Clone, Debug)]
+pub struct MapData {
+    pub size: TilemapSize,
+    /// Row major, see [MapData::index]
+    pub tiles: Vec<TerrainTileType>,
+    /// When empty the initial forest is scattered randomly instead
+    pub trees: Vec<MapTree>,
+    /// Where [crate::buildings::core::TheCore] is built
+    pub core: TilePos,
+    /// When empty trees are free to sprout anywhere
+    pub spawn_zones: Vec<SpawnZone>,
+}
+
+impl Default for MapData {
+    fn default() -> Self {
+        Self::generated()
+    }
+}
+
+impl MapData {
+    /// The map made from the static noise texture that ships with the game
+    pub fn generated() -> Self {
+        let greyscale_img = brightness_map();
+        let size = map_size(&greyscale_img);
+
+        // NOTE: the brightness map is transposed onto the tilemap, x goes down the image
+        let tiles = (0..size.y)
+            .flat_map(|y| (0..size.x).map(move |x| (x, y)))
+            .map(|(x, y)| TerrainTileType::from_brightness(greyscale_img.get_pixel(y, x)[0]))
+            .collect();
+
+        Self {
+            size,
+            tiles,
+            trees: Vec::new(),
+            core: TilePos {
+                x: size.x / 2,
+                y: size.y / 2,
+            },
+            spawn_zones: Vec::new(),
+        }
+    }
+
+    pub fn index(&self, tile: &TilePos) -> usize {
+        (tile.y * self.size.x + tile.x) as usize
+    }
+
+    pub fn terrain(&self, tile: &TilePos) -> TerrainTileType {
+        self.tiles[self.index(tile)]
+    }
 
-    let brightness_map: Vec<Vec<u8>> = (0..width)
-        .map(|y| {
-            (0..height)
-                .map(|x| greyscale_img.get_pixel(x, y)[0])
-                .collect()
+    pub fn set_terrain(&mut self, tile: &TilePos, terrain: TerrainTileType) {
+        let idx = self.index(tile);
+        self.tiles[idx] = terrain;
+    }
+
+    pub fn contains_tile(&self, x: i32, y: i32) -> bool {
+        x >= 0 && y >= 0 && (x as u32) < self.size.x && (y as u32) < self.size.y
+    }
+
+    /// World space center of a tile, the tilemap is centered on the origin
+    pub fn tile_center(&self, tile: &TilePos) -> Vec2 {
+        let map_extents = Vec2::new(self.size.x as f32, self.size.y as f32) * TILE_SIZE;
+        Vec2::new(tile.x as f32 + 0.5, tile.y as f32 + 0.5) * TILE_SIZE - map_extents / 2.0
+    }
+
+    /// Buildings sit on the top-centre of their tile, same as [CurrentTileHover::world_pos]
+    pub fn building_pos(&self, tile: &TilePos) -> Vec2 {
+        self.tile_center(tile) + Vec2::new(0.0, TILE_SIZE / 2.0)
+    }
+
+    /// The tile a building at `pos` is standing on, see [MapData::building_pos]
+    pub fn building_tile(&self, pos: Vec2) -> Option<TilePos> {
+        self.world_to_tile(pos - Vec2::new(0.0, TILE_SIZE / 2.0))
+    }
+
+    pub fn core_world_pos(&self) -> Vec2 {
+        self.building_pos(&self.core)
+    }
+
+    pub fn world_to_tile(&self, pos: Vec2) -> Option<TilePos> {
+        let map_extents = Vec2::new(self.size.x as f32, self.size.y as f32) * TILE_SIZE;
+        let tile = ((pos + map_extents / 2.0) / TILE_SIZE).floor();
+        self.contains_tile(tile.x as i32, tile.y as i32)
+            .then_some(TilePos {
+                x: tile.x as u32,
+                y: tile.y as u32,
+            })
+    }
+
+    /// All tiles within `radius` tiles of `center` that are on the map
+    pub fn tiles_in_radius(&self, center: &TilePos, radius: u32) -> Vec<TilePos> {
+        let r = radius as i32;
+        (-r..=r)
+            .flat_map(|dy| (-r..=r).map(move |dx| (dx, dy)))
+            .filter(|(dx, dy)| dx * dx + dy * dy <= r * r)
+            .map(|(dx, dy)| (center.x as i32 + dx, center.y as i32 + dy))
+            .filter(|(x, y)| self.contains_tile(*x, *y))
+            .map(|(x, y)| TilePos {
+                x: x as u32,
+                y: y as u32,
+            })
+            .collect()
+    }
+
+    /// Replaces the connected area of same-terrain tiles around `start`, returning the tiles
+    /// that were changed.
+    pub fn flood_fill(&mut self, start: &TilePos, terrain: TerrainTileType) -> Vec<TilePos> {
+        let target = self.terrain(start);
+        if target == terrain {
+            return Vec::new();
+        }
+
+        let mut changed = Vec::new();
+        let mut stack = vec![*start];
+        while let Some(tile) = stack.pop() {
+            if self.terrain(&tile) != target {
+                continue;
+            }
+            self.set_terrain(&tile, terrain);
+            changed.push(tile);
+
+            [(-1, 0), (1, 0), (0, -1), (0, 1)]
+                .into_iter()
+                .map(|(dx, dy)| (tile.x as i32 + dx, tile.y as i32 + dy))
+                .filter(|(x, y)| self.contains_tile(*x, *y))
+                .for_each(|(x, y)| {
+                    stack.push(TilePos {
+                        x: x as u32,
+                        y: y as u32,
+                    })
+                });
+        }
+        changed
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn save(&self, path: &str) -> Result<(), Box<dyn Error>> {
+        let file = MapFile::from(self);
+        let contents = ron::ser::to_string_pretty(&file, ron::ser::PrettyConfig::default())?;
+        if let Some(dir) = Path::new(path).parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(path, contents)?;
+        Ok(())
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn load(path: &str) -> Result<Self, Box<dyn Error>> {
+        let file: MapFile = ron::from_str(&fs::read_to_string(path)?)?;
+        file.try_into()
+    }
+}
+
+/// On disk representation of [MapData], terrain is stored as one string per row to keep the
+/// files readable (and diffable).
+#[derive(Serialize, Deserialize)]
+struct MapFile {
+    width: u32,
+    height: u32,
+    rows: Vec<String>,
+    /// (x, y, species)
+    trees: Vec<(u32, u32, TreeSpecies)>,
+    core: (u32, u32),
+    /// (x, y, radius)
+    spawn_zones: Vec<(u32, u32, u32)>,
+}
+
+impl From<&MapData> for MapFile {
+    fn from(map: &MapData) -> Self {
+        Self {
+            width: map.size.x,
+            height: map.size.y,
+            rows: map
+                .tiles
+                .chunks(map.size.x as usize)
+                .map(|row| row.iter().map(TerrainTileType::as_char).collect())
+                .collect(),
+            trees: map
+                .trees
+                .iter()
+                .map(|tree| (tree.tile.x, tree.tile.y, tree.species))
+                .collect(),
+            core: (map.core.x, map.core.y),
+            spawn_zones: map
+                .spawn_zones
+                .iter()
+                .map(|zone| (zone.center.x, zone.center.y, zone.radius))
+                .collect(),
+        }
+    }
+}
+
+impl TryFrom<MapFile> for MapData {
+    type Error = Box<dyn Error>;
+
+    fn try_from(file: MapFile) -> Result<Self, Self::Error> {
+        if file.rows.len() != file.height as usize {
+            return Err(format!(
+                "Expected {} rows for a {}x{} map, found {}",
+                file.height,
+                file.width,
+                file.height,
+                file.rows.len()
+            )
+            .into());
+        }
+        if let Some((y, row)) = file
+            .rows
+            .iter()
+            .enumerate()
+            .find(|(_, row)| row.chars().count() != file.width as usize)
+        {
+            return Err(format!(
+                "Expected {} tiles in row {}, found {}",
+                file.width,
+                y,
+                row.chars().count()
+            )
+            .into());
+        }
+        // everything placed on the map has to be on it, a hand edited file might not be
+        let on_map = |what: &str, (x, y): (u32, u32)| {
+            if x < file.width && y < file.height {
+                Ok(())
+            } else {
+                Err(format!(
+                    "{what} at ({x}, {y}) is off the {}x{} map",
+                    file.width, file.height
+                ))
+            }
+        };
+        on_map("The core", file.core)?;
+        file.trees
+            .iter()
+            .try_for_each(|(x, y, _)| on_map("A tree", (*x, *y)))?;
+        file.spawn_zones
+            .iter()
+            .try_for_each(|(x, y, _)| on_map("A spawn zone", (*x, *y)))?;
+
+        let tiles = file
+            .rows
+            .iter()
+            .flat_map(|row| row.chars())
+            .map(|c| TerrainTileType::from_char(c).ok_or(format!("Unknown terrain '{c}'")))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let tile = |(x, y): (u32, u32)| TilePos { x, y };
+        Ok(Self {
+            size: TilemapSize {
+                x: file.width,
+                y: file.height,
+            },
+            tiles,
+            trees: file
+                .trees
+                .into_iter()
+                .map(|(x, y, species)| MapTree {
+                    tile: tile((x, y)),
+                    species,
+                })
+                .collect(),
+            core: tile(file.core),
+            spawn_zones: file
+                .spawn_zones
+                .into_iter()
+                .map(|(x, y, radius)| SpawnZone {
+                    center: tile((x, y)),
+                    radius,
+                })
+                .collect(),
         })
-        .collect();
+    }
+}
+
+/// Marker for the tilemap so other systems (like the map editor) can repaint tiles
+#[derive(Component)]
+pub struct TerrainTilemap;
 
+pub fn create_initial_map2(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    mut bounds: ResMut<WorldBounds>,
+    map: Res<MapData>,
+) {
+    info!("Create initial tilemap");
+    spawn_tilemap(&mut commands, &asset_server, &map);
+
+    *bounds = WorldBounds::from_map_size(&map.size, &TILE_SIZE_2D);
+}
+
+/// Spawns the tiles for a [MapData], returning the tilemap entity
+pub fn spawn_tilemap(commands: &mut Commands, asset_server: &AssetServer, map: &MapData) -> Entity {
     let texture = asset_server.load("textures/terrain.png");
-    let map_size = TilemapSize {
-        y: brightness_map.first().unwrap().len() as u32,
-        x: brightness_map.len() as u32,
-    };
+    let map_size = map.size;
     let mut tile_storage = TileStorage::empty(map_size);
     let tilemap_entity = commands.spawn_empty().id();
 
-    brightness_map
-        .into_iter()
-        .enumerate()
-        .for_each(|(x_idx, ixs)| {
-            ixs.iter().enumerate().for_each(|(y_idx, brightness)| {
-                let tile_pos = TilePos {
-                    x: x_idx as u32,
-                    y: y_idx as u32,
-                };
-
-                let tile_entity = commands
-                    .spawn(TileBundle {
+    (0..map_size.y)
+        .flat_map(|y| (0..map_size.x).map(move |x| TilePos { x, y }))
+        .for_each(|tile_pos| {
+            // tiles aren't children of the tilemap so they need their own Teardown
+            let tile_entity = commands
+                .spawn((
+                    TileBundle {
                         position: tile_pos,
                         tilemap_id: TilemapId(tilemap_entity),
-                        texture_index: TileTextureIndex(match *brightness % 8u8 {
-                            0 => 6,
-                            1 => 6,
-                            2 => 5,
-                            3 => 4,
-                            4 => 4,
-                            5 => 4,
-                            6 => 3,
-                            7 => 5,
-                            _ => 0,
-                        } as u32),
+                        texture_index: TileTextureIndex(map.terrain(&tile_pos).texture_index()),
                         ..Default::default()
-                    })
-                    .id();
+                    },
+                    Teardown,
+                ))
+                .id();
 
-                tile_storage.set(&tile_pos, tile_entity);
-            });
+            tile_storage.set(&tile_pos, tile_entity);
         });
 
-    let tile_size = TilemapTileSize { x: 32.0, y: 32.0 };
+    let tile_size = TILE_SIZE_2D;
     let grid_size = tile_size.into();
     let map_type = TilemapType::Square;
 
     commands
         .entity(tilemap_entity)
-        .insert(Teardown)
+        .insert((Teardown, TerrainTilemap))
         .insert(TilemapBundle {
             grid_size,
             map_type,
@@ -132,6 +536,17 @@ pub fn create_initial_map2(mut commands: Commands, asset_server: Res<AssetServer
             transform: get_tilemap_center_transform(&map_size, &grid_size, &map_type, 0.0),
             ..Default::default()
         });
+
+    tilemap_entity
+}
+
+/// Tilemap dimensions for a brightness map, one tile per pixel
+fn map_size(img: &GrayImage) -> TilemapSize {
+    let (height, width) = img.dimensions();
+    TilemapSize {
+        x: width,
+        y: height,
+    }
 }
 
 fn brightness_map() -> GrayImage {
@@ -221,7 +636,7 @@ fn highlight_tile_labels(
             let transform = *map_transform * Transform::from_translation(tile_center);
 
             let tile_top_left = Vec2::new(transform.translation.x, transform.translation.y)
-                + Vec2::new(0.0, 32.0 / 2.0);
+                + Vec2::new(0.0, TILE_SIZE / 2.0);
             tile_hover.tile_pos = Some(tile_pos);
             tile_hover.world_pos = Some(tile_top_left);
 
@@ -229,6 +644,9 @@ fn highlight_tile_labels(
         }
     } else {
         *hr = Transform::default();
+        // Don't leave a stale tile around for placement once the cursor leaves the map
+        tile_hover.tile_pos = None;
+        tile_hover.world_pos = None;
     }
 }
 
@@ -1,9 +1,18 @@
+pub mod blueprints;
+pub mod build_queue;
 pub mod camera;
+pub mod clock;
+pub mod contracts;
 pub mod depletion;
 pub mod hp_bars;
 pub mod hud;
+pub mod inspector;
 pub mod keybinds;
+pub mod logistics;
 pub mod map;
+pub mod occupancy;
 pub mod placement;
 pub mod power;
+pub mod research;
 pub mod resources;
+pub mod weather;
@@ -0,0 +1,253 @@
+//! Which tiles are taken, and by what.
+//!
+//! **LOGIC:**
+//!
+//! - every building covers the tiles of its [Footprint], starting from the tile it stands on
+//! - trees hold the tile they grew on, however many of them share it
+//! - planned buildings waiting in the build queue hold their footprint with a [Reservation]
+//! - nothing can be built on a tile that's held, and nothing sprouts under a building
+//!
+//! The [OccupancyGrid] keeps up with buildings, trees and plans as they come and go.
+
+use bevy::{prelude::*, utils::HashMap};
+use bevy_ecs_tilemap::prelude::{TilePos, TilemapSize};
+
+use crate::{buildings::Footprint, AppState, Tree};
+
+use super::map::MapData;
+
+pub struct OccupancyPlugin;
+impl Plugin for OccupancyPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<OccupancyGrid>()
+            .add_systems(OnEnter(AppState::Gameplay), reset_grid)
+            // after buildings and trees spawned or despawned this frame actually exist or are gone
+            .add_systems(
+                PostUpdate,
+                (track_buildings, track_trees, track_reservations)
+                    .run_if(in_state(AppState::Gameplay)),
+            );
+    }
+}
+
+/// Holds the tiles of a building's [Footprint] until it's built, put on the ghost of a planned
+/// building standing where it'll go
+#[derive(Component, Clone, Copy)]
+pub struct Reservation(pub Footprint);
+
+/// What's on a single tile
+#[derive(Clone, Copy, Default)]
+struct TileOccupancy {
+    building: Option<Entity>,
+    trees: u32,
+    /// The planned building waiting for this tile, see [Reservation]
+    reserved: Option<Entity>,
+}
+
+/// Every tile on the map and whether something's on it, row major like [MapData::index]
+#[derive(Resource)]
+pub struct OccupancyGrid {
+    size: TilemapSize,
+    tiles: Vec<TileOccupancy>,
+    /// The tiles each building covers, to free them up again when it's gone
+    building_tiles: HashMap<Entity, Vec<TilePos>>,
+    /// The tile each tree grew on, same deal
+    tree_tiles: HashMap<Entity, TilePos>,
+    /// The tiles each [Reservation] holds, same deal
+    reserved_tiles: HashMap<Entity, Vec<TilePos>>,
+}
+
+impl Default for OccupancyGrid {
+    fn default() -> Self {
+        Self::new(TilemapSize { x: 0, y: 0 })
+    }
+}
+
+impl OccupancyGrid {
+    pub fn new(size: TilemapSize) -> Self {
+        Self {
+            size,
+            tiles: vec![TileOccupancy::default(); (size.x * size.y) as usize],
+            building_tiles: HashMap::default(),
+            tree_tiles: HashMap::default(),
+            reserved_tiles: HashMap::default(),
+        }
+    }
+
+    fn index(&self, tile: &TilePos) -> usize {
+        (tile.y * self.size.x + tile.x) as usize
+    }
+
+    /// The tiles a building with `footprint` standing on `anchor` would cover, see
+    /// [Footprint::offsets]. None when any of them would be off the map.
+    pub fn footprint_tiles(&self, footprint: &Footprint, anchor: &TilePos) -> Option<Vec<TilePos>> {
+        footprint
+            .offsets()
+            .map(|(dx, dy)| {
+                let (x, y) = (anchor.x as i32 + dx, anchor.y as i32 + dy);
+                (x >= 0 && y >= 0 && (x as u32) < self.size.x && (y as u32) < self.size.y)
+                    .then_some(TilePos {
+                        x: x as u32,
+                        y: y as u32,
+                    })
+            })
+            .collect()
+    }
+
+    pub fn building_at(&self, tile: &TilePos) -> Option<Entity> {
+        self.tiles
+            .get(self.index(tile))
+            .and_then(|occ| occ.building)
+    }
+
+    pub fn has_trees(&self, tile: &TilePos) -> bool {
+        self.tiles
+            .get(self.index(tile))
+            .map_or(false, |occ| occ.trees > 0)
+    }
+
+    /// The planned building holding this tile, if any
+    pub fn reserved_by(&self, tile: &TilePos) -> Option<Entity> {
+        self.tiles
+            .get(self.index(tile))
+            .and_then(|occ| occ.reserved)
+    }
+
+    pub fn is_free(&self, tile: &TilePos) -> bool {
+        self.building_at(tile).is_none()
+            && !self.has_trees(tile)
+            && self.reserved_by(tile).is_none()
+    }
+
+    /// Whether a building with `footprint` can go down on `anchor`: all of it on the map, and
+    /// none of it on something else
+    pub fn fits(&self, footprint: &Footprint, anchor: &TilePos) -> bool {
+        self.footprint_tiles(footprint, anchor)
+            .map_or(false, |tiles| tiles.iter().all(|tile| self.is_free(tile)))
+    }
+
+    /// Like [OccupancyGrid::fits], for building the plan holding `reservation` on its own
+    /// tiles
+    pub fn fits_reserved(
+        &self,
+        footprint: &Footprint,
+        anchor: &TilePos,
+        reservation: Entity,
+    ) -> bool {
+        self.footprint_tiles(footprint, anchor)
+            .map_or(false, |tiles| {
+                tiles.iter().all(|tile| {
+                    self.building_at(tile).is_none()
+                        && !self.has_trees(tile)
+                        && self
+                            .reserved_by(tile)
+                            .map_or(true, |reserved| reserved == reservation)
+                })
+            })
+    }
+
+    fn add_building(&mut self, ent: Entity, tiles: Vec<TilePos>) {
+        tiles.iter().for_each(|tile| {
+            let idx = self.index(tile);
+            self.tiles[idx].building = Some(ent);
+        });
+        self.building_tiles.insert(ent, tiles);
+    }
+
+    fn remove_building(&mut self, ent: Entity) {
+        let Some(tiles) = self.building_tiles.remove(&ent) else {
+            return;
+        };
+        tiles.iter().for_each(|tile| {
+            let idx = self.index(tile);
+            if self.tiles[idx].building == Some(ent) {
+                self.tiles[idx].building = None;
+            }
+        });
+    }
+
+    fn add_reservation(&mut self, ent: Entity, tiles: Vec<TilePos>) {
+        tiles.iter().for_each(|tile| {
+            let idx = self.index(tile);
+            self.tiles[idx].reserved = Some(ent);
+        });
+        self.reserved_tiles.insert(ent, tiles);
+    }
+
+    fn remove_reservation(&mut self, ent: Entity) {
+        let Some(tiles) = self.reserved_tiles.remove(&ent) else {
+            return;
+        };
+        tiles.iter().for_each(|tile| {
+            let idx = self.index(tile);
+            if self.tiles[idx].reserved == Some(ent) {
+                self.tiles[idx].reserved = None;
+            }
+        });
+    }
+
+    fn add_tree(&mut self, ent: Entity, tile: TilePos) {
+        let idx = self.index(&tile);
+        self.tiles[idx].trees += 1;
+        self.tree_tiles.insert(ent, tile);
+    }
+
+    fn remove_tree(&mut self, ent: Entity) {
+        let Some(tile) = self.tree_tiles.remove(&ent) else {
+            return;
+        };
+        let idx = self.index(&tile);
+        self.tiles[idx].trees = self.tiles[idx].trees.saturating_sub(1);
+    }
+}
+
+fn reset_grid(mut grid: ResMut<OccupancyGrid>, map: Res<MapData>) {
+    *grid = OccupancyGrid::new(map.size);
+}
+
+fn track_buildings(
+    mut grid: ResMut<OccupancyGrid>,
+    map: Res<MapData>,
+    mut removed: RemovedComponents<Footprint>,
+    q_added: Query<(Entity, &Footprint, &Transform), Added<Footprint>>,
+) {
+    // gone first, entity ids get reused
+    removed.read().for_each(|ent| grid.remove_building(ent));
+    q_added.iter().for_each(|(ent, footprint, tf)| {
+        let tiles = map
+            .building_tile(tf.translation.truncate())
+            .and_then(|anchor| grid.footprint_tiles(footprint, &anchor))
+            .unwrap_or_default();
+        grid.add_building(ent, tiles);
+    });
+}
+
+fn track_trees(
+    mut grid: ResMut<OccupancyGrid>,
+    map: Res<MapData>,
+    mut removed: RemovedComponents<Tree>,
+    q_added: Query<(Entity, &Transform), Added<Tree>>,
+) {
+    removed.read().for_each(|ent| grid.remove_tree(ent));
+    q_added.iter().for_each(|(ent, tf)| {
+        if let Some(tile) = map.world_to_tile(tf.translation.truncate()) {
+            grid.add_tree(ent, tile);
+        }
+    });
+}
+
+fn track_reservations(
+    mut grid: ResMut<OccupancyGrid>,
+    map: Res<MapData>,
+    mut removed: RemovedComponents<Reservation>,
+    q_added: Query<(Entity, &Reservation, &Transform), Added<Reservation>>,
+) {
+    removed.read().for_each(|ent| grid.remove_reservation(ent));
+    q_added.iter().for_each(|(ent, reservation, tf)| {
+        let tiles = map
+            .building_tile(tf.translation.truncate())
+            .and_then(|anchor| grid.footprint_tiles(&reservation.0, &anchor))
+            .unwrap_or_default();
+        grid.add_reservation(ent, tiles);
+    });
+}
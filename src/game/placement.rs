@@ -1,27 +1,66 @@
 use crate::{
     buildings::{
-        distribution::DistributionTower, spawn_building, twr_custom_mats::TowerRadiusMaterial,
-        BuildingDefinition, BuildingType,
+        barrier::{Firebreak, Wall},
+        distribution::{DistributionTower, DISTRIBUTION_RADIUS},
+        spawn_building,
+        twr_custom_mats::TowerRadiusMaterial,
+        BuildingDefinition, BuildingType, HoveredBuilding,
     },
-    game::{camera::CameraState, power::AddBuilding},
-    global_systems::eargasm::AudioRequest,
+    game::{
+        camera::CameraState,
+        power::{AddBuilding, IsPowered, SupplyRadius},
+    },
+    global_systems::{eargasm::AudioRequest, ui_util::GameFont},
     AppState, Teardown, PLACEMENT_Z,
 };
 use bevy::prelude::*;
 use bevy::window::PrimaryWindow;
+use bevy_ecs_tilemap::prelude::TilePos;
 
 use super::{
+    blueprints::Layout,
+    build_queue::PlanBuilding,
     camera::ViewCamera,
     keybinds::FloraCommand,
-    map::CurrentTileHover,
-    resources::{ExpendResource, Inventory, ResourceType},
+    map::{CurrentTileHover, MapData, WorldBounds, TILE_SIZE},
+    occupancy::OccupancyGrid,
+    research::{format_cost, Research},
+    resources::{ExpendResource, Inventory},
 };
 
+/// Power line towers go this far into the reach of the one before them, leaving room for
+/// snapping to tiles
+const POWER_LINE_SPACING: f32 = 0.85;
+/// Most towers a single power line can propose
+const POWER_LINE_MAX: usize = 64;
+
 #[derive(Resource, Default)]
 pub struct PlacementState {
     /// Some indicates current type of building user has selected in the UI or via keybinding
     /// to place; None indicates no placement is in action. TODO: should disable Tile Highlighting as well
     pub being_placed_building_type: Option<BuildingType>,
+    /// Where the mouse went down when dragging out a line of barriers
+    pub line_start: Option<TilePos>,
+    /// A copied [Layout] being pasted instead of a single building
+    pub pasting: Option<Layout>,
+    /// The chain of distribution towers being dragged out from a powered building
+    pub power_line: Option<PowerLine>,
+}
+
+/// Distribution towers proposed from `start` towards the cursor, see [power_line]
+pub struct PowerLine {
+    /// The building the line is powered from
+    start: Vec2,
+    /// How far from `start` the first tower can go and still get power
+    first_reach: f32,
+    towers: Vec<Vec2>,
+}
+
+impl PlacementState {
+    /// Whether clicks are currently putting down buildings
+    pub fn is_placing(&self) -> bool {
+        self.being_placed_building_type.is_some() || self.pasting.is_some()
+    }
 }
 
 /// Spawn towers when clicked
@@ -29,13 +68,21 @@ pub struct TowerPlacementPlugin;
 impl Plugin for TowerPlacementPlugin {
     fn build(&self, app: &mut App) {
         app.init_resource::<PlacementState>()
-            .add_systems(OnEnter(AppState::Gameplay), setup_ghost_tower)
+            .add_systems(
+                OnEnter(AppState::Gameplay),
+                (setup_ghost_tower, setup_power_line_label),
+            )
             .add_event::<PlacementStateChanged>()
             .add_systems(
                 Update,
                 (
                     change_current_building,
+                    drag_power_line
+                        .before(spawn_at_click_pos)
+                        .before(place_power_line),
                     spawn_at_click_pos,
+                    place_barrier_line,
+                    place_power_line,
                     update_ghost_tower,
                 )
                     .run_if(in_state(AppState::Gameplay)),
@@ -51,6 +98,7 @@ pub struct PlacementStateChanged {
 fn change_current_building(
     mut state: ResMut<PlacementState>,
     input: Res<Input<FloraCommand>>,
+    research: Res<Research>,
     mut changed: EventWriter<PlacementStateChanged>,
 ) {
     let prev_state = state.being_placed_building_type.clone();
@@ -79,7 +127,118 @@ fn change_current_building(
             state.being_placed_building_type = Some(BuildingType::Drain);
         }
     }
+    if input.just_released(FloraCommand::SetPlaceRoboport) {
+        if state
+            .being_placed_building_type
+            .as_ref()
+            .map_or(false, |val| val == &BuildingType::Roboport)
+        {
+            state.being_placed_building_type = None;
+        } else {
+            state.being_placed_building_type = Some(BuildingType::Roboport);
+        }
+    }
+    if input.just_released(FloraCommand::SetPlaceGenerator) {
+        if state
+            .being_placed_building_type
+            .as_ref()
+            .map_or(false, |val| val == &BuildingType::Generator)
+        {
+            state.being_placed_building_type = None;
+        } else {
+            state.being_placed_building_type = Some(BuildingType::Generator);
+        }
+    }
+    if input.just_released(FloraCommand::SetPlaceBattery) {
+        if state
+            .being_placed_building_type
+            .as_ref()
+            .map_or(false, |val| val == &BuildingType::Battery)
+        {
+            state.being_placed_building_type = None;
+        } else {
+            state.being_placed_building_type = Some(BuildingType::Battery);
+        }
+    }
+    if input.just_released(FloraCommand::SetPlaceHerbicideSprayer) {
+        if state
+            .being_placed_building_type
+            .as_ref()
+            .map_or(false, |val| val == &BuildingType::HerbicideSprayer)
+        {
+            state.being_placed_building_type = None;
+        } else {
+            state.being_placed_building_type = Some(BuildingType::HerbicideSprayer);
+        }
+    }
+    if input.just_released(FloraCommand::SetPlaceFlamethrower) {
+        if state
+            .being_placed_building_type
+            .as_ref()
+            .map_or(false, |val| val == &BuildingType::Flamethrower)
+        {
+            state.being_placed_building_type = None;
+        } else {
+            state.being_placed_building_type = Some(BuildingType::Flamethrower);
+        }
+    }
+    if input.just_released(FloraCommand::SetPlaceSeedInterceptor) {
+        if state
+            .being_placed_building_type
+            .as_ref()
+            .map_or(false, |val| val == &BuildingType::SeedInterceptor)
+        {
+            state.being_placed_building_type = None;
+        } else {
+            state.being_placed_building_type = Some(BuildingType::SeedInterceptor);
+        }
+    }
+    if input.just_released(FloraCommand::SetPlaceWall) {
+        if state
+            .being_placed_building_type
+            .as_ref()
+            .map_or(false, |val| val == &BuildingType::Wall)
+        {
+            state.being_placed_building_type = None;
+        } else {
+            state.being_placed_building_type = Some(BuildingType::Wall);
+        }
+    }
+    if input.just_released(FloraCommand::SetPlaceFirebreak) {
+        if state
+            .being_placed_building_type
+            .as_ref()
+            .map_or(false, |val| val == &BuildingType::Firebreak)
+        {
+            state.being_placed_building_type = None;
+        } else {
+            state.being_placed_building_type = Some(BuildingType::Firebreak);
+        }
+    }
+    if input.just_released(FloraCommand::SetPlaceRepairDrone) {
+        if state
+            .being_placed_building_type
+            .as_ref()
+            .map_or(false, |val| val == &BuildingType::RepairDrone)
+        {
+            state.being_placed_building_type = None;
+        } else {
+            state.being_placed_building_type = Some(BuildingType::RepairDrone);
+        }
+    }
+    // can't place what hasn't been researched yet
+    if state
+        .being_placed_building_type
+        .as_ref()
+        .map_or(false, |building| !research.is_unlocked(building))
+    {
+        state.being_placed_building_type = prev_state.clone();
+    }
     if prev_state != state.being_placed_building_type {
+        // picking a building puts away whatever was being pasted
+        if state.being_placed_building_type.is_some() {
+            state.pasting = None;
+        }
         changed.send(PlacementStateChanged {
             value: state.being_placed_building_type.clone(),
         });
@@ -106,11 +265,16 @@ fn setup_ghost_tower(mut commands: Commands) {
     ));
 }
 
-/// Slightly transparent version of the tower sprite the player is placing
+/// Slightly transparent version of the tower sprite the player is placing, with the tiles it
+/// would take up outlined. Both turn red where it doesn't fit.
+#[allow(clippy::too_many_arguments)]
 fn update_ghost_tower(
     _commands: Commands,
+    mut gizmos: Gizmos,
     placement: Res<PlacementState>,
     asset_server: Res<AssetServer>,
+    map: Res<MapData>,
+    occupancy: Res<OccupancyGrid>,
     mut q_ghost: Query<(
         Entity,
         &mut Sprite,
@@ -132,7 +296,7 @@ fn update_ghost_tower(
     if placement.is_changed() {
         match &placement.being_placed_building_type {
             Some(building_type) => {
-                sprite.custom_size = Some(Vec2::new(32.0, 64.0));
+                sprite.custom_size = Some(building_type.footprint().sprite_size());
                 let tex: Handle<Image> = asset_server.load(building_type.sprite());
                 *texture = tex;
                 *vis = Visibility::Visible;
@@ -140,6 +304,33 @@ fn update_ghost_tower(
             None => {}
         }
     }
+
+    // barriers and power lines show their own tiles
+    let blocked = placement
+        .being_placed_building_type
+        .as_ref()
+        .filter(|building| !building.is_barrier() && placement.power_line.is_none())
+        .zip(hover_tile.tile_pos)
+        .map(|(building, anchor)| {
+            let footprint = building.footprint();
+            let tiles = occupancy
+                .footprint_tiles(&footprint, &anchor)
+                .unwrap_or_default();
+            (tiles, !occupancy.fits(&footprint, &anchor))
+        });
+    let tint = match &blocked {
+        Some((_, true)) => Color::rgba(1.0, 0.3, 0.3, 0.7),
+        _ => Color::WHITE,
+    };
+    if sprite.color != tint {
+        sprite.color = tint;
+    }
+    if let Some((tiles, blocked)) = blocked {
+        let outline = if blocked { Color::RED } else { Color::WHITE };
+        tiles.iter().for_each(|tile| {
+            gizmos.rect_2d(map.tile_center(tile), 0.0, Vec2::splat(TILE_SIZE), outline);
+        });
+    }
 }
 
 #[allow(clippy::too_many_arguments)]
@@ -147,37 +338,401 @@ fn spawn_at_click_pos(
     mut commands: Commands,
     mut add_building: EventWriter<AddBuilding>,
     mut expend_resource: EventWriter<ExpendResource>,
-    texture_atlases: ResMut<Assets<TextureAtlas>>,
-    meshes: ResMut<Assets<Mesh>>,
-    materials: ResMut<Assets<TowerRadiusMaterial>>,
+    mut texture_atlases: ResMut<Assets<TextureAtlas>>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<TowerRadiusMaterial>>,
     asset_server: Res<AssetServer>,
     state: Res<PlacementState>,
     mouse_btns: Res<Input<MouseButton>>,
-    audio_mngr: EventWriter<AudioRequest>,
+    mut audio_mngr: EventWriter<AudioRequest>,
+    mut plan_building: EventWriter<PlanBuilding>,
+    input: Res<Input<FloraCommand>>,
     inventory: Res<Inventory>,
     tile_hover: Res<CurrentTileHover>,
+    occupancy: Res<OccupancyGrid>,
+    research: Res<Research>,
 ) {
+    // dragging out a power line instead
+    if state.power_line.is_some() {
+        return;
+    }
     if mouse_btns.just_pressed(MouseButton::Left) {
-        if let Some(building) = &state.being_placed_building_type {
-            if let Some(tile_world_pos) = tile_hover.world_pos {
-                if inventory.money > building.cost() {
+        if let Some(building) = state
+            .being_placed_building_type
+            .as_ref()
+            .filter(|building| !building.is_barrier())
+        {
+            if let (Some(tile_world_pos), Some(anchor)) =
+                (tile_hover.world_pos, tile_hover.tile_pos)
+            {
+                // all of it has to be on the map, and on tiles nothing else is on
+                if !occupancy.fits(&building.footprint(), &anchor)
+                    || !research.is_unlocked(building)
+                {
+                    return;
+                }
+                // can't pay for it yet, build it once the money's there
+                if input.pressed(FloraCommand::PlanBuilding)
+                    || !inventory.can_afford(building.cost())
+                {
+                    plan_building.send(PlanBuilding {
+                        building: building.clone(),
+                        pos: tile_world_pos,
+                    });
+                } else {
                     building.spawn(
                         &mut commands,
-                        texture_atlases,
-                        meshes,
-                        materials,
+                        &mut texture_atlases,
+                        &mut meshes,
+                        &mut materials,
                         asset_server,
                         tile_world_pos,
-                        audio_mngr,
+                        &mut audio_mngr,
                     );
 
-                    expend_resource.send(ExpendResource(
-                        ResourceType::CorporationPoints,
-                        building.cost(),
-                    ));
+                    building.cost().iter().for_each(|(resource, amount)| {
+                        expend_resource.send(ExpendResource(*resource, *amount));
+                    });
                     add_building.send(AddBuilding);
                 }
             }
         }
     }
 }
+
+/// Every tile on a straight line from `start` to `end`, both included
+fn tile_line(start: TilePos, end: TilePos) -> Vec<TilePos> {
+    let (x0, y0) = (start.x as i32, start.y as i32);
+    let (x1, y1) = (end.x as i32, end.y as i32);
+    let (dx, dy) = ((x1 - x0).abs(), -(y1 - y0).abs());
+    let (sx, sy) = ((x1 - x0).signum(), (y1 - y0).signum());
+
+    // Bresenham's
+    let (mut x, mut y, mut err) = (x0, y0, dx + dy);
+    let mut tiles = vec![];
+    loop {
+        tiles.push(TilePos {
+            x: x as u32,
+            y: y as u32,
+        });
+        if x == x1 && y == y1 {
+            return tiles;
+        }
+        let err2 = 2 * err;
+        if err2 >= dy {
+            err += dy;
+            x += sx;
+        }
+        if err2 <= dx {
+            err += dx;
+            y += sy;
+        }
+    }
+}
+
+/// Barriers go down in lines: press where the line starts and release where it ends. Tiles
+/// that are already taken are skipped, and the rest of the line is planned once the money
+/// runs out.
+#[allow(clippy::too_many_arguments)]
+fn place_barrier_line(
+    mut commands: Commands,
+    mut state: ResMut<PlacementState>,
+    mut add_building: EventWriter<AddBuilding>,
+    mut expend_resource: EventWriter<ExpendResource>,
+    mut plan_building: EventWriter<PlanBuilding>,
+    mut gizmos: Gizmos,
+    asset_server: Res<AssetServer>,
+    input: Res<Input<FloraCommand>>,
+    mouse_btns: Res<Input<MouseButton>>,
+    inventory: Res<Inventory>,
+    tile_hover: Res<CurrentTileHover>,
+    map: Res<MapData>,
+    occupancy: Res<OccupancyGrid>,
+    research: Res<Research>,
+) {
+    let Some(building) = state
+        .being_placed_building_type
+        .clone()
+        .filter(BuildingType::is_barrier)
+    else {
+        state.line_start = None;
+        return;
+    };
+
+    if mouse_btns.just_pressed(MouseButton::Left) {
+        state.line_start = tile_hover.tile_pos;
+    }
+    let (Some(start), Some(end)) = (state.line_start, tile_hover.tile_pos) else {
+        return;
+    };
+
+    let footprint = building.footprint();
+    let line = tile_line(start, end);
+    line.iter().for_each(|tile| {
+        let color = if occupancy.fits(&footprint, tile) {
+            Color::WHITE
+        } else {
+            Color::RED
+        };
+        gizmos.rect_2d(map.tile_center(tile), 0.0, Vec2::splat(TILE_SIZE), color);
+    });
+    if !mouse_btns.just_released(MouseButton::Left) {
+        return;
+    }
+    state.line_start = None;
+    if !research.is_unlocked(&building) {
+        return;
+    }
+
+    let mut budget = inventory.clone();
+    let mut placed = 0;
+    line.iter().for_each(|tile| {
+        if !occupancy.fits(&footprint, tile) {
+            return;
+        }
+        let pos = map.building_pos(tile);
+        if input.pressed(FloraCommand::PlanBuilding) || !budget.can_afford(building.cost()) {
+            plan_building.send(PlanBuilding {
+                building: building.clone(),
+                pos,
+            });
+            return;
+        }
+
+        building.cost().iter().for_each(|(resource, amount)| {
+            budget.remove(*resource, *amount);
+            expend_resource.send(ExpendResource(*resource, *amount));
+        });
+        let ent_id = match building {
+            BuildingType::Firebreak => {
+                spawn_building::<Firebreak>(&mut commands, Res::clone(&asset_server), pos)
+            }
+            _ => spawn_building::<Wall>(&mut commands, Res::clone(&asset_server), pos),
+        };
+        commands.entity(ent_id).insert(building.clone());
+        placed += 1;
+    });
+    if placed > 0 {
+        add_building.send(AddBuilding);
+    }
+}
+
+/// Spots for distribution towers from `start` to `end`, each one snapped to a tile and just
+/// inside the reach of the one before it. The first one only has to be within `first_reach` of
+/// `start`. Towers that would land on a taken tile are pulled back towards the one before
+/// them. The line stops early at the edge of the world, or when there's nowhere left to go.
+fn power_line(
+    map: &MapData,
+    bounds: &WorldBounds,
+    occupancy: &OccupancyGrid,
+    start: Vec2,
+    end: Vec2,
+    first_reach: f32,
+    reach: f32,
+) -> Vec<Vec2> {
+    let mut towers = vec![];
+    let (mut from, mut step) = (start, first_reach * POWER_LINE_SPACING);
+    while towers.len() < POWER_LINE_MAX {
+        let to_go = end - from;
+        if to_go.length() < TILE_SIZE {
+            break;
+        }
+        let dir = to_go.normalize();
+        let mut length = to_go.length().min(step);
+        let tile = loop {
+            if length < TILE_SIZE {
+                break None;
+            }
+            match map.building_tile(from + dir * length) {
+                Some(tile) if occupancy.fits(&DistributionTower::FOOTPRINT, &tile) => {
+                    break Some(tile)
+                }
+                _ => length -= TILE_SIZE / 2.0,
+            }
+        };
+        let Some(tile) = tile else {
+            break;
+        };
+        let next = map.building_pos(&tile);
+        if next == from || !bounds.contains(next) {
+            break;
+        }
+        towers.push(next);
+        from = next;
+        step = reach * POWER_LINE_SPACING;
+    }
+    towers
+}
+
+/// Shows what a power line would cost while it's being dragged out
+#[derive(Component)]
+struct PowerLineLabel;
+
+fn setup_power_line_label(mut commands: Commands, font: Res<GameFont>) {
+    commands.spawn((
+        Text2dBundle {
+            text: Text::from_section(
+                "",
+                TextStyle {
+                    font: font.0.clone(),
+                    font_size: 20.0,
+                    color: Color::WHITE,
+                },
+            ),
+            visibility: Visibility::Hidden,
+            ..default()
+        },
+        PowerLineLabel,
+        Teardown,
+    ));
+}
+
+/// Pressing on a building that supplies power while placing distribution towers starts a power
+/// line. While it's dragged the proposed towers, their reach and the total cost are shown.
+#[allow(clippy::too_many_arguments)]
+fn drag_power_line(
+    mut state: ResMut<PlacementState>,
+    mut gizmos: Gizmos,
+    mouse_btns: Res<Input<MouseButton>>,
+    hovered: Res<HoveredBuilding>,
+    tile_hover: Res<CurrentTileHover>,
+    map: Res<MapData>,
+    bounds: Res<WorldBounds>,
+    occupancy: Res<OccupancyGrid>,
+    research: Res<Research>,
+    inventory: Res<Inventory>,
+    q_suppliers: Query<(&Transform, &SupplyRadius), With<IsPowered>>,
+    mut q_label: Query<
+        (&mut Text, &mut Transform, &mut Visibility),
+        (With<PowerLineLabel>, Without<SupplyRadius>),
+    >,
+) {
+    let Ok((mut text, mut label_tf, mut label_vis)) = q_label.get_single_mut() else {
+        return;
+    };
+    if state.being_placed_building_type != Some(BuildingType::Distribution) {
+        if state.power_line.is_some() {
+            state.power_line = None;
+        }
+        *label_vis = Visibility::Hidden;
+        return;
+    }
+
+    if mouse_btns.just_pressed(MouseButton::Left) {
+        state.power_line =
+            hovered
+                .0
+                .and_then(|ent| q_suppliers.get(ent).ok())
+                .map(|(tf, radius)| PowerLine {
+                    start: tf.translation.truncate(),
+                    first_reach: radius.0,
+                    towers: vec![],
+                });
+    }
+    let Some(cursor) = tile_hover.world_pos.filter(|_| state.power_line.is_some()) else {
+        *label_vis = Visibility::Hidden;
+        return;
+    };
+    let Some(line) = state.power_line.as_mut() else {
+        return;
+    };
+
+    let reach = DISTRIBUTION_RADIUS * research.supply_radius_multiplier;
+    line.towers = power_line(
+        &map,
+        &bounds,
+        &occupancy,
+        line.start,
+        cursor,
+        line.first_reach,
+        reach,
+    );
+    let mut from = line.start;
+    line.towers.iter().for_each(|tower| {
+        gizmos.line_2d(from, *tower, Color::YELLOW);
+        gizmos.circle_2d(*tower, reach, Color::rgba(1.0, 1.0, 0.0, 0.3));
+        gizmos.rect_2d(
+            *tower - Vec2::new(0.0, TILE_SIZE / 2.0),
+            0.0,
+            Vec2::splat(TILE_SIZE),
+            Color::WHITE,
+        );
+        from = *tower;
+    });
+
+    let count = line.towers.len() as u32;
+    let cost: Vec<_> = DistributionTower::COST
+        .iter()
+        .map(|(resource, amount)| (*resource, amount * count))
+        .collect();
+    let affordable = inventory.can_afford(&cost);
+    text.sections[0].value = if affordable {
+        format!("{} towers: {}", count, format_cost(&cost))
+    } else {
+        format!("{} towers: {} (rest planned)", count, format_cost(&cost))
+    };
+    text.sections[0].style.color = if affordable {
+        Color::WHITE
+    } else {
+        Color::ORANGE
+    };
+    label_tf.translation = (cursor + Vec2::new(0.0, TILE_SIZE * 1.5)).extend(PLACEMENT_Z);
+    *label_vis = Visibility::Visible;
+}
+
+/// Releasing a power line builds every proposed tower that can be paid for, the rest are
+/// planned (see [PlanBuilding])
+#[allow(clippy::too_many_arguments)]
+fn place_power_line(
+    mut commands: Commands,
+    mut state: ResMut<PlacementState>,
+    mut add_building: EventWriter<AddBuilding>,
+    mut expend_resource: EventWriter<ExpendResource>,
+    mut plan_building: EventWriter<PlanBuilding>,
+    mut audio_mngr: EventWriter<AudioRequest>,
+    mut texture_atlases: ResMut<Assets<TextureAtlas>>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<TowerRadiusMaterial>>,
+    asset_server: Res<AssetServer>,
+    input: Res<Input<FloraCommand>>,
+    mouse_btns: Res<Input<MouseButton>>,
+    inventory: Res<Inventory>,
+) {
+    if !mouse_btns.just_released(MouseButton::Left) {
+        return;
+    }
+    let Some(line) = state.power_line.take() else {
+        return;
+    };
+
+    let building = BuildingType::Distribution;
+    let mut budget = inventory.clone();
+    let mut placed = 0;
+    line.towers.into_iter().for_each(|pos| {
+        if input.pressed(FloraCommand::PlanBuilding) || !budget.can_afford(building.cost()) {
+            plan_building.send(PlanBuilding {
+                building: building.clone(),
+                pos,
+            });
+            return;
+        }
+
+        building.cost().iter().for_each(|(resource, amount)| {
+            budget.remove(*resource, *amount);
+            expend_resource.send(ExpendResource(*resource, *amount));
+        });
+        building.spawn(
+            &mut commands,
+            &mut texture_atlases,
+            &mut meshes,
+            &mut materials,
+            Res::clone(&asset_server),
+            pos,
+            &mut audio_mngr,
+        );
+        placed += 1;
+    });
+    if placed > 0 {
+        add_building.send(AddBuilding);
+    }
+}
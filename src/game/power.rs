@@ -1,15 +1,40 @@
 //! Power system
+//!
+//! Coverage decides which buildings are connected at all ([IsPowered]). The [PowerGrid] then
+//! balances what the connected buildings make against what they use every tick: [Generator]s
+//! fire up when there's a shortfall, [Battery]s soak up the surplus and cover spikes, and if
+//! it's still not enough everything on the network slows down, [PowerPriority::Low] buildings
+//! first. Buildings switched off with [BuildingState::Inactive] count as disconnected.
 
 use std::ops::ControlFlow;
 
 use bevy::prelude::*;
 
 use crate::{
-    buildings::{core::TheCore, spawn_building, twr_custom_mats::TowerRadiusMaterial, Building},
+    buildings::{
+        battery::{Battery, BATTERY_CAPACITY},
+        core::TheCore,
+        generator::{Generator, GENERATOR_FUEL},
+        spawn_building,
+        synergy::Synergy,
+        twr_custom_mats::TowerRadiusMaterial,
+        Building, BuildingState,
+    },
     creeps::{CreepDie, SpawnCreep},
+    game::{
+        map::MapData,
+        research::Research,
+        resources::{Inventory, ResourceType},
+    },
+    global_systems::ui_util::GameFont,
     AppState,
 };
 
+/// Every *this* many seconds the grid balances supply and demand
+const GRID_TICK_RATE: f32 = 1.0;
+/// Where the charge label sits relative to its building
+const LABEL_OFFSET: Vec3 = Vec3::new(0.0, 44.0, 1.0);
+
 /// Marker component for buildings that require Power
 #[derive(Component)]
 pub struct RequiresPower;
@@ -23,12 +48,101 @@ impl Default for SupplyRadius {
     }
 }
 
+/// The [SupplyRadius] a building was built with, before any research
+#[derive(Component)]
+pub struct BaseSupplyRadius(pub f32);
+
 #[derive(Event)]
 pub struct AddBuilding;
 
+/// Fired when a building is destroyed or otherwise removed from the world
+#[derive(Event)]
+pub struct RemoveBuilding;
+
 #[derive(Component)]
 pub struct IsPowered;
 
+/// The building a [RequiresPower] building is getting its power from
+#[derive(Component)]
+pub struct PoweredBy(pub Entity);
+
+/// Power a building uses every second while it's connected
+#[derive(Component)]
+pub struct PowerDraw(pub f32);
+
+/// Power a building makes every second, for a [Generator] only while it's burning
+#[derive(Component)]
+pub struct PowerOutput(pub f32);
+
+/// Who gets served first when there isn't enough power to go around. Buildings without one
+/// are [PowerPriority::Normal].
+#[derive(Component, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum PowerPriority {
+    High,
+    #[default]
+    Normal,
+    Low,
+}
+
+impl PowerPriority {
+    pub fn next(&self) -> Self {
+        match self {
+            PowerPriority::High => PowerPriority::Normal,
+            PowerPriority::Normal => PowerPriority::Low,
+            PowerPriority::Low => PowerPriority::High,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            PowerPriority::High => "High",
+            PowerPriority::Normal => "Normal",
+            PowerPriority::Low => "Low",
+        }
+    }
+
+    /// Where it's served, highest first
+    fn index(&self) -> usize {
+        *self as usize
+    }
+}
+
+/// Power made and used across the whole network last tick
+#[derive(Resource)]
+pub struct PowerGrid {
+    pub production: f32,
+    pub demand: f32,
+    pub stored: f32,
+    pub capacity: f32,
+    /// Fraction of the demand that was met, powered buildings work this much of the time
+    pub satisfaction: f32,
+    /// [PowerGrid::satisfaction] for each [PowerPriority], highest first
+    priority_satisfaction: [f32; 3],
+}
+
+impl Default for PowerGrid {
+    fn default() -> Self {
+        Self {
+            production: 0.0,
+            demand: 0.0,
+            stored: 0.0,
+            capacity: 0.0,
+            satisfaction: 1.0,
+            priority_satisfaction: [1.0; 3],
+        }
+    }
+}
+
+impl PowerGrid {
+    /// How much of the time a building with `priority` gets to work
+    pub fn satisfaction_of(&self, priority: Option<&PowerPriority>) -> f32 {
+        self.priority_satisfaction[priority.copied().unwrap_or_default().index()]
+    }
+}
+
+#[derive(Resource)]
+struct GridTick(Timer);
+
 #[derive(Resource, PartialEq)]
 pub struct PowerDebug(pub bool);
 
@@ -36,11 +150,34 @@ pub struct PowerPlugin;
 impl Plugin for PowerPlugin {
     fn build(&self, app: &mut App) {
         app.insert_resource(PowerDebug(true));
+        app.insert_resource(GridTick(Timer::from_seconds(
+            GRID_TICK_RATE,
+            TimerMode::Repeating,
+        )));
+        app.init_resource::<PowerGrid>();
         app.add_event::<AddBuilding>();
-        app.add_systems(OnEnter(AppState::Gameplay), create_core);
+        app.add_event::<RemoveBuilding>();
+        app.add_systems(
+            OnEnter(AppState::Gameplay),
+            (create_core, reset_grid, setup_ui),
+        );
+        app.add_systems(OnExit(AppState::Gameplay), teardown_ui);
+        app.add_systems(
+            Update,
+            (
+                balance_grid,
+                add_charge_labels,
+                update_charge_labels,
+                update_ui,
+            )
+                .chain()
+                .run_if(in_state(AppState::Gameplay)),
+        );
         app.add_systems(
             Update,
-            (update_powered_unpowered).run_if(in_state(AppState::Gameplay)),
+            (apply_supply_research, update_powered_unpowered)
+                .chain()
+                .run_if(in_state(AppState::Gameplay)),
         )
         .add_systems(
             PostUpdate,
@@ -56,35 +193,87 @@ impl Plugin for PowerPlugin {
 /// Spawn the [buildings::TheCore] building
 fn create_core(
     mut commands: Commands,
-    texture_atlases: ResMut<Assets<TextureAtlas>>,
-    meshes: ResMut<Assets<Mesh>>,
-    materials: ResMut<Assets<TowerRadiusMaterial>>,
+    mut texture_atlases: ResMut<Assets<TextureAtlas>>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<TowerRadiusMaterial>>,
     asset_server: Res<AssetServer>,
+    map: Res<MapData>,
 ) {
     TheCore::custom_spawn(
         &mut commands,
-        texture_atlases,
-        meshes,
-        materials,
+        &mut texture_atlases,
+        &mut meshes,
+        &mut materials,
         asset_server,
-        Vec2::ZERO,
+        map.core_world_pos(),
     );
 }
 
+/// Grows every [SupplyRadius] (and the circle showing it) by what's been researched so far
+fn apply_supply_research(
+    mut commands: Commands,
+    research: Res<Research>,
+    mut q_suppliers: Query<(
+        Entity,
+        &mut SupplyRadius,
+        Option<&BaseSupplyRadius>,
+        Option<&Children>,
+    )>,
+    mut q_displays: Query<&mut Transform, With<Handle<TowerRadiusMaterial>>>,
+) {
+    q_suppliers
+        .iter_mut()
+        .for_each(|(ent, mut radius, base, children)| {
+            let Some(base) = base else {
+                commands.entity(ent).insert(BaseSupplyRadius(radius.0));
+                return;
+            };
+            let researched = base.0 * research.supply_radius_multiplier;
+            if radius.0 == researched {
+                return;
+            }
+
+            radius.0 = researched;
+            let mut displays = q_displays.iter_many_mut(children.iter().flat_map(|c| c.iter()));
+            while let Some(mut tf) = displays.fetch_next() {
+                tf.scale = Vec3::new(
+                    research.supply_radius_multiplier,
+                    research.supply_radius_multiplier,
+                    1.0,
+                );
+            }
+        });
+}
+
 pub fn update_powered_unpowered(
     mut commands: Commands,
     tower_spawned: EventReader<AddBuilding>,
+    tower_removed: EventReader<RemoveBuilding>,
     creep_spawned: EventReader<SpawnCreep>,
     creep_died: EventReader<CreepDie>,
+    radius_changed: Query<(), Changed<SupplyRadius>>,
+    state_changed: Query<(), Changed<BuildingState>>,
     building_query: Query<(Entity, &Transform), With<RequiresPower>>,
+    state_query: Query<&BuildingState>,
     supply_query: Query<(Entity, &SupplyRadius, &Transform), With<IsPowered>>,
 ) {
-    if !(creep_spawned.is_empty() && tower_spawned.is_empty() && creep_died.is_empty()) {
+    if !(creep_spawned.is_empty()
+        && tower_spawned.is_empty()
+        && tower_removed.is_empty()
+        && creep_died.is_empty()
+        && radius_changed.is_empty()
+        && state_changed.is_empty())
+    {
         // TODO: IF PERFORMANCE DIE, QUADTREE GO HERE.
 
         // for every building check that its powered by at least one building
         // O(n^2) ish (Power supplying buildings * power drawing buildings)
         building_query.iter().for_each(|(entity, drawer_tf)| {
+            // switched off buildings neither take nor pass on power
+            if matches!(state_query.get(entity), Ok(BuildingState::Inactive)) {
+                commands.entity(entity).remove::<(IsPowered, PoweredBy)>();
+                return;
+            }
             let is_powered = supply_query.iter().try_fold(
                 None,
                 |_acc: Option<f32>, (supply_entity, radius, supply_tf)| {
@@ -106,14 +295,16 @@ pub fn update_powered_unpowered(
                     if let Some(supply_ent) = is_powered {
                         // Only buildings other than this building itself can power it
                         if supply_ent != entity {
-                            commands.entity(entity).insert(IsPowered);
+                            commands
+                                .entity(entity)
+                                .insert((IsPowered, PoweredBy(supply_ent)));
                         } else {
-                            commands.entity(entity).remove::<IsPowered>();
+                            commands.entity(entity).remove::<(IsPowered, PoweredBy)>();
                         }
                     }
                 }
                 ControlFlow::Continue(_) => {
-                    commands.entity(entity).remove::<IsPowered>();
+                    commands.entity(entity).remove::<(IsPowered, PoweredBy)>();
                 }
             }
         });
@@ -123,17 +314,28 @@ pub fn update_powered_unpowered(
 pub fn update_powered_unpowered2(
     mut commands: Commands,
     tower_spawned: EventReader<AddBuilding>,
+    tower_removed: EventReader<RemoveBuilding>,
     creep_spawned: EventReader<SpawnCreep>,
     creep_died: EventReader<CreepDie>,
     building_query: Query<(Entity, &Transform), With<RequiresPower>>,
+    state_query: Query<&BuildingState>,
     supply_query: Query<(Entity, &SupplyRadius, &Transform), With<IsPowered>>,
 ) {
-    if !(creep_spawned.is_empty() && tower_spawned.is_empty() && creep_died.is_empty()) {
+    if !(creep_spawned.is_empty()
+        && tower_spawned.is_empty()
+        && tower_removed.is_empty()
+        && creep_died.is_empty())
+    {
         // TODO: IF PERFORMANCE DIE, QUADTREE GO HERE.
 
         // for every building check that its powered by at least one building
         // O(n^2) ish (Power supplying buildings * power drawing buildings)
         building_query.iter().for_each(|(entity, drawer_tf)| {
+            // switched off buildings neither take nor pass on power
+            if matches!(state_query.get(entity), Ok(BuildingState::Inactive)) {
+                commands.entity(entity).remove::<(IsPowered, PoweredBy)>();
+                return;
+            }
             let is_powered = supply_query.iter().try_fold(
                 None,
                 |_acc: Option<f32>, (supply_entity, radius, supply_tf)| {
@@ -155,14 +357,16 @@ pub fn update_powered_unpowered2(
                     if let Some(supply_ent) = is_powered {
                         // Only buildings other than this building itself can power it
                         if supply_ent != entity {
-                            commands.entity(entity).insert(IsPowered);
+                            commands
+                                .entity(entity)
+                                .insert((IsPowered, PoweredBy(supply_ent)));
                         } else {
-                            commands.entity(entity).remove::<IsPowered>();
+                            commands.entity(entity).remove::<(IsPowered, PoweredBy)>();
                         }
                     }
                 }
                 ControlFlow::Continue(_) => {
-                    commands.entity(entity).remove::<IsPowered>();
+                    commands.entity(entity).remove::<(IsPowered, PoweredBy)>();
                 }
             }
         });
@@ -190,3 +394,215 @@ fn debug_power_map_ui(
             sprite.color = Color::rgba(0.5, 0.5, 0.5, 1.0);
         });
 }
+
+fn reset_grid(mut grid: ResMut<PowerGrid>) {
+    *grid = PowerGrid::default();
+}
+
+/// Works out supply and demand for the tick. Generators only burn fuel while the network
+/// needs more than it's making, including room left in the batteries.
+#[allow(clippy::too_many_arguments)]
+fn balance_grid(
+    mut timer: ResMut<GridTick>,
+    time: Res<Time>,
+    mut grid: ResMut<PowerGrid>,
+    mut inventory: ResMut<Inventory>,
+    q_output: Query<&PowerOutput, (With<IsPowered>, Without<Generator>)>,
+    q_draw: Query<(&PowerDraw, Option<&PowerPriority>), With<IsPowered>>,
+    mut q_generators: Query<(
+        &mut Generator,
+        &PowerOutput,
+        Option<&IsPowered>,
+        Option<&Synergy>,
+    )>,
+    mut q_batteries: Query<(&mut Battery, Option<&IsPowered>)>,
+) {
+    if !timer.0.tick(time.delta()).just_finished() {
+        return;
+    }
+
+    let mut priority_demand = [0.0; 3];
+    q_draw.iter().for_each(|(draw, priority)| {
+        priority_demand[priority.copied().unwrap_or_default().index()] += draw.0;
+    });
+    let demand: f32 = priority_demand.iter().sum();
+    let room: f32 = q_batteries
+        .iter()
+        .filter(|(_, connected)| connected.is_some())
+        .map(|(battery, _)| battery.room())
+        .sum();
+
+    let mut production: f32 = q_output.iter().map(|output| output.0).sum();
+    q_generators
+        .iter_mut()
+        .for_each(|(mut generator, output, connected, synergy)| {
+            generator.burning = connected.is_some()
+                && production < demand + room
+                && inventory.get(ResourceType::Biomass) >= GENERATOR_FUEL;
+            if generator.burning {
+                inventory.remove(ResourceType::Biomass, GENERATOR_FUEL);
+                production +=
+                    output.0 * (1.0 + synergy.map_or(0.0, |synergy| synergy.generator_output));
+            }
+        });
+
+    // surplus charges the batteries, a shortfall drains them
+    let mut balance = production - demand;
+    q_batteries
+        .iter_mut()
+        .filter(|(_, connected)| connected.is_some())
+        .for_each(|(mut battery, _)| {
+            let moved = balance.clamp(-battery.charge, battery.room());
+            battery.charge += moved;
+            balance -= moved;
+        });
+
+    let (stored, batteries) = q_batteries
+        .iter()
+        .filter(|(_, connected)| connected.is_some())
+        .fold((0.0, 0), |(stored, count), (battery, _)| {
+            (stored + battery.charge, count + 1)
+        });
+    // whatever did get supplied goes to the higher priorities first
+    let mut supplied = demand + balance.min(0.0);
+    let priority_satisfaction = priority_demand.map(|wanted| {
+        if wanted <= 0.0 {
+            return 1.0;
+        }
+        let got = supplied.clamp(0.0, wanted);
+        supplied -= got;
+        got / wanted
+    });
+    *grid = PowerGrid {
+        production,
+        demand,
+        stored,
+        capacity: batteries as f32 * BATTERY_CAPACITY,
+        satisfaction: if demand > 0.0 && balance < 0.0 {
+            (1.0 + balance / demand).max(0.0)
+        } else {
+            1.0
+        },
+        priority_satisfaction,
+    };
+}
+
+/// World space text above batteries and generators showing how they're doing
+#[derive(Component)]
+struct ChargeLabel;
+
+fn add_charge_labels(
+    mut commands: Commands,
+    font: Res<GameFont>,
+    q_new: Query<Entity, Or<(Added<Battery>, Added<Generator>)>>,
+) {
+    q_new.iter().for_each(|ent| {
+        let label = commands
+            .spawn((
+                Text2dBundle {
+                    text: Text::from_section(
+                        "",
+                        TextStyle {
+                            font: font.0.clone(),
+                            font_size: 18.0,
+                            color: Color::WHITE,
+                        },
+                    ),
+                    transform: Transform::from_translation(LABEL_OFFSET),
+                    ..default()
+                },
+                ChargeLabel,
+            ))
+            .id();
+        commands.entity(ent).add_child(label);
+    });
+}
+
+fn update_charge_labels(
+    q_batteries: Query<(&Battery, &Children)>,
+    q_generators: Query<(&Generator, &Children)>,
+    mut q_labels: Query<&mut Text, With<ChargeLabel>>,
+) {
+    q_batteries.iter().for_each(|(battery, children)| {
+        let mut labels = q_labels.iter_many_mut(children);
+        while let Some(mut text) = labels.fetch_next() {
+            let fill = battery.charge / BATTERY_CAPACITY;
+            text.sections[0].value = format!("{:.0}%", fill * 100.0);
+            text.sections[0].style.color = Color::rgb(1.0 - fill, fill, 0.1);
+        }
+    });
+    q_generators.iter().for_each(|(generator, children)| {
+        let mut labels = q_labels.iter_many_mut(children);
+        while let Some(mut text) = labels.fetch_next() {
+            let (label, color) = if generator.burning {
+                ("burning", Color::ORANGE)
+            } else {
+                ("idle", Color::GRAY)
+            };
+            text.sections[0].value = label.to_string();
+            text.sections[0].style.color = color;
+        }
+    });
+}
+
+#[derive(Component)]
+struct PowerUI;
+
+#[derive(Component)]
+struct PowerText;
+
+fn setup_ui(mut commands: Commands, font: Res<GameFont>) {
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    top: Val::Px(120.0),
+                    right: Val::Px(15.0),
+                    ..default()
+                },
+                z_index: ZIndex::Global(i32::MAX - 1),
+                ..default()
+            },
+            PowerUI,
+        ))
+        .with_children(|parent| {
+            parent.spawn((
+                TextBundle::from_section(
+                    "",
+                    TextStyle {
+                        font_size: 28.0,
+                        font: font.0.clone(),
+                        color: Color::WHITE,
+                    },
+                )
+                .with_text_alignment(TextAlignment::Right),
+                PowerText,
+            ));
+        });
+}
+
+fn update_ui(grid: Res<PowerGrid>, mut q_text: Query<&mut Text, With<PowerText>>) {
+    let mut summary = format!("Power: {:.0}/{:.0}", grid.production, grid.demand);
+    if grid.capacity > 0.0 {
+        summary += &format!("\nStored: {:.0}/{:.0}", grid.stored, grid.capacity);
+    }
+    if grid.satisfaction < 1.0 {
+        summary += &format!("\nBROWNOUT: {:.0}%", grid.satisfaction * 100.0);
+    }
+
+    for mut text in &mut q_text {
+        text.sections[0].value = summary.clone();
+        text.sections[0].style.color = if grid.satisfaction < 1.0 {
+            Color::ORANGE_RED
+        } else {
+            Color::WHITE
+        };
+    }
+}
+
+fn teardown_ui(mut commands: Commands, nodes: Query<Entity, With<PowerUI>>) {
+    for ent in &nodes {
+        commands.entity(ent).despawn_recursive();
+    }
+}
@@ -0,0 +1,366 @@
+//! Research. The core works on one project from `assets/data/research.ron` at a time, each
+//! one unlocking a building, making something better, or granting a new [Ability].
+//!
+//! The research panel is toggled with [FloraCommand::ToggleResearch].
+
+use std::collections::HashSet;
+
+use bevy::prelude::*;
+use serde::Deserialize;
+
+use crate::{
+    buildings::{core::TheCore, BuildingType},
+    global_systems::ui_util::GameFont,
+    AppState,
+};
+
+use super::{
+    keybinds::FloraCommand,
+    resources::{ExpendResource, Inventory, ResourceType},
+};
+
+pub struct ResearchPlugin;
+impl Plugin for ResearchPlugin {
+    fn build(&self, app: &mut App) {
+        let tree: TechTree = ron::from_str(include_str!("../../assets/data/research.ron"))
+            .expect("Failed to parse research.ron!");
+
+        app.insert_resource(tree)
+            .init_resource::<Research>()
+            .add_systems(OnEnter(AppState::Gameplay), (reset_research, setup_ui))
+            .add_systems(OnExit(AppState::Gameplay), teardown_ui)
+            .add_systems(
+                Update,
+                (toggle_panel, start_research, progress_research, update_ui)
+                    .chain()
+                    .run_if(in_state(AppState::Gameplay)),
+            );
+    }
+}
+
+/// Things the player can do once they've been researched, rather than buildings or numbers
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Deserialize)]
+pub enum Ability {
+    /// Buying robot upgrades, see [crate::buildings::roboport::RobotUpgrades]
+    RobotUpgrades,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub enum TechEffect {
+    UnlockBuilding(BuildingType),
+    /// Multiplies drain tower damage
+    DrainEfficiency(f32),
+    /// Multiplies every [SupplyRadius](super::power::SupplyRadius)
+    SupplyRadius(f32),
+    Ability(Ability),
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct TechDef {
+    pub id: String,
+    pub name: String,
+    pub description: String,
+    pub cost: Vec<(ResourceType, u32)>,
+    /// Seconds
+    pub time: f32,
+    /// ids of the techs that have to be researched first
+    pub requires: Vec<String>,
+    pub effect: TechEffect,
+}
+
+/// Everything `assets/data/research.ron` defines
+#[derive(Resource, Clone, Debug, Deserialize)]
+pub struct TechTree {
+    pub techs: Vec<TechDef>,
+}
+
+impl TechTree {
+    fn get(&self, id: &str) -> Option<&TechDef> {
+        self.techs.iter().find(|tech| tech.id == id)
+    }
+}
+
+/// What's been researched so far, and what all of it adds up to
+#[derive(Resource)]
+pub struct Research {
+    pub completed: HashSet<String>,
+    /// The tech being researched and how long it has left
+    pub current: Option<(String, Timer)>,
+    unlocked_buildings: HashSet<BuildingType>,
+    abilities: HashSet<Ability>,
+    pub drain_multiplier: f32,
+    pub supply_radius_multiplier: f32,
+}
+
+impl Default for Research {
+    fn default() -> Self {
+        Self {
+            completed: HashSet::new(),
+            current: None,
+            unlocked_buildings: HashSet::from([
+                BuildingType::Distribution,
+                BuildingType::Wall,
+                BuildingType::Firebreak,
+            ]),
+            abilities: HashSet::new(),
+            drain_multiplier: 1.0,
+            supply_radius_multiplier: 1.0,
+        }
+    }
+}
+
+impl Research {
+    pub fn is_unlocked(&self, building: &BuildingType) -> bool {
+        self.unlocked_buildings.contains(building)
+    }
+
+    pub fn has(&self, ability: Ability) -> bool {
+        self.abilities.contains(&ability)
+    }
+
+    /// Not researched yet, but everything it requires is
+    pub fn is_available(&self, tech: &TechDef) -> bool {
+        !self.completed.contains(&tech.id)
+            && tech.requires.iter().all(|id| self.completed.contains(id))
+    }
+
+    fn complete(&mut self, tech: &TechDef) {
+        match &tech.effect {
+            TechEffect::UnlockBuilding(building) => {
+                self.unlocked_buildings.insert(building.clone());
+            }
+            TechEffect::DrainEfficiency(mult) => self.drain_multiplier *= mult,
+            TechEffect::SupplyRadius(mult) => self.supply_radius_multiplier *= mult,
+            TechEffect::Ability(ability) => {
+                self.abilities.insert(*ability);
+            }
+        }
+        self.completed.insert(tech.id.clone());
+    }
+}
+
+fn reset_research(mut research: ResMut<Research>) {
+    *research = Research::default();
+}
+
+/// Counts down the current project, only while there's a core to do the research
+fn progress_research(
+    mut research: ResMut<Research>,
+    tree: Res<TechTree>,
+    time: Res<Time>,
+    q_core: Query<(), With<TheCore>>,
+) {
+    if q_core.is_empty() {
+        return;
+    }
+    let Some((id, timer)) = &mut research.current else {
+        return;
+    };
+    if !timer.tick(time.delta()).finished() {
+        return;
+    }
+
+    let id = id.clone();
+    research.current = None;
+    if let Some(tech) = tree.get(&id) {
+        info!("Research complete: {}", tech.name);
+        research.complete(tech);
+    }
+}
+
+#[derive(Component)]
+struct ResearchUI;
+
+/// The list of techs that gets toggled
+#[derive(Component)]
+struct ResearchPanel;
+
+#[derive(Component)]
+struct ResearchStatusText;
+
+/// Clicking it starts researching the tech with this id
+#[derive(Component)]
+struct TechButton(String);
+
+fn setup_ui(mut commands: Commands, font: Res<GameFont>, tree: Res<TechTree>) {
+    let text_style = |size: f32| TextStyle {
+        font_size: size,
+        font: font.0.clone(),
+        color: Color::WHITE,
+    };
+
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    top: Val::Px(160.0),
+                    left: Val::Px(15.0),
+                    flex_direction: FlexDirection::Column,
+                    ..default()
+                },
+                z_index: ZIndex::Global(i32::MAX - 1),
+                ..default()
+            },
+            ResearchUI,
+        ))
+        .with_children(|parent| {
+            parent.spawn((
+                TextBundle::from_section("", text_style(24.0)),
+                ResearchStatusText,
+            ));
+            parent
+                .spawn((
+                    NodeBundle {
+                        style: Style {
+                            display: Display::None,
+                            flex_direction: FlexDirection::Column,
+                            ..default()
+                        },
+                        background_color: Color::rgba(0.0, 0.0, 0.0, 0.7).into(),
+                        ..default()
+                    },
+                    ResearchPanel,
+                ))
+                .with_children(|parent| {
+                    tree.techs.iter().for_each(|tech| {
+                        parent
+                            .spawn((
+                                ButtonBundle {
+                                    style: Style {
+                                        padding: UiRect::all(Val::Px(6.0)),
+                                        ..default()
+                                    },
+                                    background_color: Color::NONE.into(),
+                                    ..default()
+                                },
+                                TechButton(tech.id.clone()),
+                            ))
+                            .with_children(|parent| {
+                                parent.spawn(TextBundle::from_section("", text_style(20.0)));
+                            });
+                    });
+                });
+        });
+}
+
+fn toggle_panel(
+    input: Res<Input<FloraCommand>>,
+    mut q_panel: Query<&mut Style, With<ResearchPanel>>,
+) {
+    if !input.just_pressed(FloraCommand::ToggleResearch) {
+        return;
+    }
+
+    for mut style in &mut q_panel {
+        style.display = match style.display {
+            Display::None => Display::Flex,
+            _ => Display::None,
+        };
+    }
+}
+
+fn start_research(
+    mut research: ResMut<Research>,
+    tree: Res<TechTree>,
+    inventory: Res<Inventory>,
+    mut expend_resource: EventWriter<ExpendResource>,
+    q_buttons: Query<(&Interaction, &TechButton), Changed<Interaction>>,
+) {
+    for (interaction, button) in &q_buttons {
+        if *interaction != Interaction::Pressed || research.current.is_some() {
+            continue;
+        }
+        let Some(tech) = tree.get(&button.0) else {
+            continue;
+        };
+        if !research.is_available(tech) || !inventory.can_afford(&tech.cost) {
+            continue;
+        }
+
+        tech.cost.iter().for_each(|(resource, amount)| {
+            expend_resource.send(ExpendResource(*resource, *amount));
+        });
+        info!("Researching {}", tech.name);
+        research.current = Some((
+            tech.id.clone(),
+            Timer::from_seconds(tech.time, TimerMode::Once),
+        ));
+    }
+}
+
+pub(super) fn format_cost(cost: &[(ResourceType, u32)]) -> String {
+    cost.iter()
+        .map(|(resource, amount)| format!("{} {}", amount, resource.name()))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+fn update_ui(
+    research: Res<Research>,
+    tree: Res<TechTree>,
+    inventory: Res<Inventory>,
+    mut q_status: Query<&mut Text, With<ResearchStatusText>>,
+    q_buttons: Query<(&TechButton, &Children)>,
+    mut q_text: Query<&mut Text, Without<ResearchStatusText>>,
+) {
+    let status = match &research.current {
+        Some((id, timer)) => format!(
+            "[R] Researching {}: {:.0}s",
+            tree.get(id).map_or(id.as_str(), |tech| tech.name.as_str()),
+            timer.remaining_secs()
+        ),
+        None => "[R] Research".to_string(),
+    };
+    for mut text in &mut q_status {
+        text.sections[0].value = status.clone();
+    }
+
+    q_buttons.iter().for_each(|(button, children)| {
+        let Some(tech) = tree.get(&button.0) else {
+            return;
+        };
+        let (label, color) = if research.completed.contains(&tech.id) {
+            (format!("{} - done", tech.name), Color::GREEN)
+        } else if !research.is_available(tech) {
+            let missing: Vec<_> = tech
+                .requires
+                .iter()
+                .filter(|id| !research.completed.contains(*id))
+                .map(|id| tree.get(id).map_or(id.as_str(), |tech| tech.name.as_str()))
+                .collect();
+            (
+                format!("{} - needs {}", tech.name, missing.join(", ")),
+                Color::GRAY,
+            )
+        } else {
+            let color = if inventory.can_afford(&tech.cost) {
+                Color::WHITE
+            } else {
+                Color::ORANGE_RED
+            };
+            (
+                format!(
+                    "{} - {} ({:.0}s)\n  {}",
+                    tech.name,
+                    format_cost(&tech.cost),
+                    tech.time,
+                    tech.description
+                ),
+                color,
+            )
+        };
+
+        let mut texts = q_text.iter_many_mut(children);
+        while let Some(mut text) = texts.fetch_next() {
+            text.sections[0].value = label.clone();
+            text.sections[0].style.color = color;
+        }
+    });
+}
+
+fn teardown_ui(mut commands: Commands, nodes: Query<Entity, With<ResearchUI>>) {
+    for ent in &nodes {
+        commands.entity(ent).despawn_recursive();
+    }
+}
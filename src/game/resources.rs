@@ -1,6 +1,7 @@
-use std::fmt::format;
+use std::{collections::HashMap, fmt::format};
 
 use bevy::prelude::*;
+use serde::Deserialize;
 
 use crate::{
     global_systems::{
@@ -10,9 +11,14 @@ use crate::{
     AppState,
 };
 
-use super::hud::PIXEL;
+use super::{
+    depletion::{Bankruptcy, Debt, EconomyForecast},
+    hud::PIXEL,
+};
 
 const DEFAULT_PURSE_SIZE: u32 = 1000;
+/// Wood the company sends along with the core, enough for the first few pylons
+const DEFAULT_WOOD: u32 = 60;
 
 pub struct ResourcePlugin;
 impl Plugin for ResourcePlugin {
@@ -29,35 +35,92 @@ impl Plugin for ResourcePlugin {
     }
 }
 
-/// What the player currently has in the BANK
+/// What the player currently has in the BANK, and in the warehouse
 #[derive(Resource, Clone)]
-pub struct Inventory {
-    pub money: u32,
-}
+pub struct Inventory(HashMap<ResourceType, u32>);
 impl Default for Inventory {
     fn default() -> Self {
-        Self {
-            money: DEFAULT_PURSE_SIZE,
-        }
+        Self(HashMap::from([
+            (ResourceType::CorporationPoints, DEFAULT_PURSE_SIZE),
+            (ResourceType::Wood, DEFAULT_WOOD),
+        ]))
+    }
+}
+
+impl Inventory {
+    pub fn get(&self, resource: ResourceType) -> u32 {
+        self.0.get(&resource).copied().unwrap_or(0)
+    }
+
+    /// Shorthand for how many [ResourceType::CorporationPoints] the player has
+    pub fn money(&self) -> u32 {
+        self.get(ResourceType::CorporationPoints)
+    }
+
+    pub fn add(&mut self, resource: ResourceType, amount: u32) {
+        *self.0.entry(resource).or_default() += amount;
+    }
+
+    /// Takes away as much as possible of `amount`, never going below 0
+    pub fn remove(&mut self, resource: ResourceType, amount: u32) {
+        let held = self.0.entry(resource).or_default();
+        *held = held.saturating_sub(amount);
+    }
+
+    pub fn can_afford(&self, cost: &[(ResourceType, u32)]) -> bool {
+        cost.iter()
+            .all(|(resource, amount)| self.get(*resource) >= *amount)
     }
 }
 
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Deserialize)]
 pub enum ResourceType {
     CorporationPoints,
+    /// Most trees are made of it
+    Wood,
+    /// Leaves, flowers and other squishy bits
+    Biomass,
+    /// Pulled up out of the ground by the roots
+    Minerals,
+    /// Only ever found on the odd tree, very valuable
+    RareSpores,
+}
+
+impl ResourceType {
+    pub const ALL: [ResourceType; 5] = [
+        ResourceType::CorporationPoints,
+        ResourceType::Wood,
+        ResourceType::Biomass,
+        ResourceType::Minerals,
+        ResourceType::RareSpores,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            ResourceType::CorporationPoints => "Corpo Points",
+            ResourceType::Wood => "Wood",
+            ResourceType::Biomass => "Biomass",
+            ResourceType::Minerals => "Minerals",
+            ResourceType::RareSpores => "Rare Spores",
+        }
+    }
 }
 
+/// What something costs, every resource listed has to be paid
+pub type Cost = &'static [(ResourceType, u32)];
+
 #[derive(Event)]
 pub struct ExpendResource(pub ResourceType, pub u32);
 
-/// This event should be fired when a resource was harvested
-/// (resource, money_earned)
+/// This event should be fired when a harvested resource makes it to the core
+/// (resource, amount_earned)
 #[derive(Event)]
 pub struct Harvest(pub ResourceType, pub u32);
 
 /// System:
 /// Changes the music of the game based on how much money you have.
 fn game_stage_music_choice(inventory: Res<Inventory>, mut audio_mngr: EventWriter<AudioRequest>) {
-    if inventory.money > 1500 {
+    if inventory.money() > 1500 {
         audio_mngr.send(AudioRequest {
             component: crate::global_systems::eargasm::AudioComponent::Track2(Track2),
         })
@@ -67,18 +130,14 @@ fn game_stage_music_choice(inventory: Res<Inventory>, mut audio_mngr: EventWrite
 /// System that adds all harvested resources to the players inventory
 fn add_harvest_to_inventory(mut inventory: ResMut<Inventory>, mut harvests: EventReader<Harvest>) {
     *inventory = harvests.read().fold(inventory.clone(), |mut inv, harvest| {
-        match harvest.0 {
-            ResourceType::CorporationPoints => inv.money += harvest.1,
-        };
+        inv.add(harvest.0, harvest.1);
         inv
     });
 }
 
 fn expend_resource(mut inventory: ResMut<Inventory>, mut expent: EventReader<ExpendResource>) {
-    *inventory = expent.read().fold(inventory.clone(), |mut inv, harvest| {
-        match harvest.0 {
-            ResourceType::CorporationPoints => inv.money = inv.money.saturating_sub(harvest.1),
-        };
+    *inventory = expent.read().fold(inventory.clone(), |mut inv, expense| {
+        inv.remove(expense.0, expense.1);
         inv
     });
 }
@@ -88,7 +147,7 @@ fn expend_resource(mut inventory: ResMut<Inventory>, mut expent: EventReader<Exp
 struct InventoryDebugUI;
 
 #[derive(Component)]
-struct WoodNumber;
+struct InventoryText;
 
 /// Ugly UI for temporarily showing inventory. Will be beautified later!
 fn setup_debug_ui(mut commands: Commands, font: Res<GameFont>) {
@@ -110,7 +169,7 @@ fn setup_debug_ui(mut commands: Commands, font: Res<GameFont>) {
         .with_children(|parent| {
             parent.spawn((
                 TextBundle::from_section(
-                    "Wood: 0",
+                    "",
                     TextStyle {
                         font_size: 42.0,
                         font: font.0.clone(),
@@ -122,19 +181,45 @@ fn setup_debug_ui(mut commands: Commands, font: Res<GameFont>) {
                     margin: UiRect::all(Val::Px(15.)),
                     ..default()
                 }),
-                WoodNumber,
+                InventoryText,
             ));
         });
 }
 
-fn update_debug_ui(mut q_text: Query<&mut Text, With<WoodNumber>>, inventory: Res<Inventory>) {
+fn update_debug_ui(
+    mut q_text: Query<&mut Text, With<InventoryText>>,
+    inventory: Res<Inventory>,
+    forecast: Res<EconomyForecast>,
+    debt: Res<Debt>,
+    bankruptcy: Res<Bankruptcy>,
+) {
+    let mut lines = format!("+{:.1}/s -{:.1}/s\n", forecast.income, forecast.expenses);
+    if let Some(timer) = &bankruptcy.0 {
+        lines += &format!("BANKRUPT, {:.0}s to recover\n", timer.remaining_secs());
+    } else if let Some(secs) = forecast.secs_until_bankrupt {
+        lines += &format!("Bankrupt in {:.0}s\n", secs);
+    }
+    if debt.owed > 0.0 {
+        lines += &format!("Debt: {:.0} (-{:.1}/s)\n", debt.owed, debt.installment);
+    }
+
+    // only list what the player has actually got their hands on
+    lines += &ResourceType::ALL
+        .iter()
+        .filter(|resource| {
+            **resource == ResourceType::CorporationPoints || inventory.get(**resource) > 0
+        })
+        .map(|resource| format!("{}: {}", resource.name(), inventory.get(*resource)))
+        .collect::<Vec<_>>()
+        .join("\n");
+
     for mut text in &mut q_text {
-        text.sections[0].value = format!("Corpo Points: {}", inventory.money);
+        text.sections[0].value = lines.clone();
     }
 }
 
 fn reset_money(mut inv: ResMut<Inventory>) {
-    inv.money = DEFAULT_PURSE_SIZE;
+    *inv = Inventory::default();
 }
 
 fn teardown_debug_ui(mut commands: Commands, nodes: Query<Entity, With<InventoryDebugUI>>) {
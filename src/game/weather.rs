@@ -0,0 +1,347 @@
+//! Weather. A small state machine that forecasts an event, lets it play out, and then stays
+//! calm for a while before forecasting the next one.
+//!
+//! The events themselves are defined in `assets/data/weather.ron` and are rolled from the
+//! [GameSeed], so the same seed always gets the same forecast.
+
+use bevy::prelude::*;
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use serde::Deserialize;
+
+use crate::{
+    buildings::{core::TheCore, Building},
+    creeps::lifecycle::{spawn_seed, Sapling},
+    global_systems::ui_util::GameFont,
+    AppState, GameSeed, Health, Tree,
+};
+
+use super::clock::SeasonModifiers;
+
+/// Every *this* many seconds the active event does its thing
+const EFFECT_TICK_RATE: f32 = 1.0;
+/// Sapling damage per tick during a drought, at full intensity
+const DROUGHT_DPT: f32 = 8.0;
+/// Building damage per tick during acid rain, at full intensity. The core is built to take it.
+const ACID_RAIN_DPT: f32 = 3.0;
+/// Chance per tick for a mature tree to have its seeds ripped off during a storm, at full intensity
+const STORM_SCATTER_CHANCE: f64 = 0.0005;
+/// Wind strength when it's calm, in world units per second
+const BASE_WIND: f32 = 10.0;
+/// Extra wind strength during a storm, at full intensity
+const STORM_WIND: f32 = 120.0;
+/// How fast the wind direction wanders, radians per second
+const WIND_DRIFT: f32 = 0.05;
+
+pub struct WeatherPlugin;
+impl Plugin for WeatherPlugin {
+    fn build(&self, app: &mut App) {
+        let table: WeatherTable = ron::from_str(include_str!("../../assets/data/weather.ron"))
+            .expect("Failed to parse weather.ron!");
+
+        app.insert_resource(table)
+            .insert_resource(WeatherEffectTick(Timer::from_seconds(
+                EFFECT_TICK_RATE,
+                TimerMode::Repeating,
+            )))
+            .init_resource::<Wind>()
+            .add_systems(OnEnter(AppState::Gameplay), (reset_weather, setup_ui))
+            .add_systems(OnExit(AppState::Gameplay), teardown_ui)
+            .add_systems(
+                Update,
+                (advance_weather, blow_wind, weather_effects, update_ui)
+                    .chain()
+                    .run_if(in_state(AppState::Gameplay))
+                    .run_if(resource_exists::<Weather>()),
+            );
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize)]
+pub enum WeatherKind {
+    /// Spikes the wind and rips seeds off of trees
+    Storm,
+    /// Slows growth and kills off saplings
+    Drought,
+    /// Corrodes buildings
+    AcidRain,
+    /// Everything gets pollinated
+    PollenBlizzard,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+struct WeatherEventDef {
+    kind: WeatherKind,
+    name: String,
+    /// Relative chance of being picked
+    weight: u32,
+    duration: (f32, f32),
+    intensity: (f32, f32),
+}
+
+/// Everything `assets/data/weather.ron` defines
+#[derive(Resource, Clone, Debug, Deserialize)]
+struct WeatherTable {
+    calm: (f32, f32),
+    warning: f32,
+    events: Vec<WeatherEventDef>,
+}
+
+impl WeatherTable {
+    fn roll(&self, rng: &mut StdRng) -> WeatherEvent {
+        let total: u32 = self.events.iter().map(|def| def.weight).sum();
+        let mut pick = rng.gen_range(0..total.max(1));
+        let def = self
+            .events
+            .iter()
+            .find(|def| {
+                if pick < def.weight {
+                    true
+                } else {
+                    pick -= def.weight;
+                    false
+                }
+            })
+            .unwrap_or(&self.events[0]);
+
+        WeatherEvent {
+            kind: def.kind,
+            name: def.name.clone(),
+            duration: rng.gen_range(def.duration.0..=def.duration.1),
+            intensity: rng.gen_range(def.intensity.0..=def.intensity.1),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct WeatherEvent {
+    pub kind: WeatherKind,
+    pub name: String,
+    /// Seconds
+    pub duration: f32,
+    /// 0.0 - 1.0
+    pub intensity: f32,
+}
+
+#[derive(Clone, Debug)]
+pub enum WeatherPhase {
+    Calm(Timer),
+    /// The next event has been announced and hits when the timer finishes
+    Forecast(WeatherEvent, Timer),
+    Active(WeatherEvent, Timer),
+}
+
+#[derive(Resource)]
+pub struct Weather {
+    pub phase: WeatherPhase,
+    rng: StdRng,
+}
+
+impl Weather {
+    pub fn active(&self) -> Option<&WeatherEvent> {
+        match &self.phase {
+            WeatherPhase::Active(event, _) => Some(event),
+            _ => None,
+        }
+    }
+
+    fn active_intensity(&self, kind: WeatherKind) -> f32 {
+        self.active()
+            .filter(|event| event.kind == kind)
+            .map_or(0.0, |event| event.intensity)
+    }
+
+    /// Applies the current weather on top of the season's effect on the plants
+    pub fn modify(&self, mut modifiers: SeasonModifiers) -> SeasonModifiers {
+        modifiers.growth *= 1.0 - self.active_intensity(WeatherKind::Drought);
+        modifiers.pollination *= 1.0 + 4.0 * self.active_intensity(WeatherKind::PollenBlizzard);
+        modifiers
+    }
+}
+
+/// Which way seeds and pollen get blown
+#[derive(Resource)]
+pub struct Wind {
+    /// Radians
+    pub angle: f32,
+    /// World units per second
+    pub strength: f32,
+}
+
+impl Default for Wind {
+    fn default() -> Self {
+        Self {
+            angle: 0.0,
+            strength: BASE_WIND,
+        }
+    }
+}
+
+impl Wind {
+    pub fn velocity(&self) -> Vec2 {
+        Vec2::from_angle(self.angle) * self.strength
+    }
+}
+
+#[derive(Resource)]
+struct WeatherEffectTick(Timer);
+
+fn reset_weather(
+    mut commands: Commands,
+    seed: Res<GameSeed>,
+    table: Res<WeatherTable>,
+    mut wind: ResMut<Wind>,
+) {
+    let mut rng = StdRng::seed_from_u64(seed.0);
+    let calm = rng.gen_range(table.calm.0..=table.calm.1);
+    *wind = Wind {
+        angle: rng.gen_range(0.0..std::f32::consts::TAU),
+        ..default()
+    };
+
+    commands.insert_resource(Weather {
+        phase: WeatherPhase::Calm(Timer::from_seconds(calm, TimerMode::Once)),
+        rng,
+    });
+}
+
+/// Moves the weather through calm -> forecast -> active -> calm
+fn advance_weather(mut weather: ResMut<Weather>, table: Res<WeatherTable>, time: Res<Time>) {
+    let weather = &mut *weather;
+    let next = match &mut weather.phase {
+        WeatherPhase::Calm(timer) => timer.tick(time.delta()).finished().then(|| {
+            let event = table.roll(&mut weather.rng);
+            info!("Forecast: {} in {}s", event.name, table.warning);
+            WeatherPhase::Forecast(event, Timer::from_seconds(table.warning, TimerMode::Once))
+        }),
+        WeatherPhase::Forecast(event, timer) => timer.tick(time.delta()).finished().then(|| {
+            info!("{} has started", event.name);
+            let duration = Timer::from_seconds(event.duration, TimerMode::Once);
+            WeatherPhase::Active(event.clone(), duration)
+        }),
+        WeatherPhase::Active(event, timer) => timer.tick(time.delta()).finished().then(|| {
+            info!("{} has passed", event.name);
+            let calm = weather.rng.gen_range(table.calm.0..=table.calm.1);
+            WeatherPhase::Calm(Timer::from_seconds(calm, TimerMode::Once))
+        }),
+    };
+
+    if let Some(next) = next {
+        weather.phase = next;
+    }
+}
+
+fn blow_wind(mut wind: ResMut<Wind>, weather: Res<Weather>, time: Res<Time>) {
+    let target = BASE_WIND + STORM_WIND * weather.active_intensity(WeatherKind::Storm);
+    // ease towards the target so storms build up and die down
+    wind.strength += (target - wind.strength) * time.delta_seconds().min(1.0);
+    wind.angle += WIND_DRIFT * time.delta_seconds();
+}
+
+/// Damage and seed scattering from the active event
+fn weather_effects(
+    mut commands: Commands,
+    mut timer: ResMut<WeatherEffectTick>,
+    time: Res<Time>,
+    weather: Res<Weather>,
+    mut q_saplings: Query<&mut Health, (With<Sapling>, Without<Building>)>,
+    mut q_buildings: Query<&mut Health, (With<Building>, Without<Tree>, Without<TheCore>)>,
+    q_mature_trees: Query<
+        (&Transform, &crate::creeps::TreeSpecies),
+        (With<Tree>, Without<Sapling>),
+    >,
+) {
+    if !timer.0.tick(time.delta()).just_finished() {
+        return;
+    }
+    let Some(event) = weather.active().cloned() else {
+        return;
+    };
+
+    match event.kind {
+        WeatherKind::Drought => {
+            let damage = (DROUGHT_DPT * event.intensity).round() as u32;
+            q_saplings.iter_mut().for_each(|mut hp| hp.deduct(damage));
+        }
+        WeatherKind::AcidRain => {
+            let damage = (ACID_RAIN_DPT * event.intensity).round() as u32;
+            q_buildings.iter_mut().for_each(|mut hp| hp.deduct(damage));
+        }
+        WeatherKind::Storm => {
+            let chance = STORM_SCATTER_CHANCE * event.intensity as f64;
+            // not the forecast's rng, how many trees there are to roll for differs every run
+            let rng = &mut rand::thread_rng();
+            q_mature_trees.iter().for_each(|(tree_tf, species)| {
+                if rng.gen_bool(chance) {
+                    spawn_seed(&mut commands, tree_tf.translation.truncate(), *species, rng);
+                }
+            });
+        }
+        WeatherKind::PollenBlizzard => {
+            // handled through [Weather::modify]
+        }
+    }
+}
+
+#[derive(Component)]
+struct WeatherUI;
+
+#[derive(Component)]
+struct WeatherText;
+
+fn setup_ui(mut commands: Commands, font: Res<GameFont>) {
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    width: Val::Percent(100.0),
+                    justify_content: JustifyContent::Center,
+                    ..default()
+                },
+                z_index: ZIndex::Global(i32::MAX - 1),
+                ..default()
+            },
+            WeatherUI,
+        ))
+        .with_children(|parent| {
+            parent.spawn((
+                TextBundle::from_section(
+                    "",
+                    TextStyle {
+                        font_size: 32.0,
+                        font: font.0.clone(),
+                        color: Color::ORANGE,
+                    },
+                )
+                .with_style(Style {
+                    margin: UiRect::all(Val::Px(15.)),
+                    ..default()
+                }),
+                WeatherText,
+            ));
+        });
+}
+
+fn update_ui(weather: Res<Weather>, mut q_text: Query<&mut Text, With<WeatherText>>) {
+    let alert = match &weather.phase {
+        WeatherPhase::Calm(_) => String::new(),
+        WeatherPhase::Forecast(event, timer) => format!(
+            "WARNING: {} incoming in {:.0}s",
+            event.name,
+            timer.remaining_secs()
+        ),
+        WeatherPhase::Active(event, timer) => {
+            format!("{}! {:.0}s remaining", event.name, timer.remaining_secs())
+        }
+    };
+
+    for mut text in &mut q_text {
+        text.sections[0].value = alert.clone();
+    }
+}
+
+fn teardown_ui(mut commands: Commands, nodes: Query<Entity, With<WeatherUI>>) {
+    for ent in &nodes {
+        commands.entity(ent).despawn_recursive();
+    }
+}
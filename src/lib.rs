@@ -1,7 +1,7 @@
 #![allow(clippy::type_complexity)]
 #![allow(unused_imports, dead_code)]
 use bevy::{
-    ecs::{component::Component, schedule::States},
+    ecs::{component::Component, entity::Entity, schedule::States, system::Resource},
     prelude::{Deref, DerefMut, Event},
     time::Timer,
 };
@@ -14,7 +14,7 @@ pub mod global_systems;
 pub mod scenes;
 
 pub mod prelude {
-    pub use crate::{AttackSpeed, CorpoPoints, Health, MovementSpeed, Tree};
+    pub use crate::{AttackSpeed, CorpoPoints, Health, MaxHealth, MovementSpeed, Tree};
 }
 
 /// Top-level states that the game can be in
@@ -26,6 +26,8 @@ pub enum AppState {
     Gameplay,
     DevScene,
     GameOver,
+    /// Painting terrain, forests and spawn zones for maps that can then be played
+    MapEditor,
 }
 
 #[derive(States, Debug, Clone, Copy, Eq, PartialEq, Hash, Default)]
@@ -45,6 +47,43 @@ const PLACEMENT_Z: f32 = 0.5;
 #[derive(Event)]
 pub struct GameOver;
 
+/// Seed for everything that should play out the same way given the same game, like the weather
+#[derive(Resource, Clone, Copy, Debug)]
+pub struct GameSeed(pub u64);
+impl Default for GameSeed {
+    fn default() -> Self {
+        Self(rand::random())
+    }
+}
+
+/// How hard the game is, picked on the main menu and kept between games
+#[derive(Resource, Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum Difficulty {
+    Easy,
+    #[default]
+    Normal,
+    Hard,
+}
+
+impl Difficulty {
+    pub fn next(&self) -> Self {
+        match self {
+            Difficulty::Easy => Difficulty::Normal,
+            Difficulty::Normal => Difficulty::Hard,
+            Difficulty::Hard => Difficulty::Easy,
+        }
+    }
+
+    /// Radius of the area around [buildings::core::TheCore] the initial forest leaves clear
+    pub fn core_clearing_radius(&self) -> f32 {
+        match self {
+            Difficulty::Easy => 560.0,
+            Difficulty::Normal => 400.0,
+            Difficulty::Hard => 240.0,
+        }
+    }
+}
+
 /// Marker component indicating an entity needs to be torn down (destroyed) when going
 /// from [AppState::Gameplay] or [AppState::Paused] back to [AppState::MainMenu]
 #[derive(Component)]
@@ -56,8 +95,10 @@ pub struct Tree;
 #[derive(Component)]
 pub struct MovementSpeed(u32);
 
-#[derive(Component)]
-pub struct Targeting(usize); //TODO: Entity id, for .get()s of an 'n' of 1
+/// What something is currently going after, so its targets can be looked up with `.get()`s
+/// rather than searched for again
+#[derive(Component, Default)]
+pub struct Targeting(pub Vec<Entity>);
 
 #[derive(Component)]
 pub struct AttackSpeed(usize);
@@ -69,8 +110,22 @@ impl Health {
     pub fn deduct(&mut self, value: u32) {
         self.0 = self.0.saturating_sub(value);
     }
+
+    /// Heals by `value`, never going over `max`
+    pub fn restore(&mut self, value: u32, max: &MaxHealth) {
+        self.0 = self.0.saturating_add(value).min(max.0);
+    }
+
+    /// How much is missing before it's back at `max`
+    pub fn missing(&self, max: &MaxHealth) -> u32 {
+        max.0.saturating_sub(self.0)
+    }
 }
 
+/// The most [Health] something can be repaired back up to
+#[derive(Component)]
+pub struct MaxHealth(pub u32);
+
 /// The game's currency
 #[derive(Component)]
 pub struct CorpoPoints(u32);
@@ -15,8 +15,8 @@ use flora_cause::{
         eargasm::EargasmPlugin, fade_transition::TransitionPlugin, ui_util::UIUtilPlugin,
     },
     scenes::{
-        game_over::GameOverPlugin, gameplay::GameplayPlugin, menu::MainMenuPlugin,
-        pause::PausePlugin, splash::SplashPlugin,
+        game_over::GameOverPlugin, gameplay::GameplayPlugin, map_editor::MapEditorPlugin,
+        menu::MainMenuPlugin, pause::PausePlugin, splash::SplashPlugin,
     },
     AppState, PauseMenuState,
 };
@@ -49,6 +49,7 @@ fn main() {
         MainMenuPlugin,
         PausePlugin,
         GameOverPlugin,
+        MapEditorPlugin,
     ))
     .add_state::<AppState>()
     .add_state::<PauseMenuState>()
@@ -3,16 +3,21 @@ use bevy_ecs_tilemap::TilemapPlugin;
 
 use crate::{
     buildings::{
-        core::TheCorePlugin, distribution::DistributionTowerPlugin, drain::DrainTowerPlugin,
-        twr_custom_mats::TowerRadiusMaterial, Building,
+        barrier::BarrierPlugin, core::TheCorePlugin, distribution::DistributionTowerPlugin,
+        drain::DrainTowerPlugin, repair::RepairPlugin, roboport::RoboportPlugin,
+        synergy::SynergyPlugin, turrets::TurretsPlugin, twr_custom_mats::TowerRadiusMaterial,
+        Building, BuildingsPlugin,
     },
     creeps::CreepPlugin,
     game::{
-        camera::GameCameraPlugin, depletion::DepletionPlugin, hp_bars::HealthBarUIPlugin,
-        hud::HudPlugin, map::MapPlugin, placement::TowerPlacementPlugin, power::PowerPlugin,
-        resources::ResourcePlugin,
+        blueprints::BlueprintPlugin, build_queue::BuildQueuePlugin, camera::GameCameraPlugin,
+        clock::WorldClockPlugin, contracts::ContractsPlugin, depletion::DepletionPlugin,
+        hp_bars::HealthBarUIPlugin, hud::HudPlugin, inspector::InspectorPlugin,
+        logistics::LogisticsPlugin, map::MapPlugin, occupancy::OccupancyPlugin,
+        placement::TowerPlacementPlugin, power::PowerPlugin, research::ResearchPlugin,
+        resources::ResourcePlugin, weather::WeatherPlugin,
     },
-    AppState, Teardown,
+    AppState, GameSeed, Teardown,
 };
 
 use super::pause::{capture_cursor, check_for_keyboard_pause, release_cursor, PausePlugin};
@@ -37,6 +42,24 @@ impl Plugin for GameplayPlugin {
             HudPlugin,
             Material2dPlugin::<TowerRadiusMaterial>::default(),
         ))
+        .add_plugins((
+            WorldClockPlugin,
+            WeatherPlugin,
+            BuildingsPlugin,
+            LogisticsPlugin,
+            RoboportPlugin,
+            ContractsPlugin,
+            ResearchPlugin,
+            TurretsPlugin,
+            BarrierPlugin,
+            RepairPlugin,
+            InspectorPlugin,
+            BlueprintPlugin,
+            BuildQueuePlugin,
+            OccupancyPlugin,
+            SynergyPlugin,
+        ))
+        .init_resource::<GameSeed>()
         .add_systems(OnEnter(AppState::Gameplay), capture_cursor)
         .add_systems(
             Update,
@@ -46,7 +69,7 @@ impl Plugin for GameplayPlugin {
     }
 }
 
-fn teardown_all(mut commands: Commands, to_teardown: Query<Entity, With<Teardown>>) {
+pub fn teardown_all(mut commands: Commands, to_teardown: Query<Entity, With<Teardown>>) {
     info!("Tearing down all gameplay entities!!!");
     to_teardown.iter().for_each(|entity| {
         commands.entity(entity).despawn_recursive();
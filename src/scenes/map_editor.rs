@@ -0,0 +1,491 @@
+//! In-game map editor. Reuses the gameplay camera and tile hovering to paint terrain, forests,
+//! the core location and tree spawn zones onto the [MapData] that gameplay starts from.
+//!
+//! Maps saved here can only be played from the editor, loading one and playing it. Starting a
+//! game from the main menu always uses the generated map.
+
+use bevy::{prelude::*, utils::HashSet};
+use bevy_ecs_tilemap::prelude::*;
+use rand::Rng;
+
+use crate::{
+    creeps::{tree_atlas, TreeSpecies},
+    game::{
+        keybinds::FloraCommand,
+        map::{
+            spawn_tilemap, CurrentTileHover, MapData, MapTree, SpawnZone, TerrainTileType,
+            TerrainTilemap, WorldBounds, TILE_SIZE, TILE_SIZE_2D,
+        },
+    },
+    global_systems::{
+        fade_transition::{transition_to, TransitionState},
+        ui_util::GameFont,
+    },
+    AppState, Teardown, BUILDING_Z, CREEP_Z,
+};
+
+use super::gameplay::teardown_all;
+
+/// Where the editor saves maps to and loads them from
+const MAP_FILE_PATH: &str = "assets/maps/custom.ron";
+/// How many strokes can be undone
+const UNDO_LIMIT: usize = 32;
+/// Largest brush radius, in tiles
+const BRUSH_MAX: u32 = 16;
+/// Chance for each empty tile under the brush to get a tree, per frame of forest painting
+const FOREST_DENSITY: f64 = 0.05;
+
+pub struct MapEditorPlugin;
+impl Plugin for MapEditorPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<EditorState>()
+            .add_systems(OnEnter(AppState::MapEditor), (setup_ui, reset_editor))
+            .add_systems(OnExit(AppState::MapEditor), (teardown_ui, teardown_all))
+            .add_systems(
+                Update,
+                (
+                    select_tool,
+                    paint,
+                    undo,
+                    save_load,
+                    leave_editor,
+                    sync_markers,
+                    draw_overlays,
+                    update_ui,
+                )
+                    .chain()
+                    .run_if(in_state(AppState::MapEditor)),
+            );
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+enum EditorTool {
+    Paint(TerrainTileType),
+    Fill(TerrainTileType),
+    Forest(TreeSpecies),
+    Erase,
+    Core,
+    SpawnZone,
+}
+
+#[derive(Resource)]
+struct EditorState {
+    tool: EditorTool,
+    /// Radius of the brush in tiles, 0 is a single tile
+    brush: u32,
+    /// Snapshots of the map taken at the start of every stroke
+    history: Vec<MapData>,
+    /// Trees/core moved, the marker sprites need respawning
+    markers_dirty: bool,
+}
+
+impl Default for EditorState {
+    fn default() -> Self {
+        Self {
+            tool: EditorTool::Paint(TerrainTileType::Grass),
+            brush: 1,
+            history: Vec::new(),
+            markers_dirty: true,
+        }
+    }
+}
+
+impl EditorState {
+    fn push_history(&mut self, snapshot: MapData) {
+        if self.history.len() == UNDO_LIMIT {
+            self.history.remove(0);
+        }
+        self.history.push(snapshot);
+    }
+}
+
+/// Sprites standing in for the trees and core while editing
+#[derive(Component)]
+struct EditorMarker;
+
+#[derive(Component)]
+struct EditorUI;
+
+#[derive(Component)]
+struct EditorToolText;
+
+fn reset_editor(mut state: ResMut<EditorState>) {
+    *state = EditorState::default();
+}
+
+fn select_tool(mut state: ResMut<EditorState>, input: Res<Input<FloraCommand>>) {
+    let terrain_keys = [
+        (FloraCommand::EditorTerrainDirt, TerrainTileType::Dirt),
+        (FloraCommand::EditorTerrainGrass, TerrainTileType::Grass),
+        (FloraCommand::EditorTerrainRock, TerrainTileType::Rock),
+        (FloraCommand::EditorTerrainSand, TerrainTileType::Sand),
+    ];
+    for (command, terrain) in terrain_keys {
+        if input.just_pressed(command) {
+            state.tool = match state.tool {
+                EditorTool::Fill(_) => EditorTool::Fill(terrain),
+                _ => EditorTool::Paint(terrain),
+            };
+        }
+    }
+
+    if input.just_pressed(FloraCommand::EditorFill) {
+        state.tool = match state.tool {
+            EditorTool::Paint(terrain) => EditorTool::Fill(terrain),
+            EditorTool::Fill(terrain) => EditorTool::Paint(terrain),
+            _ => EditorTool::Fill(TerrainTileType::Grass),
+        };
+    }
+    if input.just_pressed(FloraCommand::EditorForest) {
+        // pressing it again cycles through the species
+        state.tool = match state.tool {
+            EditorTool::Forest(species) => {
+                let idx = TreeSpecies::ALL.iter().position(|s| *s == species);
+                let next = idx.map_or(0, |idx| (idx + 1) % TreeSpecies::ALL.len());
+                EditorTool::Forest(TreeSpecies::ALL[next])
+            }
+            _ => EditorTool::Forest(TreeSpecies::default()),
+        };
+    }
+    if input.just_pressed(FloraCommand::EditorErase) {
+        state.tool = EditorTool::Erase;
+    }
+    if input.just_pressed(FloraCommand::EditorCore) {
+        state.tool = EditorTool::Core;
+    }
+    if input.just_pressed(FloraCommand::EditorSpawnZone) {
+        state.tool = EditorTool::SpawnZone;
+    }
+
+    if input.just_pressed(FloraCommand::EditorBrushGrow) {
+        state.brush = (state.brush + 1).min(BRUSH_MAX);
+    }
+    if input.just_pressed(FloraCommand::EditorBrushShrink) {
+        state.brush = state.brush.saturating_sub(1);
+    }
+}
+
+/// Applies the current tool under the cursor while the left mouse button is held
+fn paint(
+    mut state: ResMut<EditorState>,
+    mut map: ResMut<MapData>,
+    mouse_btns: Res<Input<MouseButton>>,
+    tile_hover: Res<CurrentTileHover>,
+    q_tilemap: Query<&TileStorage, With<TerrainTilemap>>,
+    mut q_tiles: Query<&mut TileTextureIndex>,
+) {
+    let Some(hovered) = tile_hover.tile_pos else {
+        return;
+    };
+    if !mouse_btns.pressed(MouseButton::Left) {
+        return;
+    }
+
+    let stroke_started = mouse_btns.just_pressed(MouseButton::Left);
+    if stroke_started {
+        let snapshot = map.clone();
+        state.push_history(snapshot);
+    }
+
+    let brush_tiles = map.tiles_in_radius(&hovered, state.brush);
+    let repainted = match state.tool {
+        EditorTool::Paint(terrain) => {
+            let changed: Vec<_> = brush_tiles
+                .into_iter()
+                .filter(|tile| map.terrain(tile) != terrain)
+                .collect();
+            changed
+                .iter()
+                .for_each(|tile| map.set_terrain(tile, terrain));
+            changed
+        }
+        EditorTool::Fill(terrain) if stroke_started => map.flood_fill(&hovered, terrain),
+        EditorTool::Fill(_) => Vec::new(),
+        EditorTool::Forest(species) => {
+            let mut rng = rand::thread_rng();
+            let occupied: HashSet<TilePos> = map.trees.iter().map(|tree| tree.tile).collect();
+            let new_trees: Vec<_> = brush_tiles
+                .into_iter()
+                .filter(|tile| !occupied.contains(tile) && rng.gen_bool(FOREST_DENSITY))
+                .map(|tile| MapTree { tile, species })
+                .collect();
+
+            state.markers_dirty |= !new_trees.is_empty();
+            map.trees.extend(new_trees);
+            Vec::new()
+        }
+        EditorTool::Erase => {
+            let brush: HashSet<TilePos> = brush_tiles.into_iter().collect();
+            let (trees, zones) = (map.trees.len(), map.spawn_zones.len());
+            map.trees.retain(|tree| !brush.contains(&tree.tile));
+            map.spawn_zones.retain(|zone| !brush.contains(&zone.center));
+
+            state.markers_dirty |= trees != map.trees.len() || zones != map.spawn_zones.len();
+            Vec::new()
+        }
+        EditorTool::Core => {
+            if stroke_started {
+                map.core = hovered;
+                state.markers_dirty = true;
+            }
+            Vec::new()
+        }
+        EditorTool::SpawnZone => {
+            if stroke_started {
+                let radius = state.brush.max(1);
+                map.spawn_zones.push(SpawnZone {
+                    center: hovered,
+                    radius,
+                });
+            }
+            Vec::new()
+        }
+    };
+
+    if let Ok(storage) = q_tilemap.get_single() {
+        repaint_tiles(&repainted, &map, storage, &mut q_tiles);
+    }
+}
+
+fn repaint_tiles(
+    tiles: &[TilePos],
+    map: &MapData,
+    storage: &TileStorage,
+    q_tiles: &mut Query<&mut TileTextureIndex>,
+) {
+    tiles.iter().for_each(|tile| {
+        if let Some(Ok(mut texture)) = storage.get(tile).map(|ent| q_tiles.get_mut(ent)) {
+            texture.0 = map.terrain(tile).texture_index();
+        }
+    });
+}
+
+fn all_tiles(map: &MapData) -> Vec<TilePos> {
+    (0..map.size.y)
+        .flat_map(|y| (0..map.size.x).map(move |x| TilePos { x, y }))
+        .collect()
+}
+
+fn undo(
+    mut state: ResMut<EditorState>,
+    mut map: ResMut<MapData>,
+    input: Res<Input<FloraCommand>>,
+    q_tilemap: Query<&TileStorage, With<TerrainTilemap>>,
+    mut q_tiles: Query<&mut TileTextureIndex>,
+) {
+    if !input.just_pressed(FloraCommand::EditorUndo) {
+        return;
+    }
+    let Some(previous) = state.history.pop() else {
+        info!("Nothing to undo");
+        return;
+    };
+
+    *map = previous;
+    state.markers_dirty = true;
+    if let Ok(storage) = q_tilemap.get_single() {
+        repaint_tiles(&all_tiles(&map), &map, storage, &mut q_tiles);
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn save_load(
+    mut commands: Commands,
+    mut state: ResMut<EditorState>,
+    mut map: ResMut<MapData>,
+    mut bounds: ResMut<WorldBounds>,
+    input: Res<Input<FloraCommand>>,
+    asset_server: Res<AssetServer>,
+    q_tilemap: Query<(Entity, &TileStorage), With<TerrainTilemap>>,
+) {
+    if input.just_pressed(FloraCommand::EditorSave) {
+        #[cfg(not(target_arch = "wasm32"))]
+        match map.save(MAP_FILE_PATH) {
+            Ok(()) => info!("Saved map to {MAP_FILE_PATH}"),
+            Err(e) => error!("Failed to save map to {MAP_FILE_PATH}: {e}"),
+        }
+        #[cfg(target_arch = "wasm32")]
+        warn!("Saving maps isn't supported in the browser");
+    }
+
+    if input.just_pressed(FloraCommand::EditorLoad) {
+        #[cfg(not(target_arch = "wasm32"))]
+        match MapData::load(MAP_FILE_PATH) {
+            Ok(loaded) => {
+                info!("Loaded map from {MAP_FILE_PATH}");
+                let snapshot = map.clone();
+                state.push_history(snapshot);
+                *map = loaded;
+                state.markers_dirty = true;
+
+                // the loaded map may be a different size, so rebuild the tilemap from scratch
+                q_tilemap.iter().for_each(|(tilemap, storage)| {
+                    storage
+                        .iter()
+                        .flatten()
+                        .for_each(|tile| commands.entity(*tile).despawn());
+                    commands.entity(tilemap).despawn_recursive();
+                });
+                spawn_tilemap(&mut commands, &asset_server, &map);
+                *bounds = WorldBounds::from_map_size(&map.size, &TILE_SIZE_2D);
+            }
+            Err(e) => error!("Failed to load map from {MAP_FILE_PATH}: {e}"),
+        }
+        #[cfg(target_arch = "wasm32")]
+        warn!("Loading maps isn't supported in the browser");
+    }
+}
+
+/// Play the map as it is, or go back to the main menu
+fn leave_editor(input: Res<Input<FloraCommand>>, mut transition_state: ResMut<TransitionState>) {
+    if input.just_pressed(FloraCommand::EditorPlay) {
+        transition_to(AppState::Gameplay, &mut transition_state);
+    }
+    if input.just_pressed(FloraCommand::Pause) {
+        transition_to(AppState::MainMenu, &mut transition_state);
+    }
+}
+
+fn sync_markers(
+    mut commands: Commands,
+    mut state: ResMut<EditorState>,
+    map: Res<MapData>,
+    asset_server: Res<AssetServer>,
+    mut texture_atlases: ResMut<Assets<TextureAtlas>>,
+    q_markers: Query<Entity, With<EditorMarker>>,
+) {
+    if !state.markers_dirty {
+        return;
+    }
+    state.markers_dirty = false;
+
+    q_markers
+        .iter()
+        .for_each(|ent| commands.entity(ent).despawn_recursive());
+
+    let mut rng = rand::thread_rng();
+    let atlas = tree_atlas(&asset_server, &mut texture_atlases);
+    map.trees.iter().for_each(|tree| {
+        let pos = map.tile_center(&tree.tile);
+        commands.spawn((
+            SpriteSheetBundle {
+                texture_atlas: atlas.clone(),
+                sprite: TextureAtlasSprite::new(tree.species.sprite_index(&mut rng)),
+                transform: Transform::from_xyz(pos.x, pos.y, CREEP_Z),
+                ..default()
+            },
+            EditorMarker,
+            Teardown,
+        ));
+    });
+
+    let core_pos = map.core_world_pos();
+    commands.spawn((
+        SpriteBundle {
+            texture: asset_server.load("textures/core.png"),
+            transform: Transform::from_xyz(core_pos.x, core_pos.y, BUILDING_Z),
+            ..default()
+        },
+        EditorMarker,
+        Teardown,
+    ));
+}
+
+/// Outlines the brush and the spawn zones
+fn draw_overlays(
+    mut gizmos: Gizmos,
+    state: Res<EditorState>,
+    map: Res<MapData>,
+    tile_hover: Res<CurrentTileHover>,
+) {
+    map.spawn_zones.iter().for_each(|zone| {
+        gizmos
+            .circle_2d(
+                map.tile_center(&zone.center),
+                zone.radius as f32 * TILE_SIZE,
+                Color::ORANGE_RED,
+            )
+            .segments(48);
+    });
+
+    if let Some(tile) = tile_hover.tile_pos {
+        let radius = (state.brush as f32 + 0.5) * TILE_SIZE;
+        gizmos
+            .circle_2d(map.tile_center(&tile), radius, Color::WHITE)
+            .segments(32);
+    }
+}
+
+fn setup_ui(mut commands: Commands, font: Res<GameFont>) {
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    width: Val::Percent(100.0),
+                    height: Val::Percent(100.0),
+                    align_items: AlignItems::FlexStart,
+                    justify_content: JustifyContent::FlexStart,
+                    ..default()
+                },
+                z_index: ZIndex::Global(i32::MAX - 1),
+                ..default()
+            },
+            EditorUI,
+        ))
+        .with_children(|parent| {
+            parent.spawn((
+                TextBundle::from_section(
+                    "",
+                    TextStyle {
+                        font_size: 24.0,
+                        font: font.0.clone(),
+                        color: Color::WHITE,
+                    },
+                )
+                .with_style(Style {
+                    margin: UiRect::all(Val::Px(15.)),
+                    ..default()
+                }),
+                EditorToolText,
+            ));
+        });
+}
+
+fn update_ui(
+    state: Res<EditorState>,
+    map: Res<MapData>,
+    mut q_text: Query<&mut Text, With<EditorToolText>>,
+) {
+    if !(state.is_changed() || map.is_changed()) {
+        return;
+    }
+
+    let tool = match state.tool {
+        EditorTool::Paint(terrain) => format!("Paint {terrain:?}"),
+        EditorTool::Fill(terrain) => format!("Fill {terrain:?}"),
+        EditorTool::Forest(species) => format!("Forest ({species:?})"),
+        EditorTool::Erase => "Erase trees & zones".to_string(),
+        EditorTool::Core => "Place core".to_string(),
+        EditorTool::SpawnZone => "Spawn zone".to_string(),
+    };
+
+    for mut text in &mut q_text {
+        text.sections[0].value = format!(
+            "MAP EDITOR\n\
+            Tool: {tool}   Brush: {}   Trees: {}   Undo: {}\n\
+            [1-4] terrain  [F] fill  [T] forest  [E] erase  [H] core  [X] spawn zone\n\
+            [ / ] brush size  [Ctrl+Z] undo  [Ctrl+S] save  [Ctrl+L] load\n\
+            [Enter] play  [Esc] main menu",
+            state.brush,
+            map.trees.len(),
+            state.history.len(),
+        );
+    }
+}
+
+fn teardown_ui(mut commands: Commands, nodes: Query<Entity, With<EditorUI>>) {
+    for ent in &nodes {
+        commands.entity(ent).despawn_recursive();
+    }
+}
@@ -6,13 +6,16 @@ use bevy::{
 };
 
 use crate::{
-    game::camera::{main_layer, rt_cam3d, v3d_layer, UiCamera},
+    game::{
+        camera::{main_layer, rt_cam3d, v3d_layer, UiCamera},
+        map::MapData,
+    },
     global_systems::{
         eargasm::{AudioComponent, AudioRequest, IntroVoice, TheCompanyThanksYou},
         fade_transition::{transition_to, TransitionState},
         ui_util::{btn, img, txt, GameFont},
     },
-    AppState, PauseMenuState,
+    AppState, Difficulty, GameSeed, PauseMenuState,
 };
 
 use super::pause::release_cursor;
@@ -20,7 +23,8 @@ use super::pause::release_cursor;
 pub struct MainMenuPlugin;
 impl Plugin for MainMenuPlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(OnEnter(AppState::MainMenu), (setup, release_cursor))
+        app.init_resource::<Difficulty>()
+            .add_systems(OnEnter(AppState::MainMenu), (setup, release_cursor))
             .add_systems(OnExit(AppState::MainMenu), teardown)
             .add_systems(
                 Update,
@@ -32,6 +36,8 @@ impl Plugin for MainMenuPlugin {
 #[derive(Component)]
 enum Action {
     StartGame,
+    CycleDifficulty,
+    MapEditor,
     DevScene,
     QuitGame,
     Credits,
@@ -48,21 +54,41 @@ struct DemoCube;
 
 /// React to button presses
 fn interact(
-    interaction_query: Query<(&Interaction, &Action), (Changed<Interaction>, With<Button>)>,
+    mut commands: Commands,
+    interaction_query: Query<
+        (&Interaction, &Action, &Children),
+        (Changed<Interaction>, With<Button>),
+    >,
+    mut q_text: Query<&mut Text>,
+    mut difficulty: ResMut<Difficulty>,
     mut app_exit_events: EventWriter<AppExit>,
     // mut app_state: ResMut<NextState<AppState>>,
     mut transition_state: ResMut<TransitionState>,
     mut audio_mngr: EventWriter<AudioRequest>,
 ) {
-    for (interaction, menu_button_action) in &interaction_query {
+    for (interaction, menu_button_action, children) in &interaction_query {
         if *interaction == Interaction::Pressed {
             match menu_button_action {
                 Action::StartGame => {
+                    // a fresh game is always played on the generated map
+                    commands.insert_resource(MapData::generated());
+                    commands.insert_resource(GameSeed::default());
                     transition_to(AppState::Gameplay, &mut transition_state);
                     audio_mngr.send(AudioRequest {
                         component: AudioComponent::IntroVoice(IntroVoice),
                     });
                 }
+                Action::CycleDifficulty => {
+                    *difficulty = difficulty.next();
+                    for child in children {
+                        if let Ok(mut text) = q_text.get_mut(*child) {
+                            text.sections[0].value = difficulty_label(*difficulty);
+                        }
+                    }
+                }
+                Action::MapEditor => {
+                    transition_to(AppState::MapEditor, &mut transition_state);
+                }
                 Action::DevScene => {
                     // app_state.set(AppState::DevScene);
                 }
@@ -83,7 +109,12 @@ fn interact(
     }
 }
 
+fn difficulty_label(difficulty: Difficulty) -> String {
+    format!("Difficulty: {:?}", difficulty)
+}
+
 /// Runs when we enter [AppState::MainMenu]
+#[allow(clippy::too_many_arguments)]
 fn setup(
     mut commands: Commands,
     mut images: ResMut<Assets<Image>>,
@@ -92,6 +123,7 @@ fn setup(
     font: Res<GameFont>,
     mut paused: ResMut<NextState<PauseMenuState>>,
     asset_server: Res<AssetServer>,
+    difficulty: Res<Difficulty>,
 ) {
     paused.set(PauseMenuState::Unpaused);
 
@@ -176,6 +208,13 @@ fn setup(
         None,
     );
     let start_button = btn(&mut commands, &font, "Start Game", Action::StartGame);
+    let difficulty_button = btn(
+        &mut commands,
+        &font,
+        &difficulty_label(*difficulty),
+        Action::CycleDifficulty,
+    );
+    let editor_button = btn(&mut commands, &font, "Map Editor", Action::MapEditor);
     // let gpu_test = btn(&mut commands, &font, "Dev Scene", Action::DevScene);
 
     #[cfg(not(target_arch = "wasm32"))]
@@ -204,7 +243,10 @@ fn setup(
                 },
                 ..default()
             });
-            cb.add_child(title).add_child(start_button);
+            cb.add_child(title)
+                .add_child(start_button)
+                .add_child(difficulty_button)
+                .add_child(editor_button);
             // .add_child(gpu_test);
 
             #[cfg(not(target_arch = "wasm32"))]
@@ -1,5 +1,6 @@
 pub mod game_over;
 pub mod gameplay;
+pub mod map_editor;
 pub mod menu;
 pub mod pause;
 pub mod splash;